@@ -45,18 +45,49 @@ pub struct AppConfig {
     pub database_file: String,
     pub storage: StorageConfig,
     pub video_transcoding: VideoTranscodingConfig,
+    /// Without it, password resets and notifications are disabled.
+    #[serde(default)]
+    pub mail: Option<MailConfig>,
     #[serde(default)]
     pub env: AppEnv,
 }
 
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    #[default]
+    Azure,
+    Local,
+    S3,
+}
+
 #[derive(Clone, Debug, Deserialize)]
 pub struct StorageConfig {
+    #[serde(default)]
+    pub backend: StorageBackend,
     pub container_media: String,
+    #[serde(default)]
     pub azure_storage_account: String,
+    #[serde(default)]
     pub azure_storage_access_key: String,
     pub azure_storage_endpoint: Option<String>,
     #[serde(default)]
     pub emulator: bool,
+    /// Root directory for the local backend.
+    #[serde(default)]
+    pub local_dir: Option<String>,
+    /// Key used to sign URLs for the local backend.
+    #[serde(default)]
+    pub local_secret: Option<String>,
+    /// Only required for S3-compatible services other than AWS, e.g. MinIO.
+    #[serde(default)]
+    pub s3_endpoint: Option<String>,
+    #[serde(default)]
+    pub s3_region: Option<String>,
+    #[serde(default)]
+    pub s3_access_key_id: Option<String>,
+    #[serde(default)]
+    pub s3_secret_access_key: Option<String>,
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -69,6 +100,37 @@ pub struct VideoTranscodingConfig {
     #[serde(default)]
     pub github_token: Option<String>,
     pub github_client_token: Option<String>,
+    /// Attempts before a task failing with transient errors is marked as failed.
+    #[serde(default)]
+    pub max_attempts: Option<i32>,
+    /// Minutes to wait for the callback of a delayed backend before dispatching again.
+    #[serde(default)]
+    pub dispatch_timeout_minutes: Option<i64>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum MailBackend {
+    Smtp,
+    /// Drops emails in [`MailConfig::file_dir`], for development.
+    File,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct MailConfig {
+    pub backend: MailBackend,
+    /// Sender address, e.g. `Cookie Odyssey <noreply@example.com>`.
+    pub from: String,
+    #[serde(default)]
+    pub file_dir: Option<String>,
+    #[serde(default)]
+    pub smtp_host: Option<String>,
+    #[serde(default)]
+    pub smtp_port: Option<u16>,
+    #[serde(default)]
+    pub smtp_username: Option<String>,
+    #[serde(default)]
+    pub smtp_password: Option<String>,
 }
 
 impl AppConfig {
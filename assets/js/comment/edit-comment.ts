@@ -8,7 +8,7 @@ export class EditCommentController extends TypedController(
     targets: {
       editButton: "button",
       submit: "button",
-      view: "p",
+      view: "div",
       editForm: "form",
       editTextarea: "textarea",
       cancelButton: "button",
@@ -53,7 +53,8 @@ export class EditCommentController extends TypedController(
     } else {
       jsUtils.show($view);
       jsUtils.hide($editForm);
-      $editTextarea.value = $view.textContent ?? "";
+      // The view is rendered Markdown, so reset from the original source instead.
+      $editTextarea.value = $editTextarea.defaultValue;
       $editTextarea.focus();
     }
   }
@@ -10,12 +10,16 @@ pub struct Model {
     pub id: i32,
     pub bucket: String,
     pub key: String,
+    /// Only set for browser uploads, through `media_upload_url_post`.
+    pub uploaded_by: Option<i32>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
     #[sea_orm(has_many = "super::journal::Entity")]
     Journal,
+    #[sea_orm(has_many = "super::journal_track::Entity")]
+    JournalTrack,
     #[sea_orm(has_many = "super::video_transcode_task::Entity")]
     VideoTranscodeTask,
 }
@@ -26,6 +30,12 @@ impl Related<super::journal::Entity> for Entity {
     }
 }
 
+impl Related<super::journal_track::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::JournalTrack.def()
+    }
+}
+
 impl Related<super::video_transcode_task::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::VideoTranscodeTask.def()
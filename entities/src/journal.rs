@@ -31,6 +31,16 @@ pub enum Relation {
     JournalComment,
     #[sea_orm(has_many = "super::journal_entry::Entity")]
     JournalEntry,
+    #[sea_orm(has_many = "super::journal_member::Entity")]
+    JournalMember,
+    #[sea_orm(has_many = "super::journal_slug_redirect::Entity")]
+    JournalSlugRedirect,
+    #[sea_orm(has_many = "super::journal_subscription::Entity")]
+    JournalSubscription,
+    #[sea_orm(has_many = "super::journal_track::Entity")]
+    JournalTrack,
+    #[sea_orm(has_many = "super::notification::Entity")]
+    Notification,
 }
 
 impl Related<super::file::Entity> for Entity {
@@ -51,4 +61,34 @@ impl Related<super::journal_entry::Entity> for Entity {
     }
 }
 
+impl Related<super::journal_member::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::JournalMember.def()
+    }
+}
+
+impl Related<super::journal_slug_redirect::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::JournalSlugRedirect.def()
+    }
+}
+
+impl Related<super::journal_subscription::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::JournalSubscription.def()
+    }
+}
+
+impl Related<super::journal_track::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::JournalTrack.def()
+    }
+}
+
+impl Related<super::notification::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Notification.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
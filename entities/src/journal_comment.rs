@@ -25,6 +25,8 @@ pub enum Relation {
         on_delete = "NoAction"
     )]
     Journal,
+    #[sea_orm(has_many = "super::notification::Entity")]
+    Notification,
     #[sea_orm(
         belongs_to = "super::user::Entity",
         from = "Column::UserId",
@@ -41,6 +43,12 @@ impl Related<super::journal::Entity> for Entity {
     }
 }
 
+impl Related<super::notification::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Notification.def()
+    }
+}
+
 impl Related<super::user::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::User.def()
@@ -19,6 +19,7 @@ pub struct Model {
     pub lat: Option<f32>,
     #[sea_orm(column_type = "Float", nullable)]
     pub lng: Option<f32>,
+    pub author_id: Option<i32>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -33,6 +34,16 @@ pub enum Relation {
     Journal,
     #[sea_orm(has_many = "super::journal_entry_media::Entity")]
     JournalEntryMedia,
+    #[sea_orm(has_many = "super::notification::Entity")]
+    Notification,
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::AuthorId",
+        to = "super::user::Column::Id",
+        on_update = "NoAction",
+        on_delete = "SetNull"
+    )]
+    User,
 }
 
 impl Related<super::journal::Entity> for Entity {
@@ -47,4 +58,16 @@ impl Related<super::journal_entry_media::Entity> for Entity {
     }
 }
 
+impl Related<super::notification::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Notification.def()
+    }
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
@@ -3,7 +3,7 @@
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
 #[sea_orm(table_name = "journal_entry_media")]
 pub struct Model {
     #[sea_orm(primary_key)]
@@ -19,6 +19,17 @@ pub struct Model {
     pub thumbnail_width: i32,
     pub thumbnail_height: i32,
     pub thumbnail_file_id: i32,
+    pub taken_at: Option<DateTime>,
+    #[sea_orm(column_type = "Float", nullable)]
+    pub lat: Option<f32>,
+    #[sea_orm(column_type = "Float", nullable)]
+    pub lng: Option<f32>,
+    pub camera_model: Option<String>,
+    pub orientation: Option<i32>,
+    #[sea_orm(column_type = "Double", nullable)]
+    pub duration_s: Option<f64>,
+    pub video_codec: Option<String>,
+    pub rotation: Option<i32>,
 }
 
 // KEEP ME
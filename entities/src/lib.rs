@@ -2,10 +2,17 @@
 
 pub mod prelude;
 
+pub mod feed_token;
 pub mod file;
 pub mod journal;
 pub mod journal_comment;
 pub mod journal_entry;
 pub mod journal_entry_media;
+pub mod journal_member;
+pub mod journal_slug_redirect;
+pub mod journal_subscription;
+pub mod journal_track;
+pub mod notification;
+pub mod password_reset_token;
 pub mod user;
 pub mod video_transcode_task;
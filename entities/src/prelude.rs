@@ -1,9 +1,16 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.12
 
+pub use super::feed_token::Entity as FeedToken;
 pub use super::file::Entity as File;
 pub use super::journal::Entity as Journal;
 pub use super::journal_comment::Entity as JournalComment;
 pub use super::journal_entry::Entity as JournalEntry;
 pub use super::journal_entry_media::Entity as JournalEntryMedia;
+pub use super::journal_member::Entity as JournalMember;
+pub use super::journal_slug_redirect::Entity as JournalSlugRedirect;
+pub use super::journal_subscription::Entity as JournalSubscription;
+pub use super::journal_track::Entity as JournalTrack;
+pub use super::notification::Entity as Notification;
+pub use super::password_reset_token::Entity as PasswordResetToken;
 pub use super::user::Entity as User;
 pub use super::video_transcode_task::Entity as VideoTranscodeTask;
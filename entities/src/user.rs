@@ -20,8 +20,26 @@ pub struct Model {
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_one = "super::feed_token::Entity")]
+    FeedToken,
     #[sea_orm(has_many = "super::journal_comment::Entity")]
     JournalComment,
+    #[sea_orm(has_many = "super::journal_entry::Entity")]
+    JournalEntry,
+    #[sea_orm(has_many = "super::journal_member::Entity")]
+    JournalMember,
+    #[sea_orm(has_many = "super::journal_subscription::Entity")]
+    JournalSubscription,
+    #[sea_orm(has_many = "super::notification::Entity")]
+    Notification,
+    #[sea_orm(has_many = "super::password_reset_token::Entity")]
+    PasswordResetToken,
+}
+
+impl Related<super::feed_token::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::FeedToken.def()
+    }
 }
 
 impl Related<super::journal_comment::Entity> for Entity {
@@ -30,4 +48,34 @@ impl Related<super::journal_comment::Entity> for Entity {
     }
 }
 
+impl Related<super::journal_entry::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::JournalEntry.def()
+    }
+}
+
+impl Related<super::journal_member::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::JournalMember.def()
+    }
+}
+
+impl Related<super::journal_subscription::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::JournalSubscription.def()
+    }
+}
+
+impl Related<super::notification::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Notification.def()
+    }
+}
+
+impl Related<super::password_reset_token::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PasswordResetToken.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
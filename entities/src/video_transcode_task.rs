@@ -14,6 +14,9 @@ pub struct Model {
     pub status: TaskStatus,
     pub detail: String,
     pub file_id: i32,
+    pub attempts: i32,
+    pub next_attempt_at: Option<DateTimeUtc>,
+    pub dispatched_at: Option<DateTimeUtc>,
 }
 
 // KEEP ME
@@ -23,6 +26,9 @@ pub struct Model {
 pub enum TaskStatus {
     #[sea_orm(string_value = "pending")]
     Pending,
+    /// Sent to a delayed backend, waiting for its callback.
+    #[sea_orm(string_value = "dispatched")]
+    Dispatched,
     #[sea_orm(string_value = "completed")]
     Completed,
     #[sea_orm(string_value = "failed")]
@@ -9,6 +9,18 @@ mod m20240526_203520_create_table_journal_comment;
 mod m20250520_001650_media_type;
 mod m20250622_181713_journal_entry_media_width_height_thumbnail;
 mod m20251007_205825_create_table_video_transcode_task;
+mod m20261018_000001_create_table_password_reset_token;
+mod m20261018_000002_create_table_journal_member;
+mod m20261018_000003_entry_author_file_uploader;
+mod m20261018_000004_create_fts_tables;
+mod m20261018_000005_create_table_journal_slug_redirect;
+mod m20261018_000006_journal_entry_media_exif;
+mod m20261018_000007_create_table_journal_track;
+mod m20261018_000008_create_table_feed_token;
+mod m20261018_000009_create_table_notification;
+mod m20261018_000010_video_transcode_task_retries;
+mod m20261018_000011_video_transcode_task_dispatched;
+mod m20261018_000012_journal_entry_media_video;
 
 pub struct Migrator;
 
@@ -25,6 +37,18 @@ impl MigratorTrait for Migrator {
             Box::new(m20250520_001650_media_type::Migration),
             Box::new(m20250622_181713_journal_entry_media_width_height_thumbnail::Migration),
             Box::new(m20251007_205825_create_table_video_transcode_task::Migration),
+            Box::new(m20261018_000001_create_table_password_reset_token::Migration),
+            Box::new(m20261018_000002_create_table_journal_member::Migration),
+            Box::new(m20261018_000003_entry_author_file_uploader::Migration),
+            Box::new(m20261018_000004_create_fts_tables::Migration),
+            Box::new(m20261018_000005_create_table_journal_slug_redirect::Migration),
+            Box::new(m20261018_000006_journal_entry_media_exif::Migration),
+            Box::new(m20261018_000007_create_table_journal_track::Migration),
+            Box::new(m20261018_000008_create_table_feed_token::Migration),
+            Box::new(m20261018_000009_create_table_notification::Migration),
+            Box::new(m20261018_000010_video_transcode_task_retries::Migration),
+            Box::new(m20261018_000011_video_transcode_task_dispatched::Migration),
+            Box::new(m20261018_000012_journal_entry_media_video::Migration),
         ]
     }
 }
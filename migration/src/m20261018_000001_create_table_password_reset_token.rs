@@ -0,0 +1,50 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20240512_173332_create_table_user::User;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(PasswordResetToken::Table)
+                    .if_not_exists()
+                    .col(pk_auto(PasswordResetToken::Id))
+                    .col(integer(PasswordResetToken::UserId))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(PasswordResetToken::Table, PasswordResetToken::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    // Only the hash is stored, so a leaked database can't be used to reset passwords.
+                    .col(string_uniq(PasswordResetToken::TokenHash))
+                    .col(timestamp(PasswordResetToken::CreatedAt))
+                    .col(timestamp(PasswordResetToken::ExpiresAt))
+                    .col(timestamp_null(PasswordResetToken::UsedAt))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PasswordResetToken::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum PasswordResetToken {
+    Table,
+    Id,
+    UserId,
+    TokenHash,
+    CreatedAt,
+    ExpiresAt,
+    UsedAt,
+}
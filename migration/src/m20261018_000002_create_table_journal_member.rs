@@ -0,0 +1,79 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20240508_223223_create_table_journal::Journal;
+use crate::m20240512_173332_create_table_user::User;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(JournalMember::Table)
+                    .if_not_exists()
+                    .col(pk_auto(JournalMember::Id))
+                    .col(integer(JournalMember::JournalId))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(JournalMember::Table, JournalMember::JournalId)
+                            .to(Journal::Table, Journal::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .col(integer(JournalMember::UserId))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(JournalMember::Table, JournalMember::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .col(
+                        string(JournalMember::Role).check(Expr::col(JournalMember::Role).is_in([
+                            "viewer",
+                            "contributor",
+                            "owner",
+                        ])),
+                    )
+                    .index(
+                        Index::create()
+                            .unique()
+                            .col(JournalMember::JournalId)
+                            .col(JournalMember::UserId),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Until now, every approved user could see every journal.
+        // Keep it that way for existing data, and let admins trim memberships afterwards.
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            r#"
+            INSERT INTO journal_member (journal_id, user_id, role)
+            SELECT journal.id, user.id, 'viewer'
+            FROM journal, user
+            WHERE user.approved AND NOT user.admin
+            "#,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(JournalMember::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum JournalMember {
+    Table,
+    Id,
+    JournalId,
+    UserId,
+    Role,
+}
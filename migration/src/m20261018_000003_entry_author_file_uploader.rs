@@ -0,0 +1,61 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite doesn't let you add foreign keys to an existing table through `ALTER TABLE`...
+        // except for nullable columns added with `ADD COLUMN`, which sea-query doesn't know about.
+        // Existing entries have no author: they were written by admins.
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            r#"
+            ALTER TABLE journal_entry
+            ADD COLUMN author_id INTEGER NULL REFERENCES user (id) ON DELETE SET NULL
+            "#,
+        )
+        .await?;
+        // Who the upload URL was issued to, so only they can commit the file to an entry.
+        db.execute_unprepared(
+            r#"
+            ALTER TABLE file
+            ADD COLUMN uploaded_by INTEGER NULL REFERENCES user (id) ON DELETE SET NULL
+            "#,
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(File::Table)
+                    .drop_column(File::UploadedBy)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(JournalEntry::Table)
+                    .drop_column(JournalEntry::AuthorId)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum JournalEntry {
+    Table,
+    AuthorId,
+}
+
+#[derive(DeriveIden)]
+enum File {
+    Table,
+    UploadedBy,
+}
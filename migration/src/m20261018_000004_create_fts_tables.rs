@@ -0,0 +1,87 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// (fts table, content table, indexed columns)
+const FTS_TABLES: [(&str, &str, &[&str]); 3] = [
+    (
+        "journal_entry_fts",
+        "journal_entry",
+        &["title", "text", "address"],
+    ),
+    (
+        "journal_entry_media_fts",
+        "journal_entry_media",
+        &["caption"],
+    ),
+    ("journal_comment_fts", "journal_comment", &["text"]),
+];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        // External content tables, kept in sync with triggers.
+        // See https://www.sqlite.org/fts5.html#external_content_tables
+        for (fts, content, columns) in FTS_TABLES {
+            let cols = columns.join(", ");
+            let new_cols = columns
+                .iter()
+                .map(|c| format!("new.{c}"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let old_cols = columns
+                .iter()
+                .map(|c| format!("old.{c}"))
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            db.execute_unprepared(&format!(
+                r#"
+                CREATE VIRTUAL TABLE {fts} USING fts5(
+                    {cols},
+                    content='{content}',
+                    content_rowid='id',
+                    tokenize='unicode61 remove_diacritics 2'
+                );
+
+                CREATE TRIGGER {fts}_ai AFTER INSERT ON {content} BEGIN
+                    INSERT INTO {fts}(rowid, {cols}) VALUES (new.id, {new_cols});
+                END;
+
+                CREATE TRIGGER {fts}_ad AFTER DELETE ON {content} BEGIN
+                    INSERT INTO {fts}({fts}, rowid, {cols}) VALUES ('delete', old.id, {old_cols});
+                END;
+
+                CREATE TRIGGER {fts}_au AFTER UPDATE ON {content} BEGIN
+                    INSERT INTO {fts}({fts}, rowid, {cols}) VALUES ('delete', old.id, {old_cols});
+                    INSERT INTO {fts}(rowid, {cols}) VALUES (new.id, {new_cols});
+                END;
+
+                INSERT INTO {fts}({fts}) VALUES ('rebuild');
+                "#
+            ))
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        for (fts, _, _) in FTS_TABLES {
+            db.execute_unprepared(&format!(
+                r#"
+                DROP TRIGGER IF EXISTS {fts}_ai;
+                DROP TRIGGER IF EXISTS {fts}_ad;
+                DROP TRIGGER IF EXISTS {fts}_au;
+                DROP TABLE IF EXISTS {fts};
+                "#
+            ))
+            .await?;
+        }
+        Ok(())
+    }
+}
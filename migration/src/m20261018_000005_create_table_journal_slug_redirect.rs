@@ -0,0 +1,43 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20240508_223223_create_table_journal::Journal;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(JournalSlugRedirect::Table)
+                    .if_not_exists()
+                    .col(pk_auto(JournalSlugRedirect::Id))
+                    .col(string_uniq(JournalSlugRedirect::Slug))
+                    .col(integer(JournalSlugRedirect::JournalId))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(JournalSlugRedirect::Table, JournalSlugRedirect::JournalId)
+                            .to(Journal::Table, Journal::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(JournalSlugRedirect::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum JournalSlugRedirect {
+    Table,
+    Id,
+    Slug,
+    JournalId,
+}
@@ -0,0 +1,61 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite only supports one column per `ALTER TABLE`.
+        let columns = [
+            // Naive, like `journal_entry.time`: cameras record the local time.
+            date_time_null(JournalEntryMedia::TakenAt),
+            float_null(JournalEntryMedia::Lat),
+            float_null(JournalEntryMedia::Lng),
+            string_null(JournalEntryMedia::CameraModel),
+            integer_null(JournalEntryMedia::Orientation),
+        ];
+        for mut column in columns {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(JournalEntryMedia::Table)
+                        .add_column(&mut column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let columns = [
+            JournalEntryMedia::TakenAt,
+            JournalEntryMedia::Lat,
+            JournalEntryMedia::Lng,
+            JournalEntryMedia::CameraModel,
+            JournalEntryMedia::Orientation,
+        ];
+        for column in columns {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(JournalEntryMedia::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum JournalEntryMedia {
+    Table,
+    TakenAt,
+    Lat,
+    Lng,
+    CameraModel,
+    Orientation,
+}
@@ -0,0 +1,64 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20240508_221939_create_table_file::File;
+use crate::m20240508_223223_create_table_journal::Journal;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(JournalTrack::Table)
+                    .if_not_exists()
+                    .col(pk_auto(JournalTrack::Id))
+                    .col(integer(JournalTrack::JournalId))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(JournalTrack::Table, JournalTrack::JournalId)
+                            .to(Journal::Table, Journal::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    // NULL for tracks of the whole journal.
+                    .col(date_null(JournalTrack::Date))
+                    .col(string(JournalTrack::Name))
+                    // The raw GPX. Orphans are deleted by `StorageCleanup`.
+                    .col(integer(JournalTrack::FileId))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(JournalTrack::Table, JournalTrack::FileId)
+                            .to(File::Table, File::Id),
+                    )
+                    .col(double(JournalTrack::DistanceM))
+                    .col(double(JournalTrack::ElevationGainM))
+                    // JSON, simplified: `[[[lat, lng], ...], ...]`, one line per segment.
+                    .col(text(JournalTrack::Polyline))
+                    .col(timestamp(JournalTrack::CreatedAt))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(JournalTrack::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum JournalTrack {
+    Table,
+    Id,
+    JournalId,
+    Date,
+    Name,
+    FileId,
+    DistanceM,
+    ElevationGainM,
+    Polyline,
+    CreatedAt,
+}
@@ -0,0 +1,48 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20240512_173332_create_table_user::User;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(FeedToken::Table)
+                    .if_not_exists()
+                    .col(pk_auto(FeedToken::Id))
+                    // One per user, which is reset to revoke the feed URLs.
+                    .col(integer_uniq(FeedToken::UserId))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(FeedToken::Table, FeedToken::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    // Stored in clear (unlike password reset tokens): the feed URLs are shown
+                    // again on every journal page, and only grant read access.
+                    .col(string_uniq(FeedToken::Token))
+                    .col(timestamp(FeedToken::CreatedAt))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(FeedToken::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum FeedToken {
+    Table,
+    Id,
+    UserId,
+    Token,
+    CreatedAt,
+}
@@ -0,0 +1,135 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20240508_223223_create_table_journal::Journal;
+use crate::m20240512_173332_create_table_user::User;
+use crate::m20240518_025335_create_table_journal_entry::JournalEntry;
+use crate::m20240526_203520_create_table_journal_comment::JournalComment;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(JournalSubscription::Table)
+                    .if_not_exists()
+                    .col(pk_auto(JournalSubscription::Id))
+                    .col(integer(JournalSubscription::JournalId))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(JournalSubscription::Table, JournalSubscription::JournalId)
+                            .to(Journal::Table, Journal::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .col(integer(JournalSubscription::UserId))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(JournalSubscription::Table, JournalSubscription::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .col(string(JournalSubscription::Frequency).check(
+                        Expr::col(JournalSubscription::Frequency).is_in(["instant", "daily"]),
+                    ))
+                    .index(
+                        Index::create()
+                            .unique()
+                            .col(JournalSubscription::JournalId)
+                            .col(JournalSubscription::UserId),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // The queue of emails to send, one row per recipient and event.
+        manager
+            .create_table(
+                Table::create()
+                    .table(Notification::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Notification::Id))
+                    .col(integer(Notification::UserId))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(Notification::Table, Notification::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .col(integer(Notification::JournalId))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(Notification::Table, Notification::JournalId)
+                            .to(Journal::Table, Journal::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    // Deleted entries and comments don't need to be announced anymore.
+                    .col(integer_null(Notification::EntryId))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(Notification::Table, Notification::EntryId)
+                            .to(JournalEntry::Table, JournalEntry::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .col(integer_null(Notification::CommentId))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(Notification::Table, Notification::CommentId)
+                            .to(JournalComment::Table, JournalComment::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    // Whether it waits for the daily digest.
+                    .col(boolean(Notification::Digest))
+                    .col(timestamp(Notification::CreatedAt))
+                    .col(timestamp_null(Notification::SentAt))
+                    .col(integer(Notification::Attempts).default(0))
+                    .col(text_null(Notification::Error))
+                    // Entries can be unpublished and published again.
+                    .index(
+                        Index::create()
+                            .unique()
+                            .col(Notification::UserId)
+                            .col(Notification::EntryId),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Notification::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(JournalSubscription::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum JournalSubscription {
+    Table,
+    Id,
+    JournalId,
+    UserId,
+    Frequency,
+}
+
+#[derive(DeriveIden)]
+enum Notification {
+    Table,
+    Id,
+    UserId,
+    JournalId,
+    EntryId,
+    CommentId,
+    Digest,
+    CreatedAt,
+    SentAt,
+    Attempts,
+    Error,
+}
@@ -0,0 +1,92 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20240508_221939_create_table_file::File;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite can't alter the check constraint on `status`, recreate the table.
+        let old_table = Alias::new("video_transcode_task_backup");
+        manager
+            .rename_table(
+                Table::rename()
+                    .table(VideoTranscodeTask::Table, old_table.clone())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(VideoTranscodeTask::Table)
+                    .col(pk_auto(VideoTranscodeTask::Id))
+                    .col(timestamp(VideoTranscodeTask::CreatedAt))
+                    .col(timestamp_null(VideoTranscodeTask::UpdatedAt))
+                    .col(string(VideoTranscodeTask::Status).default("pending").check(
+                        Expr::col(VideoTranscodeTask::Status).is_in([
+                            "pending",
+                            "dispatched", // NEW
+                            "completed",
+                            "failed",
+                        ]),
+                    ))
+                    .col(string(VideoTranscodeTask::Detail))
+                    .col(integer(VideoTranscodeTask::FileId))
+                    .col(integer(VideoTranscodeTask::Attempts).default(0))
+                    .col(timestamp_null(VideoTranscodeTask::NextAttemptAt))
+                    .col(timestamp_null(VideoTranscodeTask::DispatchedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(VideoTranscodeTask::Table, VideoTranscodeTask::FileId)
+                            .to(File::Table, File::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Tasks already sent to a delayed backend can't be told apart, they stay pending.
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            r#"
+            INSERT INTO video_transcode_task (
+                id, created_at, updated_at, status, detail, file_id, attempts, next_attempt_at
+            )
+            SELECT id, created_at, updated_at, status, detail, file_id, attempts, next_attempt_at
+            FROM video_transcode_task_backup
+            "#,
+        )
+        .await?;
+
+        manager
+            .drop_table(Table::drop().table(old_table).to_owned())
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Keeps the `dispatched` status and column, which the previous code ignores.
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "UPDATE video_transcode_task SET status = 'pending' WHERE status = 'dispatched'",
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum VideoTranscodeTask {
+    Table,
+    Id,
+    CreatedAt,
+    UpdatedAt,
+    Status,
+    Detail,
+    FileId,
+    Attempts,
+    NextAttemptAt,
+    DispatchedAt, // NEW
+}
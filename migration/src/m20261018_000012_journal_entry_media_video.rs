@@ -0,0 +1,56 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite only supports one column per `ALTER TABLE`.
+        // Filled in by the video transcoder, from the uploaded file.
+        let columns = [
+            double_null(JournalEntryMedia::DurationS),
+            string_null(JournalEntryMedia::VideoCodec),
+            // Degrees, counterclockwise like ffprobe reports it.
+            integer_null(JournalEntryMedia::Rotation),
+        ];
+        for mut column in columns {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(JournalEntryMedia::Table)
+                        .add_column(&mut column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let columns = [
+            JournalEntryMedia::DurationS,
+            JournalEntryMedia::VideoCodec,
+            JournalEntryMedia::Rotation,
+        ];
+        for column in columns {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(JournalEntryMedia::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum JournalEntryMedia {
+    Table,
+    DurationS,
+    VideoCodec,
+    Rotation,
+}
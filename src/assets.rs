@@ -21,6 +21,11 @@ impl AssetManifest {
             .get(asset_key)
             .map(|value| format!("{}/{}", self.url_base, value))
     }
+
+    /// File names of the assets, relative to the manifest.
+    pub fn files(&self) -> impl Iterator<Item = &String> {
+        self.manifest.values()
+    }
 }
 
 async fn load_manifest(path: &str) -> Result<ManifestData, anyhow::Error> {
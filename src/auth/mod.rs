@@ -1,4 +1,5 @@
 pub mod github_action_auth;
+pub mod password_reset;
 pub mod perms;
 pub mod routes;
 pub mod sessions;
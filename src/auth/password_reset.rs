@@ -0,0 +1,83 @@
+//! Single-use, time-limited tokens for resetting forgotten passwords.
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter,
+    TransactionTrait,
+};
+use sha2::{Digest, Sha256};
+
+use super::sessions::AuthBackend;
+use entities::{prelude::*, *};
+
+const TOKEN_TTL: chrono::Duration = chrono::Duration::hours(1);
+
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Creates a new token for the user, and returns it in clear (to be sent by email).
+pub async fn create_password_reset_token(
+    user_id: i32,
+    db: &DatabaseConnection,
+) -> Result<String, DbErr> {
+    let token = nanoid::nanoid!(32);
+    let now = chrono::Utc::now();
+    let data = password_reset_token::ActiveModel {
+        user_id: sea_orm::ActiveValue::Set(user_id),
+        token_hash: sea_orm::ActiveValue::Set(hash_token(&token)),
+        created_at: sea_orm::ActiveValue::Set(now),
+        expires_at: sea_orm::ActiveValue::Set(now + TOKEN_TTL),
+        used_at: sea_orm::ActiveValue::Set(None),
+        id: sea_orm::ActiveValue::NotSet,
+    };
+    data.insert(db).await?;
+    Ok(token)
+}
+
+/// Returns the token if it can still be used.
+pub async fn query_valid_password_reset_token(
+    token: &str,
+    db: &impl sea_orm::ConnectionTrait,
+) -> Result<Option<password_reset_token::Model>, DbErr> {
+    PasswordResetToken::find()
+        .filter(password_reset_token::Column::TokenHash.eq(hash_token(token)))
+        .filter(password_reset_token::Column::UsedAt.is_null())
+        .filter(password_reset_token::Column::ExpiresAt.gt(chrono::Utc::now()))
+        .one(db)
+        .await
+}
+
+/// Sets the user's new password and burns all of their outstanding tokens.
+/// Returns `false` if the token is invalid, used or expired.
+pub async fn reset_password(
+    token: &str,
+    password: String,
+    db: &DatabaseConnection,
+) -> Result<bool, DbErr> {
+    let tx = db.begin().await?;
+
+    let token = match query_valid_password_reset_token(token, &tx).await? {
+        Some(token) => token,
+        None => return Ok(false),
+    };
+
+    let data = user::ActiveModel {
+        id: sea_orm::ActiveValue::Set(token.user_id),
+        password: sea_orm::ActiveValue::Set(AuthBackend::hash_password(password)),
+        ..Default::default()
+    };
+    User::update(data).exec(&tx).await?;
+
+    let data = password_reset_token::ActiveModel {
+        used_at: sea_orm::ActiveValue::Set(Some(chrono::Utc::now())),
+        ..Default::default()
+    };
+    PasswordResetToken::update_many()
+        .set(data)
+        .filter(password_reset_token::Column::UserId.eq(token.user_id))
+        .filter(password_reset_token::Column::UsedAt.is_null())
+        .exec(&tx)
+        .await?;
+
+    tx.commit().await?;
+    Ok(true)
+}
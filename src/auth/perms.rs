@@ -1,16 +1,24 @@
 use anyhow::Context as _;
 use async_trait::async_trait;
 use axum_login::AuthzBackend as _;
-use sea_orm::{ColumnTrait as _, QueryFilter as _, Select};
+use sea_orm::{
+    sea_query::SelectStatement, ColumnTrait as _, Condition, ConnectionTrait, EntityTrait as _,
+    PaginatorTrait as _, QueryFilter as _, QuerySelect as _, QueryTrait as _, Select,
+};
 use std::collections::HashSet;
 
+use entities::{journal_member::JournalMemberRole, prelude::*, *};
+
 use super::sessions::AuthBackend;
-use crate::AuthSession;
+use crate::{AuthSession, AuthUser};
 
 /// Simplest permissioning ever.
+/// Access to individual journals is granted through [`journal_member`] instead.
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub enum Permission {
     Admin,
+    /// Can write entries, in the journals where they're at least a contributor.
+    Contributor,
 }
 
 #[async_trait]
@@ -24,26 +32,156 @@ impl axum_login::AuthzBackend for AuthBackend {
         let mut perms = HashSet::new();
         if user.0.admin {
             perms.insert(Permission::Admin);
+            perms.insert(Permission::Contributor);
+        } else {
+            let contributions = JournalMember::find()
+                .filter(journal_member::Column::UserId.eq(user.0.id))
+                .filter(
+                    journal_member::Column::Role
+                        .is_in([JournalMemberRole::Contributor, JournalMemberRole::Owner]),
+                )
+                .count(&self.db)
+                .await?;
+            if contributions > 0 {
+                perms.insert(Permission::Contributor);
+            }
         }
         Ok(perms)
     }
 }
 
+fn member_journal_ids(user: &AuthUser) -> SelectStatement {
+    JournalMember::find()
+        .select_only()
+        .column(journal_member::Column::JournalId)
+        .filter(journal_member::Column::UserId.eq(user.0.id))
+        .into_query()
+}
+
+fn session_user(session: &AuthSession) -> &AuthUser {
+    session.user.as_ref().expect("Should have a user")
+}
+
 impl AuthBackend {
+    async fn is_admin(&self, session: &AuthSession) -> Result<bool, anyhow::Error> {
+        self.is_admin_user(session_user(session)).await
+    }
+
+    async fn is_admin_user(&self, user: &AuthUser) -> Result<bool, anyhow::Error> {
+        self.has_perm(user, Permission::Admin)
+            .await
+            .context("has_perm failed")
+    }
+
+    /// Admins can see every journal, everyone else only sees the journals they're a member of.
+    pub async fn filter_journals(
+        &self,
+        session: &AuthSession,
+        q: Select<journal::Entity>,
+    ) -> Result<Select<journal::Entity>, anyhow::Error> {
+        self.filter_journals_for_user(session_user(session), q)
+            .await
+    }
+
+    /// Like [`Self::filter_journals`], for requests authenticated without a session (e.g. feeds).
+    pub async fn filter_journals_for_user(
+        &self,
+        user: &AuthUser,
+        mut q: Select<journal::Entity>,
+    ) -> Result<Select<journal::Entity>, anyhow::Error> {
+        if !self.is_admin_user(user).await? {
+            q = q.filter(journal::Column::Id.in_subquery(member_journal_ids(user)));
+        }
+        Ok(q)
+    }
+
     pub async fn filter_journal_entries(
         &self,
         session: &AuthSession,
-        mut q: Select<entities::journal_entry::Entity>,
+        q: Select<entities::journal_entry::Entity>,
     ) -> Result<Select<entities::journal_entry::Entity>, anyhow::Error> {
-        let user = session.user.as_ref().expect("Should have a user");
-        let can_view_draft = self
-            .has_perm(user, Permission::Admin)
+        self.filter_journal_entries_for_user(session_user(session), q)
             .await
-            .context("has_perm failed")?;
+    }
 
-        if !can_view_draft {
-            q = q.filter(entities::journal_entry::Column::Draft.eq(false));
+    pub async fn filter_journal_entries_for_user(
+        &self,
+        user: &AuthUser,
+        mut q: Select<entities::journal_entry::Entity>,
+    ) -> Result<Select<entities::journal_entry::Entity>, anyhow::Error> {
+        let is_admin = self.is_admin_user(user).await?;
+
+        if !is_admin {
+            q = q
+                .filter(
+                    entities::journal_entry::Column::JournalId
+                        .in_subquery(member_journal_ids(user)),
+                )
+                // Drafts are only visible to admins and their author.
+                .filter(
+                    Condition::any()
+                        .add(entities::journal_entry::Column::Draft.eq(false))
+                        .add(entities::journal_entry::Column::AuthorId.eq(user.0.id)),
+                );
         }
         Ok(q)
     }
+
+    pub async fn can_view_journal(
+        &self,
+        session: &AuthSession,
+        journal_id: i32,
+        db: &impl ConnectionTrait,
+    ) -> Result<bool, anyhow::Error> {
+        let q = Journal::find_by_id(journal_id);
+        let journal = self.filter_journals(session, q).await?.one(db).await?;
+        Ok(journal.is_some())
+    }
+
+    /// Returns the user's role in the journal, where admins are implicitly owners.
+    pub async fn get_journal_role(
+        &self,
+        session: &AuthSession,
+        journal_id: i32,
+        db: &impl ConnectionTrait,
+    ) -> Result<Option<JournalMemberRole>, anyhow::Error> {
+        if self.is_admin(session).await? {
+            return Ok(Some(JournalMemberRole::Owner));
+        }
+        let user = session_user(session);
+        let member = JournalMember::find()
+            .filter(journal_member::Column::JournalId.eq(journal_id))
+            .filter(journal_member::Column::UserId.eq(user.0.id))
+            .one(db)
+            .await?;
+        Ok(member.map(|member| member.role))
+    }
+
+    /// Whether the user can add entries to the journal.
+    pub async fn can_write_journal(
+        &self,
+        session: &AuthSession,
+        journal_id: i32,
+        db: &impl ConnectionTrait,
+    ) -> Result<bool, anyhow::Error> {
+        let role = self.get_journal_role(session, journal_id, db).await?;
+        Ok(role >= Some(JournalMemberRole::Contributor))
+    }
+
+    /// Owners can edit every entry of their journal, contributors only their own.
+    pub async fn can_edit_journal_entry(
+        &self,
+        session: &AuthSession,
+        entry: &journal_entry::Model,
+        db: &impl ConnectionTrait,
+    ) -> Result<bool, anyhow::Error> {
+        let user = session_user(session);
+        let role = self.get_journal_role(session, entry.journal_id, db).await?;
+        let can_edit = match role {
+            Some(JournalMemberRole::Owner) => true,
+            Some(JournalMemberRole::Contributor) => entry.author_id == Some(user.0.id),
+            Some(JournalMemberRole::Viewer) | None => false,
+        };
+        Ok(can_edit)
+    }
 }
@@ -1,11 +1,89 @@
-use axum::extract::State;
+use axum::{
+    extract::{rejection::FormRejection, State},
+    response::{Html, IntoResponse},
+    Form,
+};
+use minijinja::context;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use serde::Deserialize;
+use tracing::error;
 
-use crate::{AppState, RouteResult};
+use super::super::{password_reset::create_password_reset_token, sessions::AuthBackend};
+use crate::{mail::Email, AppState, FormError, NotFound, Route, RouteResult, Templ};
+use entities::{prelude::*, *};
 
-pub async fn forgot_password_get(_state: State<AppState>) -> RouteResult {
-    todo!()
+fn disabled() -> NotFound {
+    NotFound::new("Password resets are disabled, please ask an administrator.".into())
 }
 
-pub async fn forgot_password_post(_state: State<AppState>) -> RouteResult {
-    todo!()
+pub async fn forgot_password_get(state: State<AppState>, templ: Templ) -> RouteResult {
+    if state.mailer.is_none() {
+        return Ok(disabled().render(&templ)?.into_response());
+    }
+    let html = templ.render("forgot_password.html")?;
+    Ok(html.into_response())
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct ForgotPassword {
+    email: String,
+}
+
+pub async fn forgot_password_post(
+    state: State<AppState>,
+    templ: Templ,
+    form: Result<Form<ForgotPassword>, FormRejection>,
+) -> RouteResult {
+    let Some(mailer) = state.mailer.clone() else {
+        return Ok(disabled().render(&templ)?.into_response());
+    };
+    let form = match form {
+        Ok(form) => form.0,
+        Err(err) => {
+            let resp = FormError::from(err).render(&state)?;
+            return Ok(resp.into_response());
+        }
+    };
+
+    let email = AuthBackend::normalize_email(form.email);
+    let user = User::find()
+        .filter(user::Column::Email.eq(&email))
+        .filter(user::Column::Approved.eq(true))
+        .one(&state.db)
+        .await?;
+
+    // Don't let on whether the user exists: same response, and mail errors are only logged.
+    if let Some(user) = user {
+        let token = create_password_reset_token(user.id, &state.db).await?;
+        let href_reset = Route::PasswordResetGet {
+            token: Some(&token),
+        }
+        .as_url(&state.server_name);
+        let ctx = context! { user, href_reset };
+        let body = state
+            .template_engine
+            .get_template("email/password_reset.txt")?
+            .render(ctx)?;
+        let email = Email {
+            to: email,
+            subject: "Reset your password".to_string(),
+            body,
+        };
+        tokio::spawn(async move {
+            if let Err(err) = mailer.send(email).await {
+                error!("Failed to send password reset email: {err:#?}");
+            }
+        });
+    }
+
+    let body = r#"
+    <div class="alert alert-success">
+        If an account exists for this email, you will receive a link to reset your password shortly.
+    </div>
+    "#;
+    let resp = (
+        [("HX-Swap", "outerHTML"), ("HX-Target", "this")],
+        Html(body),
+    );
+    Ok(resp.into_response())
 }
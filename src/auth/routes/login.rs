@@ -17,10 +17,19 @@ pub struct NextUrl {
     next: Option<String>,
 }
 
-pub async fn login_get(templ: Templ, Query(NextUrl { next }): Query<NextUrl>) -> RouteResult {
+pub async fn login_get(
+    state: State<AppState>,
+    templ: Templ,
+    Query(NextUrl { next }): Query<NextUrl>,
+) -> RouteResult {
+    // Password resets are sent by email.
+    let href_forgot_password = state
+        .mailer
+        .as_ref()
+        .map(|_| Route::ForgotPasswordGet.as_path());
     let ctx = context! {
         href_register => &Route::RegisterGet.as_path(),
-        href_forgot_password => &Route::ForgotPasswordGet.as_path(),
+        href_forgot_password,
         next => &next.unwrap_or("/".to_string()),
     };
     let html = templ.render_ctx("login.html", ctx)?;
@@ -1,11 +1,13 @@
 mod forgot_password;
 mod login;
 mod logout;
+mod password_reset;
 mod register;
 mod user_list;
 
 pub use forgot_password::*;
 pub use login::*;
 pub use logout::*;
+pub use password_reset::*;
 pub use register::*;
 pub use user_list::*;
@@ -0,0 +1,68 @@
+use axum::{
+    extract::{rejection::FormRejection, Path, State},
+    response::{Html, IntoResponse},
+    Form,
+};
+use serde::Deserialize;
+
+use super::super::password_reset::{query_valid_password_reset_token, reset_password};
+use crate::{AppState, FormError, NotFound, Route, RouteResult, Templ};
+
+const INVALID_TOKEN_MSG: &str = "This link is invalid or has expired. Please request a new one.";
+
+pub async fn password_reset_get(
+    state: State<AppState>,
+    templ: Templ,
+    Path(token): Path<String>,
+) -> RouteResult {
+    if query_valid_password_reset_token(&token, &state.db)
+        .await?
+        .is_none()
+    {
+        let resp = NotFound::new(INVALID_TOKEN_MSG.into()).render(&templ)?;
+        return Ok(resp.into_response());
+    }
+
+    let html = templ.render("password_reset.html")?;
+    Ok(html.into_response())
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct PasswordReset {
+    password: String,
+}
+
+pub async fn password_reset_post(
+    state: State<AppState>,
+    Path(token): Path<String>,
+    form: Result<Form<PasswordReset>, FormRejection>,
+) -> RouteResult {
+    let form = match form {
+        Ok(form) => form.0,
+        Err(err) => {
+            let resp = FormError::from(err).render(&state)?;
+            return Ok(resp.into_response());
+        }
+    };
+
+    if !reset_password(&token, form.password, &state.db).await? {
+        let resp = FormError::new(INVALID_TOKEN_MSG).render(&state)?;
+        return Ok(resp.into_response());
+    }
+
+    let href_login = Route::LoginGet.as_path();
+    let body = format!(
+        r#"
+    <div class="alert alert-success">
+        Your password has been reset!
+        <br />
+        You can now <a href="{href_login}" class="link">login</a> with your new password.
+    </div>
+    "#
+    );
+    let resp = (
+        [("HX-Swap", "outerHTML"), ("HX-Target", "this")],
+        Html(body),
+    );
+    Ok(resp.into_response())
+}
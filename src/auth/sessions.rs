@@ -38,7 +38,7 @@ pub async fn init_session(
         .with_secure(!AppEnv::is_dev())
         .with_http_only(true);
 
-    let auth_backend = AuthBackend { db: db.clone() };
+    let auth_backend = AuthBackend::new(db.clone());
     let auth_layer = axum_login::AuthManagerLayerBuilder::new(auth_backend, session_layer).build();
 
     Ok(auth_layer)
@@ -84,10 +84,14 @@ pub enum AuthError {
 
 #[derive(Debug, Clone)]
 pub struct AuthBackend {
-    db: sea_orm::DatabaseConnection,
+    pub(super) db: sea_orm::DatabaseConnection,
 }
 
 impl AuthBackend {
+    pub fn new(db: sea_orm::DatabaseConnection) -> Self {
+        Self { db }
+    }
+
     pub fn hash_password(password: String) -> String {
         password_auth::generate_hash(password)
     }
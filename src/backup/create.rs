@@ -0,0 +1,154 @@
+use std::{
+    collections::HashSet,
+    env::temp_dir,
+    io::{self, BufWriter},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::Context;
+use sea_orm::{ConnectionTrait, DatabaseConnection, EntityTrait, QueryOrder, Statement};
+use tracing::{info, warn};
+
+use crate::storage::FileStore;
+use entities::{prelude::*, *};
+
+use super::{
+    connect, tar::TarWriter, BackupBlob, BackupItem, BackupManifest, HashWriter, MissingBlob,
+    DATABASE_PATH, MANIFEST_PATH, MANIFEST_VERSION,
+};
+
+pub struct Backup {
+    pub db: DatabaseConnection,
+    pub storage: Arc<FileStore>,
+    pub out_file: PathBuf,
+}
+
+impl Backup {
+    pub async fn run(&self) -> Result<(), anyhow::Error> {
+        let work_dir = temp_dir().join(format!("cookie-odyssey-backup-{}", nanoid::nanoid!()));
+        tokio::fs::create_dir_all(&work_dir)
+            .await
+            .context("Failed to create work directory for backup")?;
+        let res = self.run_in(&work_dir).await;
+        if let Err(err) = tokio::fs::remove_dir_all(&work_dir).await {
+            warn!("Failed to remove {work_dir:?}: {err}");
+        }
+        res
+    }
+
+    async fn run_in(&self, work_dir: &Path) -> Result<(), anyhow::Error> {
+        // The server may be running: WAL is enabled, so copying the file isn't enough.
+        let db_path = work_dir.join(DATABASE_PATH);
+        let q = Statement::from_sql_and_values(
+            sea_orm::DatabaseBackend::Sqlite,
+            "VACUUM INTO ?",
+            [db_path.to_string_lossy().to_string().into()],
+        );
+        self.db
+            .execute(q)
+            .await
+            .context("Failed to snapshot the database")?;
+        let database = hash_file(&db_path, DATABASE_PATH)?;
+
+        // Files from the snapshot, so both match.
+        let snapshot_url = format!("sqlite://{}?mode=ro", db_path.display());
+        let snapshot = connect(snapshot_url).await?;
+        let files = File::find()
+            .order_by_asc(file::Column::Id)
+            .all(&snapshot)
+            .await?;
+        snapshot.close().await?;
+
+        let blobs_dir = work_dir.join("blobs");
+        tokio::fs::create_dir_all(&blobs_dir).await?;
+        let mut seen = HashSet::new();
+        let mut blobs = Vec::new();
+        let mut missing = Vec::new();
+        for file in files {
+            // Several rows may point to the same blob.
+            if !seen.insert((file.bucket.clone(), file.key.clone())) {
+                continue;
+            }
+            let path = blobs_dir.join(file.id.to_string());
+            let res = self
+                .storage
+                .download_to_file(file.bucket.clone(), file.key.clone(), &path)
+                .await;
+            if let Err(err) = res {
+                warn!("Blob {}/{} is missing: {err:#}", file.bucket, file.key);
+                missing.push(MissingBlob {
+                    bucket: file.bucket,
+                    key: file.key,
+                });
+                continue;
+            }
+            let archive_path = format!("blobs/{}/{}", file.bucket, file.key);
+            blobs.push((
+                path.clone(),
+                BackupBlob {
+                    item: hash_file(&path, &archive_path)?,
+                    bucket: file.bucket,
+                    key: file.key,
+                },
+            ));
+        }
+
+        let (paths, blobs): (Vec<_>, Vec<_>) = blobs.into_iter().unzip();
+        let manifest = BackupManifest {
+            version: MANIFEST_VERSION,
+            created_at: chrono::Utc::now(),
+            database,
+            blobs,
+            missing,
+        };
+        self.write_archive(&manifest, &db_path, &paths)?;
+
+        info!(
+            "Backed up the database and {} blobs to {:?} ({} missing)",
+            manifest.blobs.len(),
+            self.out_file,
+            manifest.missing.len()
+        );
+        Ok(())
+    }
+
+    /// The manifest comes first, so restores can verify files as they go.
+    fn write_archive(
+        &self,
+        manifest: &BackupManifest,
+        db_path: &Path,
+        blob_paths: &[PathBuf],
+    ) -> Result<(), anyhow::Error> {
+        let out = std::fs::File::create(&self.out_file)
+            .with_context(|| format!("Failed to create {:?}", self.out_file))?;
+        let mut tar = TarWriter::new(BufWriter::new(out));
+
+        let manifest_json = serde_json::to_vec_pretty(manifest)?;
+        tar.append(
+            MANIFEST_PATH,
+            manifest_json.len() as u64,
+            &mut &manifest_json[..],
+        )?;
+        let items = std::iter::once((db_path, &manifest.database)).chain(
+            blob_paths
+                .iter()
+                .map(PathBuf::as_path)
+                .zip(manifest.blobs.iter().map(|blob| &blob.item)),
+        );
+        for (path, item) in items {
+            let mut file = std::fs::File::open(path)?;
+            tar.append(&item.path, item.size, &mut file)?;
+        }
+        tar.finish()?;
+        Ok(())
+    }
+}
+
+fn hash_file(path: &Path, archive_path: &str) -> Result<BackupItem, anyhow::Error> {
+    let mut file = std::fs::File::open(path).with_context(|| format!("Failed to open {path:?}"))?;
+    let mut writer = HashWriter::new(io::sink());
+    io::copy(&mut file, &mut writer)?;
+    let (_, item) = writer.finish(archive_path.to_string())?;
+    Ok(item)
+}
@@ -0,0 +1,102 @@
+//! Backup archives: a tar with a manifest, a snapshot of the database, and every blob
+//! referenced by the `file` table.
+//!
+//! ```text
+//! manifest.json
+//! db.sqlite
+//! blobs/{bucket}/{key}
+//! ```
+mod create;
+mod restore;
+mod tar;
+
+use std::io::{self, Write};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+pub use create::Backup;
+pub use restore::Restore;
+
+const MANIFEST_VERSION: u32 = 1;
+const MANIFEST_PATH: &str = "manifest.json";
+const DATABASE_PATH: &str = "db.sqlite";
+
+#[derive(Serialize, Deserialize, Debug)]
+struct BackupManifest {
+    version: u32,
+    created_at: chrono::DateTime<chrono::Utc>,
+    database: BackupItem,
+    blobs: Vec<BackupBlob>,
+    /// Referenced by the `file` table, but not found in storage.
+    missing: Vec<MissingBlob>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct BackupItem {
+    path: String,
+    size: u64,
+    /// Hex-encoded SHA-256.
+    sha256: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct BackupBlob {
+    bucket: String,
+    key: String,
+    #[serde(flatten)]
+    item: BackupItem,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct MissingBlob {
+    bucket: String,
+    key: String,
+}
+
+/// Connects to a database other than the app's, quietly.
+async fn connect(url: String) -> Result<sea_orm::DatabaseConnection, sea_orm::DbErr> {
+    let mut opts = sea_orm::ConnectOptions::new(url);
+    opts.sqlx_logging(false);
+    sea_orm::Database::connect(opts).await
+}
+
+/// Computes the size and SHA-256 of what is written through it.
+struct HashWriter<W: Write> {
+    inner: W,
+    hasher: Sha256,
+    size: u64,
+}
+
+impl<W: Write> HashWriter<W> {
+    fn new(inner: W) -> Self {
+        Self {
+            inner,
+            hasher: Sha256::new(),
+            size: 0,
+        }
+    }
+
+    fn finish(mut self, path: String) -> io::Result<(W, BackupItem)> {
+        self.inner.flush()?;
+        let item = BackupItem {
+            path,
+            size: self.size,
+            sha256: hex::encode(self.hasher.finalize()),
+        };
+        Ok((self.inner, item))
+    }
+}
+
+impl<W: Write> Write for HashWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.hasher.update(&buf[..n]);
+        self.size += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
@@ -0,0 +1,148 @@
+use std::{
+    collections::HashMap,
+    env::temp_dir,
+    io::{BufReader, Read},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{bail, Context};
+use sea_orm::{sea_query::Expr, EntityTrait};
+use tracing::{debug, info, warn};
+
+use crate::storage::{Bucket, FileStore};
+use entities::{prelude::*, *};
+
+use super::{
+    connect,
+    tar::{TarEntry, TarReader},
+    BackupItem, BackupManifest, HashWriter, DATABASE_PATH, MANIFEST_PATH, MANIFEST_VERSION,
+};
+
+/// Rebuilds a fresh instance from a [`super::Backup`] archive.
+/// Blobs are uploaded first, so a failed restore never leaves a database pointing at
+/// missing files.
+pub struct Restore {
+    pub storage: Arc<FileStore>,
+    pub database_file: PathBuf,
+    pub archive: PathBuf,
+}
+
+impl Restore {
+    pub async fn run(&self) -> Result<(), anyhow::Error> {
+        if tokio::fs::try_exists(&self.database_file).await? {
+            bail!(
+                "{:?} already exists, restores are only supported on fresh instances",
+                self.database_file
+            );
+        }
+
+        let work_dir = temp_dir().join(format!("cookie-odyssey-restore-{}", nanoid::nanoid!()));
+        tokio::fs::create_dir_all(&work_dir)
+            .await
+            .context("Failed to create work directory for restore")?;
+        let res = self.run_in(&work_dir).await;
+        if let Err(err) = tokio::fs::remove_dir_all(&work_dir).await {
+            warn!("Failed to remove {work_dir:?}: {err}");
+        }
+        res
+    }
+
+    async fn run_in(&self, work_dir: &Path) -> Result<(), anyhow::Error> {
+        let archive = std::fs::File::open(&self.archive)
+            .with_context(|| format!("Failed to open {:?}", self.archive))?;
+        let mut tar = TarReader::new(BufReader::new(archive));
+
+        let manifest: BackupManifest = match tar.next_entry()? {
+            Some(entry) if entry.name == MANIFEST_PATH => {
+                let mut json = Vec::with_capacity(entry.size as usize);
+                tar.copy_data(&entry, &mut json)?;
+                serde_json::from_slice(&json).context("Invalid backup manifest")?
+            }
+            _ => bail!("Not a backup archive: {MANIFEST_PATH} should come first"),
+        };
+        if manifest.version != MANIFEST_VERSION {
+            bail!("Unsupported backup version: {}", manifest.version);
+        }
+        info!(
+            "Restoring backup from {} ({} blobs)",
+            manifest.created_at,
+            manifest.blobs.len()
+        );
+        for blob in &manifest.missing {
+            warn!(
+                "Blob {}/{} was missing at backup time",
+                blob.bucket, blob.key
+            );
+        }
+
+        // Blobs go to the media bucket of this instance, whatever its name was before.
+        let bucket = Bucket::Media.to_name(self.storage.conf()).clone();
+        let mut blobs: HashMap<&str, _> = manifest
+            .blobs
+            .iter()
+            .map(|blob| (blob.item.path.as_str(), blob))
+            .collect();
+        let db_path = work_dir.join(DATABASE_PATH);
+        let mut db_restored = false;
+        while let Some(entry) = tar.next_entry()? {
+            if entry.name == DATABASE_PATH {
+                extract(&mut tar, &entry, &manifest.database, &db_path)?;
+                db_restored = true;
+            } else if let Some(blob) = blobs.remove(entry.name.as_str()) {
+                let path = work_dir.join("blob");
+                extract(&mut tar, &entry, &blob.item, &path)?;
+                self.storage
+                    .upload_file(bucket.clone(), blob.key.clone(), &path)
+                    .await
+                    .with_context(|| format!("Failed to upload {}", blob.key))?;
+                debug!("Restored {}", blob.key);
+            } else {
+                bail!("Unexpected file in backup: {}", entry.name);
+            }
+        }
+        if !db_restored {
+            bail!("The backup has no database");
+        }
+        if let Some(path) = blobs.keys().next() {
+            bail!("The backup is incomplete: {path} not found");
+        }
+
+        tokio::fs::copy(&db_path, &self.database_file)
+            .await
+            .with_context(|| format!("Failed to copy the database to {:?}", self.database_file))?;
+        let url = format!("sqlite://{}?mode=rw", self.database_file.display());
+        let db = connect(url).await?;
+        File::update_many()
+            .col_expr(file::Column::Bucket, Expr::value(bucket))
+            .exec(&db)
+            .await?;
+        db.close().await?;
+
+        info!(
+            "Restored the database and {} blobs. Run the migrations before starting the server.",
+            manifest.blobs.len()
+        );
+        Ok(())
+    }
+}
+
+/// Extracts the entry to `path`, and checks it against the manifest.
+fn extract<R: Read>(
+    tar: &mut TarReader<R>,
+    entry: &TarEntry,
+    expected: &BackupItem,
+    path: &Path,
+) -> Result<(), anyhow::Error> {
+    let file = std::fs::File::create(path).with_context(|| format!("Failed to create {path:?}"))?;
+    let mut writer = HashWriter::new(file);
+    tar.copy_data(entry, &mut writer)?;
+    let (_, item) = writer.finish(entry.name.clone())?;
+    if item.size != expected.size || item.sha256 != expected.sha256 {
+        bail!(
+            "Checksum mismatch for {}, the backup is corrupted",
+            entry.name
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,259 @@
+//! Minimal tar (ustar) archives: regular files only, no compression.
+//! Long names are stored in PAX extended headers.
+//! Enough for backups, and still readable with `tar -xf`.
+
+use std::io::{self, Read, Write};
+
+use anyhow::{bail, Context};
+
+const BLOCK_SIZE: u64 = 512;
+/// Of the name field in the header, which must be NUL-terminated.
+const NAME_LEN: usize = 100;
+
+fn padding(size: u64) -> u64 {
+    (BLOCK_SIZE - size % BLOCK_SIZE) % BLOCK_SIZE
+}
+
+/// Writes `value` as a NUL-terminated octal number, filling `field`.
+fn write_octal(field: &mut [u8], value: u64) -> anyhow::Result<()> {
+    let digits = field.len() - 1;
+    let s = format!("{value:0digits$o}");
+    if s.len() > digits {
+        bail!("Value too large for tar header: {value}");
+    }
+    field[..digits].copy_from_slice(s.as_bytes());
+    field[digits] = 0;
+    Ok(())
+}
+
+/// A `{length} {key}={value}\n` record, the length counting itself.
+fn pax_record(key: &str, value: &str) -> String {
+    let rest = format!(" {key}={value}\n");
+    let mut len = rest.len();
+    while (len.to_string().len() + rest.len()) != len {
+        len = len.to_string().len() + rest.len();
+    }
+    format!("{len}{rest}")
+}
+
+/// Returns the `path` of PAX extended header records, if any.
+fn parse_pax_path(data: &[u8]) -> anyhow::Result<Option<String>> {
+    let mut path = None;
+    let mut rest = data;
+    while !rest.is_empty() {
+        let space = rest
+            .iter()
+            .position(|b| *b == b' ')
+            .context("Invalid PAX record")?;
+        let len: usize = std::str::from_utf8(&rest[..space])?
+            .parse()
+            .context("Invalid PAX record length")?;
+        if len <= space || len > rest.len() {
+            bail!("Invalid PAX record length: {len}");
+        }
+        let record = std::str::from_utf8(&rest[space + 1..len - 1])?;
+        if let Some(value) = record.strip_prefix("path=") {
+            path = Some(value.to_string());
+        }
+        rest = &rest[len..];
+    }
+    Ok(path)
+}
+
+fn read_octal(field: &[u8]) -> anyhow::Result<u64> {
+    let s = std::str::from_utf8(field)?.trim_matches(|c: char| c == '\0' || c == ' ');
+    if s.is_empty() {
+        return Ok(0);
+    }
+    u64::from_str_radix(s, 8).with_context(|| format!("Invalid octal number in tar header: {s}"))
+}
+
+pub struct TarWriter<W: Write> {
+    inner: W,
+}
+
+impl<W: Write> TarWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self { inner }
+    }
+
+    /// Appends a file, whose content is exactly `size` bytes read from `data`.
+    pub fn append(&mut self, name: &str, size: u64, data: &mut impl Read) -> anyhow::Result<()> {
+        let mut short_name = name;
+        if name.len() >= NAME_LEN {
+            let record = pax_record("path", name);
+            self.append_entry(
+                "PaxHeader",
+                b'x',
+                record.len() as u64,
+                &mut record.as_bytes(),
+            )?;
+            // Readers without PAX support get a truncated name.
+            let mut end = NAME_LEN - 1;
+            while !name.is_char_boundary(end) {
+                end -= 1;
+            }
+            short_name = &name[..end];
+        }
+        self.append_entry(short_name, b'0', size, data)
+    }
+
+    fn append_entry(
+        &mut self,
+        name: &str,
+        entry_type: u8,
+        size: u64,
+        data: &mut impl Read,
+    ) -> anyhow::Result<()> {
+        let mut header = [0u8; BLOCK_SIZE as usize];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        write_octal(&mut header[100..108], 0o644)?; // mode
+        write_octal(&mut header[108..116], 0)?; // uid
+        write_octal(&mut header[116..124], 0)?; // gid
+        write_octal(&mut header[124..136], size)?;
+        let mtime = chrono::Utc::now().timestamp().max(0) as u64;
+        write_octal(&mut header[136..148], mtime)?;
+        header[156] = entry_type;
+        header[257..263].copy_from_slice(b"ustar\0");
+        header[263..265].copy_from_slice(b"00");
+        // The checksum is computed with its own field filled with spaces.
+        header[148..156].fill(b' ');
+        let checksum: u64 = header.iter().map(|b| *b as u64).sum();
+        write_octal(&mut header[148..155], checksum)?;
+        self.inner.write_all(&header)?;
+
+        let copied = io::copy(&mut data.take(size), &mut self.inner)?;
+        if copied != size {
+            bail!("Unexpected end of data for {name}: {copied} bytes instead of {size}");
+        }
+        self.inner
+            .write_all(&[0u8; BLOCK_SIZE as usize][..padding(size) as usize])?;
+        Ok(())
+    }
+
+    /// Writes the end-of-archive marker.
+    pub fn finish(mut self) -> anyhow::Result<W> {
+        self.inner.write_all(&[0u8; 2 * BLOCK_SIZE as usize])?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+#[derive(Debug)]
+pub struct TarEntry {
+    pub name: String,
+    pub size: u64,
+}
+
+pub struct TarReader<R: Read> {
+    inner: R,
+    /// Bytes of the current entry (data and padding) that were not consumed.
+    unread: u64,
+}
+
+impl<R: Read> TarReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self { inner, unread: 0 }
+    }
+
+    /// Returns the next file, whose content can then be read with [`Self::copy_data`].
+    /// Unread content is skipped.
+    pub fn next_entry(&mut self) -> anyhow::Result<Option<TarEntry>> {
+        io::copy(&mut (&mut self.inner).take(self.unread), &mut io::sink())?;
+        self.unread = 0;
+
+        let Some((entry_type, mut entry)) = self.read_header()? else {
+            return Ok(None);
+        };
+        if entry_type == b'x' {
+            let mut data = Vec::new();
+            self.copy_data(&entry, &mut data)?;
+            let path = parse_pax_path(&data)?;
+            io::copy(&mut (&mut self.inner).take(self.unread), &mut io::sink())?;
+            self.unread = 0;
+
+            entry = match self.read_header()? {
+                Some((b'0' | 0, next)) => next,
+                _ => bail!("Expected a file after the PAX header of {}", entry.name),
+            };
+            if let Some(path) = path {
+                entry.name = path;
+            }
+        } else if !matches!(entry_type, b'0' | 0) {
+            bail!("Unsupported tar entry type: {}", entry_type as char);
+        }
+        Ok(Some(entry))
+    }
+
+    fn read_header(&mut self) -> anyhow::Result<Option<(u8, TarEntry)>> {
+        let mut header = [0u8; BLOCK_SIZE as usize];
+        self.inner
+            .read_exact(&mut header)
+            .context("Unexpected end of tar archive")?;
+        if header.iter().all(|b| *b == 0) {
+            return Ok(None);
+        }
+
+        let stored: u64 = read_octal(&header[148..156])?;
+        header[148..156].fill(b' ');
+        let checksum: u64 = header.iter().map(|b| *b as u64).sum();
+        if stored != checksum {
+            bail!("Corrupted tar header");
+        }
+
+        let name_len = header[..NAME_LEN]
+            .iter()
+            .position(|b| *b == 0)
+            .unwrap_or(NAME_LEN);
+        let name = String::from_utf8(header[..name_len].to_vec())?;
+        let size = read_octal(&header[124..136])?;
+        self.unread = size + padding(size);
+        Ok(Some((header[156], TarEntry { name, size })))
+    }
+
+    pub fn copy_data(&mut self, entry: &TarEntry, out: &mut impl Write) -> anyhow::Result<()> {
+        let copied = io::copy(&mut (&mut self.inner).take(entry.size), out)?;
+        if copied != entry.size {
+            bail!("Unexpected end of tar archive in {}", entry.name);
+        }
+        self.unread -= copied;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let long_name = format!("blobs/media/{}.jpeg", "a".repeat(200));
+        let files: [(&str, &[u8]); 4] = [
+            ("manifest.json", b"{}"),
+            ("blobs/media/empty.txt", b""),
+            ("blobs/media/block.bin", &[7u8; 512]),
+            (&long_name, b"long"),
+        ];
+        let mut writer = TarWriter::new(Vec::new());
+        for (name, data) in files {
+            writer
+                .append(name, data.len() as u64, &mut &data[..])
+                .unwrap();
+        }
+        let archive = writer.finish().unwrap();
+        assert_eq!(archive.len() % BLOCK_SIZE as usize, 0);
+
+        let mut reader = TarReader::new(&archive[..]);
+        // Skipped without reading.
+        let entry = reader.next_entry().unwrap().unwrap();
+        assert_eq!(entry.name, "manifest.json");
+        for (name, data) in &files[1..] {
+            let entry = reader.next_entry().unwrap().unwrap();
+            assert_eq!(entry.name, *name);
+            let mut out = Vec::new();
+            reader.copy_data(&entry, &mut out).unwrap();
+            assert_eq!(out, *data);
+        }
+        assert!(reader.next_entry().unwrap().is_none());
+    }
+}
@@ -1,8 +1,10 @@
 use itertools::{zip_eq, Itertools};
 use sea_orm::{
-    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, LoaderTrait, QueryFilter, QueryOrder,
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, LoaderTrait,
+    QueryFilter, QueryOrder,
 };
 use serde::Serialize;
+use thiserror::Error;
 
 pub struct AddCommentToJournal {
     pub journal_id: i32,
@@ -14,7 +16,7 @@ pub struct AddCommentToJournal {
 pub async fn add_comment_to_journal(
     params: AddCommentToJournal,
     db: &DatabaseConnection,
-) -> Result<(), DbErr> {
+) -> Result<entities::journal_comment::Model, DbErr> {
     let created_at = chrono::Utc::now().timestamp();
     let data = entities::journal_comment::ActiveModel {
         created_at: sea_orm::ActiveValue::Set(created_at),
@@ -25,7 +27,73 @@ pub async fn add_comment_to_journal(
         id: sea_orm::ActiveValue::NotSet,
     };
 
-    entities::journal_comment::Entity::insert(data)
+    data.insert(db).await
+}
+
+#[derive(Error, Debug)]
+pub enum CommentMutationError {
+    #[error("Comment not found")]
+    NotFound,
+    #[error("You are not allowed to {0} this comment")]
+    Forbidden(&'static str),
+    #[error("db error: {0:?}")]
+    DbError(#[from] DbErr),
+}
+
+/// Fetches the comment, making sure it belongs to the journal.
+async fn query_journal_comment(
+    journal_id: i32,
+    comment_id: i32,
+    db: &DatabaseConnection,
+) -> Result<entities::journal_comment::Model, CommentMutationError> {
+    entities::journal_comment::Entity::find_by_id(comment_id)
+        .filter(entities::journal_comment::Column::JournalId.eq(journal_id))
+        .one(db)
+        .await?
+        .ok_or(CommentMutationError::NotFound)
+}
+
+pub struct EditJournalComment {
+    pub journal_id: i32,
+    pub comment_id: i32,
+    pub text: String,
+}
+
+/// Only the author can edit their comment.
+pub async fn edit_journal_comment(
+    params: EditJournalComment,
+    user: &entities::user::Model,
+    db: &DatabaseConnection,
+) -> Result<(), CommentMutationError> {
+    let comment = query_journal_comment(params.journal_id, params.comment_id, db).await?;
+    if comment.user_id != user.id {
+        return Err(CommentMutationError::Forbidden("edit"));
+    }
+
+    let data = entities::journal_comment::ActiveModel {
+        id: sea_orm::ActiveValue::Unchanged(comment.id),
+        text: sea_orm::ActiveValue::Set(params.text),
+        ..Default::default()
+    };
+    entities::journal_comment::Entity::update(data)
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+/// The author or an admin can delete a comment.
+pub async fn delete_journal_comment(
+    journal_id: i32,
+    comment_id: i32,
+    user: &entities::user::Model,
+    db: &DatabaseConnection,
+) -> Result<(), CommentMutationError> {
+    let comment = query_journal_comment(journal_id, comment_id, db).await?;
+    if comment.user_id != user.id && !user.admin {
+        return Err(CommentMutationError::Forbidden("delete"));
+    }
+
+    entities::journal_comment::Entity::delete_by_id(comment.id)
         .exec(db)
         .await?;
     Ok(())
@@ -77,3 +145,77 @@ pub async fn query_comments_for_journal(
 
     Ok(ret)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::testing::{insert_journal, insert_user, test_db};
+
+    async fn setup() -> (
+        DatabaseConnection,
+        entities::journal_comment::Model,
+        entities::user::Model,
+        entities::user::Model,
+    ) {
+        let db = test_db().await;
+        let journal = insert_journal(&db, "lisbon").await;
+        let author = insert_user(&db, "author@example.com", false).await;
+        let other = insert_user(&db, "other@example.com", false).await;
+        let params = AddCommentToJournal {
+            journal_id: journal.id,
+            user_id: author.id,
+            text: "Hello".to_string(),
+            date: None,
+        };
+        let comment = add_comment_to_journal(params, &db).await.unwrap();
+        (db, comment, author, other)
+    }
+
+    async fn query_text(comment_id: i32, db: &DatabaseConnection) -> Option<String> {
+        entities::journal_comment::Entity::find_by_id(comment_id)
+            .one(db)
+            .await
+            .unwrap()
+            .map(|comment| comment.text)
+    }
+
+    #[tokio::test]
+    async fn test_edit_by_other_user() {
+        let (db, comment, author, other) = setup().await;
+        let edit = |text: &str| EditJournalComment {
+            journal_id: comment.journal_id,
+            comment_id: comment.id,
+            text: text.to_string(),
+        };
+
+        let res = edit_journal_comment(edit("Hacked"), &other, &db).await;
+        assert!(matches!(res, Err(CommentMutationError::Forbidden("edit"))));
+        assert_eq!(query_text(comment.id, &db).await.unwrap(), "Hello");
+
+        edit_journal_comment(edit("Hello!"), &author, &db)
+            .await
+            .unwrap();
+        assert_eq!(query_text(comment.id, &db).await.unwrap(), "Hello!");
+    }
+
+    #[tokio::test]
+    async fn test_delete_by_other_user() {
+        let (db, comment, _, other) = setup().await;
+        let res = delete_journal_comment(comment.journal_id, comment.id, &other, &db).await;
+        assert!(matches!(
+            res,
+            Err(CommentMutationError::Forbidden("delete"))
+        ));
+        assert!(query_text(comment.id, &db).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_delete_by_admin() {
+        let (db, comment, _, _) = setup().await;
+        let admin = insert_user(&db, "admin@example.com", true).await;
+        delete_journal_comment(comment.journal_id, comment.id, &admin, &db)
+            .await
+            .unwrap();
+        assert!(query_text(comment.id, &db).await.is_none());
+    }
+}
@@ -4,13 +4,15 @@ use axum::{
     Form,
 };
 use minijinja::context;
-use sea_orm::{DatabaseConnection, EntityTrait};
+use sea_orm::DatabaseConnection;
 use serde::{Deserialize, Serialize};
 
-use entities::{prelude::*, *};
-
 use crate::{
-    comment::queries::{add_comment_to_journal, query_comments_for_journal, AddCommentToJournal},
+    comment::queries::{
+        add_comment_to_journal, delete_journal_comment, edit_journal_comment,
+        query_comments_for_journal, AddCommentToJournal, CommentMutationError, EditJournalComment,
+    },
+    notification::queries::notify_comment_added,
     AppState, AuthSession, Route, RouteError, RouteResult, Templ, Toast,
 };
 
@@ -39,6 +41,13 @@ pub async fn journal_comment_add_post(
         }
         Ok(form) => form,
     };
+    if !session
+        .backend
+        .can_view_journal(&session, query.journal_id, &state.db)
+        .await?
+    {
+        return Ok(Toast::danger("Journal not found").into_response());
+    }
 
     let text = form.0.text;
     let user_id = session.user.as_ref().expect("Should be authenticated").0.id;
@@ -49,7 +58,11 @@ pub async fn journal_comment_add_post(
         user_id,
     };
 
-    add_comment_to_journal(params, &state.db).await?;
+    let comment = add_comment_to_journal(params, &state.db).await?;
+    if state.mailer.is_some() {
+        notify_comment_added(&comment, &state.db).await?;
+        state.notifier.wake();
+    }
 
     let html = CommentList {
         journal_id: query.journal_id,
@@ -88,14 +101,23 @@ pub async fn journal_comment_edit_post(
         }
         Ok(form) => form,
     };
+    if !session
+        .backend
+        .can_view_journal(&session, query.journal_id, &state.db)
+        .await?
+    {
+        return Ok(Toast::danger("Journal not found").into_response());
+    }
 
-    let data = journal_comment::ActiveModel {
-        id: sea_orm::ActiveValue::Set(form.comment_id),
-        text: sea_orm::ActiveValue::Set(form.0.text),
-        ..Default::default()
+    let user = &session.user.as_ref().expect("Should be authenticated").0;
+    let params = EditJournalComment {
+        journal_id: query.journal_id,
+        comment_id: form.comment_id,
+        text: form.0.text,
     };
-    // TODO [perms] Verify permissions server-side.
-    JournalComment::update(data).exec(&state.db).await?;
+    if let Err(err) = edit_journal_comment(params, user, &state.db).await {
+        return comment_mutation_error(err);
+    }
 
     let html = CommentList {
         journal_id: query.journal_id,
@@ -133,11 +155,20 @@ pub async fn journal_comment_delete_post(
         }
         Ok(form) => form,
     };
+    if !session
+        .backend
+        .can_view_journal(&session, query.journal_id, &state.db)
+        .await?
+    {
+        return Ok(Toast::danger("Journal not found").into_response());
+    }
 
-    // TODO [perms] Verify permissions server-side.
-    JournalComment::delete_by_id(form.comment_id)
-        .exec(&state.db)
-        .await?;
+    let user = &session.user.as_ref().expect("Should be authenticated").0;
+    if let Err(err) =
+        delete_journal_comment(query.journal_id, form.comment_id, user, &state.db).await
+    {
+        return comment_mutation_error(err);
+    }
 
     let html = CommentList {
         journal_id: query.journal_id,
@@ -150,6 +181,13 @@ pub async fn journal_comment_delete_post(
     Ok(html.into_response())
 }
 
+fn comment_mutation_error(err: CommentMutationError) -> RouteResult {
+    match err {
+        CommentMutationError::DbError(err) => Err(err.into()),
+        err => Ok(Toast::danger(err.to_string()).into_response()),
+    }
+}
+
 pub struct CommentList {
     pub journal_id: i32,
     pub date: Option<chrono::NaiveDate>,
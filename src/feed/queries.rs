@@ -0,0 +1,58 @@
+//! Secret tokens for reading feeds, since feed readers can't log in.
+use sea_orm::{
+    sea_query::OnConflict, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter,
+};
+
+use entities::{prelude::*, *};
+
+/// Returns the user's token, creating it on first use.
+pub async fn get_or_create_feed_token(
+    user_id: i32,
+    db: &DatabaseConnection,
+) -> Result<String, DbErr> {
+    let data = feed_token::ActiveModel {
+        user_id: sea_orm::ActiveValue::Set(user_id),
+        token: sea_orm::ActiveValue::Set(nanoid::nanoid!(32)),
+        created_at: sea_orm::ActiveValue::Set(chrono::Utc::now()),
+        id: sea_orm::ActiveValue::NotSet,
+    };
+    FeedToken::insert(data)
+        .on_conflict(
+            OnConflict::column(feed_token::Column::UserId)
+                .do_nothing()
+                .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await?;
+
+    let token = FeedToken::find()
+        .filter(feed_token::Column::UserId.eq(user_id))
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("feed_token".into()))?;
+    Ok(token.token)
+}
+
+/// Revokes the user's feed URLs. A new token is created on next use.
+pub async fn reset_feed_token(user_id: i32, db: &DatabaseConnection) -> Result<(), DbErr> {
+    FeedToken::delete_many()
+        .filter(feed_token::Column::UserId.eq(user_id))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+/// Returns the owner of the token, unless they're not approved (anymore).
+pub async fn query_user_by_feed_token(
+    token: &str,
+    db: &DatabaseConnection,
+) -> Result<Option<user::Model>, DbErr> {
+    let user = FeedToken::find()
+        .filter(feed_token::Column::Token.eq(token))
+        .find_also_related(User)
+        .one(db)
+        .await?
+        .and_then(|(_, user)| user)
+        .filter(|user| user.approved);
+    Ok(user)
+}
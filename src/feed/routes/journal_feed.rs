@@ -0,0 +1,178 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{Html, IntoResponse as _},
+    Form,
+};
+use minijinja::{context, HtmlEscape};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    feed::queries::{get_or_create_feed_token, query_user_by_feed_token, reset_feed_token},
+    journal::{
+        queries::{query_journal_by_slug, query_media_for_journal_entry, MediaFull},
+        routes::JournalDayGetPath,
+    },
+    template_engine::render_markdown,
+    AppState, AuthSession, AuthUser, Route, RouteError, RouteResult, Templ, Toast,
+};
+use entities::{prelude::*, *};
+
+/// Feed readers only care about recent entries.
+const FEED_LIMIT: u64 = 50;
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct JournalFeedQuery {
+    pub token: String,
+}
+
+#[derive(Serialize, Debug)]
+struct FeedEntry {
+    id: i32,
+    title: String,
+    datetime: chrono::NaiveDateTime,
+    author: Option<String>,
+    /// HTML, to be escaped in the feed.
+    content: String,
+    href: String,
+}
+
+/// The rendered text, followed by the thumbnails (linking to the originals).
+fn render_content(text: &str, media: &[MediaFull]) -> String {
+    let mut html = render_markdown(text);
+    for media in media {
+        html.push_str(&format!(
+            r#"<p><a href="{}"><img src="{}" alt="{}"></a></p>"#,
+            HtmlEscape(&media.url_original),
+            HtmlEscape(&media.url_thumbnail),
+            HtmlEscape(&media.caption),
+        ));
+    }
+    html
+}
+
+/// Atom feed of the published entries, authenticated with the user's feed token
+/// (outside `login_required!`).
+pub async fn journal_feed_get(
+    state: State<AppState>,
+    templ: Templ,
+    session: AuthSession,
+    Path(slug): Path<String>,
+    Query(query): Query<JournalFeedQuery>,
+) -> RouteResult {
+    let user = match query_user_by_feed_token(&query.token, &state.db).await? {
+        Some(user) => AuthUser(user),
+        None => return Ok(StatusCode::NOT_FOUND.into_response()),
+    };
+    // The session is anonymous, but the backend still works.
+    let backend = &session.backend;
+
+    let q = Journal::find().filter(journal::Column::Slug.eq(&slug));
+    let journal = backend
+        .filter_journals_for_user(&user, q)
+        .await?
+        .one(&state.db)
+        .await?;
+    let journal = match journal {
+        Some(journal) => journal,
+        None => return Ok(StatusCode::NOT_FOUND.into_response()),
+    };
+
+    let q = JournalEntry::find()
+        .filter(journal_entry::Column::JournalId.eq(journal.id))
+        // Even for their authors.
+        .filter(journal_entry::Column::Draft.eq(false))
+        .order_by_desc(journal_entry::Column::Date)
+        .order_by_desc(journal_entry::Column::Time)
+        .limit(FEED_LIMIT);
+    let entries_db = backend
+        .filter_journal_entries_for_user(&user, q)
+        .await?
+        .find_also_related(User)
+        .all(&state.db)
+        .await?;
+
+    let mut entries = Vec::with_capacity(entries_db.len());
+    for (entry, author) in entries_db {
+        let media = query_media_for_journal_entry(entry.id, &state.db, &state.storage).await?;
+        let href = Route::JournalDayGet(Some(&JournalDayGetPath {
+            slug: journal.slug.clone(),
+            date: entry.date,
+        }))
+        .as_url(&state.server_name);
+        entries.push(FeedEntry {
+            id: entry.id,
+            title: entry.title,
+            datetime: chrono::NaiveDateTime::new(entry.date, entry.time),
+            author: author.map(|author| format!("{} {}", author.first_name, author.last_name)),
+            content: render_content(&entry.text, &media),
+            href,
+        });
+    }
+
+    let updated = entries
+        .first()
+        .map(|entry| entry.datetime)
+        .unwrap_or_else(|| chrono::NaiveDateTime::new(journal.start_date, Default::default()));
+    let ctx = context! {
+        href_self => Route::JournalFeedGet { slug: Some(&journal.slug), query: Some(&query) }
+            .as_url(&state.server_name),
+        href_journal_detail => Route::JournalDetailGet { slug: Some(&journal.slug) }
+            .as_url(&state.server_name),
+        journal,
+        updated,
+        entries,
+    };
+    let body = templ.render_ctx("feed/journal_feed.xml", ctx)?;
+    let resp = (
+        [(header::CONTENT_TYPE, "application/atom+xml; charset=utf-8")],
+        body.0,
+    );
+    Ok(resp.into_response())
+}
+
+/// Feed URL of the journal for the current user, with the link to reset it.
+pub async fn render_journal_feed_link(
+    state: &AppState,
+    templ: &Templ,
+    session: &AuthSession,
+    journal: &journal::Model,
+) -> Result<Html<String>, RouteError> {
+    let user = session.user.as_ref().expect("Should be authenticated");
+    let token = get_or_create_feed_token(user.0.id, &state.db).await?;
+    let ctx = context! {
+        journal,
+        href_journal_feed => Route::JournalFeedGet {
+            slug: Some(&journal.slug),
+            query: Some(&JournalFeedQuery { token }),
+        }
+        .as_url(&state.server_name),
+        href_feed_token_reset => Route::FeedTokenResetPost.as_path(),
+    };
+    templ.render_ctx("feed/journal_feed_link.html", ctx)
+}
+
+#[derive(Deserialize, Debug)]
+pub struct FeedTokenResetPost {
+    slug: String,
+}
+
+/// Revokes the feed URLs of the user, for all journals.
+pub async fn feed_token_reset_post(
+    state: AppState,
+    templ: Templ,
+    session: AuthSession,
+    form: Form<FeedTokenResetPost>,
+) -> RouteResult {
+    let journal = match query_journal_by_slug(form.0.slug, &state.db, &session).await? {
+        Ok(journal) => journal,
+        Err(_) => return Ok(Toast::danger("Journal not found").into_response()),
+    };
+    let user = session.user.as_ref().expect("Should be authenticated");
+    reset_feed_token(user.0.id, &state.db).await?;
+
+    let html = render_journal_feed_link(&state, &templ, &session, &journal).await?;
+    let toast = Toast::success("Your feed links have been reset");
+    Ok((toast.into_headers(), html).into_response())
+}
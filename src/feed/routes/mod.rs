@@ -0,0 +1,3 @@
+mod journal_feed;
+
+pub use journal_feed::*;
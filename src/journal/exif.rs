@@ -0,0 +1,107 @@
+use std::{env::temp_dir, io::Cursor};
+
+use anyhow::Context;
+use exif::{In, Tag, Value};
+
+use crate::storage::FileStore;
+use entities::file;
+
+/// Metadata we keep from a photo's EXIF.
+#[derive(Debug, Default, PartialEq)]
+pub struct ExifData {
+    /// Local time of the camera.
+    pub taken_at: Option<chrono::NaiveDateTime>,
+    pub lat: Option<f32>,
+    pub lng: Option<f32>,
+    pub camera_model: Option<String>,
+    /// 1-8, see https://exiftool.org/TagNames/EXIF.html
+    pub orientation: Option<i32>,
+}
+
+/// Downloads the file from storage, and reads its EXIF.
+pub async fn read_exif_from_storage(
+    file: &file::Model,
+    storage: &FileStore,
+) -> anyhow::Result<ExifData> {
+    let path = temp_dir().join(format!("cookie-odyssey-exif-{}", nanoid::nanoid!()));
+    storage
+        .download_to_file(file.bucket.clone(), file.key.clone(), &path)
+        .await?;
+    let bytes = tokio::fs::read(&path).await;
+    // Best effort.
+    let _ = tokio::fs::remove_file(&path).await;
+    let bytes = bytes.with_context(|| format!("Failed to read {path:?}"))?;
+    parse_exif(&bytes)
+}
+
+pub fn parse_exif(bytes: &[u8]) -> anyhow::Result<ExifData> {
+    let exif = exif::Reader::new()
+        .read_from_container(&mut Cursor::new(bytes))
+        .context("Failed to read EXIF")?;
+
+    let ascii = |tag: Tag| -> Option<String> {
+        match &exif.get_field(tag, In::PRIMARY)?.value {
+            Value::Ascii(values) => {
+                let value = String::from_utf8_lossy(values.first()?);
+                let value = value.trim_matches(char::from(0)).trim();
+                (!value.is_empty()).then(|| value.to_string())
+            }
+            _ => None,
+        }
+    };
+    let coordinate = |tag: Tag, ref_tag: Tag, negative_ref: &str| -> Option<f32> {
+        let degrees = match &exif.get_field(tag, In::PRIMARY)?.value {
+            Value::Rational(parts) if parts.len() == 3 => {
+                parts[0].to_f64() + parts[1].to_f64() / 60.0 + parts[2].to_f64() / 3600.0
+            }
+            _ => return None,
+        };
+        let sign = match ascii(ref_tag) {
+            Some(r) if r.eq_ignore_ascii_case(negative_ref) => -1.0,
+            _ => 1.0,
+        };
+        degrees.is_finite().then_some((sign * degrees) as f32)
+    };
+
+    let taken_at = ascii(Tag::DateTimeOriginal)
+        .or_else(|| ascii(Tag::DateTime))
+        .and_then(|s| chrono::NaiveDateTime::parse_from_str(&s, "%Y:%m:%d %H:%M:%S").ok());
+    let orientation = exif
+        .get_field(Tag::Orientation, In::PRIMARY)
+        .and_then(|field| field.value.get_uint(0))
+        .map(|o| o as i32);
+
+    Ok(ExifData {
+        taken_at,
+        lat: coordinate(Tag::GPSLatitude, Tag::GPSLatitudeRef, "S"),
+        lng: coordinate(Tag::GPSLongitude, Tag::GPSLongitudeRef, "W"),
+        camera_model: ascii(Tag::Model),
+        orientation,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_exif() {
+        let bytes = std::fs::read("test_data/exif/gps.jpg").unwrap();
+        let exif = parse_exif(&bytes).unwrap();
+        assert_eq!(
+            exif.taken_at,
+            chrono::NaiveDate::from_ymd_opt(2026, 1, 2)
+                .unwrap()
+                .and_hms_opt(10, 30, 15)
+        );
+        assert!((exif.lat.unwrap() - 38.708_332).abs() < 1e-4);
+        assert!((exif.lng.unwrap() + 9.137_5).abs() < 1e-4);
+        assert_eq!(exif.camera_model.as_deref(), Some("Pixel 7"));
+        assert_eq!(exif.orientation, Some(6));
+    }
+
+    #[test]
+    fn test_parse_exif_missing() {
+        assert!(parse_exif(b"not an image").is_err());
+    }
+}
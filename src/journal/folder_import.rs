@@ -0,0 +1,256 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    env::temp_dir,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::Context;
+use chrono::{NaiveDate, NaiveDateTime};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter,
+};
+use tracing::{info, warn};
+
+use crate::{
+    journal::{
+        exif::{parse_exif, ExifData},
+        queries::append_journal_entry_media,
+        routes::{JournalEntryMediaCommitBody, JournalEntryMediaCommitItem},
+        thumbnail::{generate_thumbnail, THUMBNAIL_EXT},
+    },
+    storage::{Bucket, FileStore},
+    video_transcoding::daemon::VideoTranscoder,
+};
+use entities::{journal_entry_media::MediaType, prelude::*, *};
+
+const IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp", "gif"];
+const VIDEO_EXTENSIONS: &[&str] = &["mp4", "mov", "m4v", "webm", "3gp", "mkv"];
+
+/// Imports a folder of photos and videos into a journal, as one draft entry per day.
+/// Files are dated by their EXIF, or their modification time.
+pub struct FolderImport {
+    pub db: DatabaseConnection,
+    pub storage: Arc<FileStore>,
+    pub video_transcoder: Arc<VideoTranscoder>,
+    pub slug: String,
+    pub dir: PathBuf,
+    pub author_email: Option<String>,
+}
+
+struct FolderFile {
+    path: PathBuf,
+    media_type: MediaType,
+    /// From the EXIF, or the modification time.
+    taken_at: NaiveDateTime,
+    /// Parsed once here, rather than downloaded again when committed.
+    exif: ExifData,
+}
+
+impl FolderImport {
+    pub async fn run(&self) -> Result<(), anyhow::Error> {
+        let work_dir = temp_dir().join(format!("cookie-odyssey-folder-{}", nanoid::nanoid!()));
+        tokio::fs::create_dir_all(&work_dir)
+            .await
+            .context("Failed to create work directory for import")?;
+        let res = self.run_in(&work_dir).await;
+        if let Err(err) = tokio::fs::remove_dir_all(&work_dir).await {
+            warn!("Failed to remove {work_dir:?}: {err}");
+        }
+        res
+    }
+
+    async fn run_in(&self, work_dir: &Path) -> Result<(), anyhow::Error> {
+        let journal = Journal::find()
+            .filter(journal::Column::Slug.eq(&self.slug))
+            .one(&self.db)
+            .await?
+            .with_context(|| format!("Journal not found: {}", self.slug))?;
+        let author_id = match &self.author_email {
+            Some(email) => {
+                let user = User::find()
+                    .filter(user::Column::Email.eq(email))
+                    .one(&self.db)
+                    .await?
+                    .with_context(|| format!("User not found: {email}"))?;
+                Some(user.id)
+            }
+            None => None,
+        };
+
+        let mut paths = Vec::new();
+        list_files(&self.dir, &mut paths)
+            .with_context(|| format!("Failed to list {:?}", self.dir))?;
+        let mut days: BTreeMap<NaiveDate, Vec<FolderFile>> = BTreeMap::new();
+        for path in paths {
+            match read_file(path).await? {
+                Some(file) => days.entry(file.taken_at.date()).or_default().push(file),
+                None => continue,
+            }
+        }
+        if days.is_empty() {
+            info!("No photos or videos found in {:?}", self.dir);
+            return Ok(());
+        }
+
+        let bucket = Bucket::Media.to_name(self.storage.conf()).clone();
+        let mut imported = 0;
+        for (date, mut files) in days {
+            files.sort_by_key(|file| file.taken_at);
+            let count = files.len();
+            let mut uploaded = Vec::with_capacity(count);
+            for file in files {
+                match self.upload(&file, &bucket, work_dir).await {
+                    Ok(item) => uploaded.push((file, item)),
+                    Err(err) => warn!("Skipping {:?}: {err:#}", file.path),
+                }
+            }
+            // No empty drafts.
+            let Some((first, _)) = uploaded.first() else {
+                warn!("{date}: none of the {count} files could be uploaded");
+                continue;
+            };
+
+            let location = uploaded
+                .iter()
+                .find_map(|(file, _)| file.exif.lat.zip(file.exif.lng));
+            let data = journal_entry::ActiveModel {
+                journal_id: ActiveValue::Set(journal.id),
+                author_id: ActiveValue::Set(author_id),
+                title: ActiveValue::Set(date.format("%A %-d %B").to_string()),
+                date: ActiveValue::Set(date),
+                time: ActiveValue::Set(first.taken_at.time()),
+                draft: ActiveValue::Set(true),
+                lat: ActiveValue::Set(location.map(|(lat, _)| lat)),
+                lng: ActiveValue::Set(location.map(|(_, lng)| lng)),
+                ..Default::default()
+            };
+            let entry = data.insert(&self.db).await?;
+
+            let mut exif = HashMap::with_capacity(uploaded.len());
+            let mut items = Vec::with_capacity(uploaded.len());
+            for (file, item) in uploaded {
+                exif.insert(item.file_id_original, file.exif);
+                items.push(item);
+            }
+            imported += items.len();
+            info!("{date}: {} of {count} files", items.len());
+            let body = JournalEntryMediaCommitBody {
+                entry_id: entry.id,
+                items,
+            };
+            append_journal_entry_media(
+                &body,
+                exif,
+                &self.db,
+                &self.storage,
+                &self.video_transcoder,
+            )
+            .await?;
+        }
+
+        info!("Imported {imported} files into '{}'", self.slug);
+        Ok(())
+    }
+
+    /// Uploads the file and its thumbnail, named like browser uploads.
+    async fn upload(
+        &self,
+        file: &FolderFile,
+        bucket: &str,
+        work_dir: &Path,
+    ) -> Result<JournalEntryMediaCommitItem, anyhow::Error> {
+        let thumbnail_path = work_dir.join(format!("thumbnail{THUMBNAIL_EXT}"));
+        let thumbnail =
+            generate_thumbnail(&file.path, &thumbnail_path, file.exif.orientation).await?;
+
+        let key_original = match file.path.extension() {
+            Some(ext) => format!(
+                "{}.{}",
+                nanoid::nanoid!(),
+                ext.to_string_lossy().to_lowercase()
+            ),
+            None => nanoid::nanoid!(),
+        };
+        let key_thumbnail = format!("{}_thumbnail{THUMBNAIL_EXT}", nanoid::nanoid!());
+        let mut file_ids = Vec::with_capacity(2);
+        for (key, path) in [(key_original, &file.path), (key_thumbnail, &thumbnail_path)] {
+            self.storage
+                .upload_file(bucket.to_string(), key.clone(), path)
+                .await
+                .with_context(|| format!("Failed to upload {key}"))?;
+            let data = file::ActiveModel {
+                bucket: ActiveValue::Set(bucket.to_string()),
+                key: ActiveValue::Set(key),
+                uploaded_by: ActiveValue::Set(None),
+                id: ActiveValue::NotSet,
+            };
+            file_ids.push(data.insert(&self.db).await?.id);
+        }
+
+        Ok(JournalEntryMediaCommitItem {
+            media_type: file.media_type,
+            file_id_original: file_ids[0],
+            width_original: thumbnail.width_original,
+            height_original: thumbnail.height_original,
+            file_id_thumbnail: file_ids[1],
+            width_thumbnail: thumbnail.width,
+            height_thumbnail: thumbnail.height,
+        })
+    }
+}
+
+/// Recursively, skipping hidden files.
+fn list_files(dir: &Path, paths: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            list_files(&path, paths)?;
+        } else {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Returns `None` for files that are neither photos nor videos.
+async fn read_file(path: PathBuf) -> Result<Option<FolderFile>, anyhow::Error> {
+    let ext = match path.extension() {
+        Some(ext) => ext.to_string_lossy().to_lowercase(),
+        None => return Ok(None),
+    };
+    let media_type = if IMAGE_EXTENSIONS.contains(&ext.as_str()) {
+        MediaType::Image
+    } else if VIDEO_EXTENSIONS.contains(&ext.as_str()) {
+        MediaType::Video
+    } else {
+        return Ok(None);
+    };
+
+    let exif = match media_type {
+        MediaType::Image => {
+            let bytes = tokio::fs::read(&path)
+                .await
+                .with_context(|| format!("Failed to read {path:?}"))?;
+            parse_exif(&bytes).unwrap_or_default()
+        }
+        MediaType::Video => Default::default(),
+    };
+    let taken_at = match exif.taken_at {
+        Some(taken_at) => taken_at,
+        None => {
+            let modified = tokio::fs::metadata(&path).await?.modified()?;
+            chrono::DateTime::<chrono::Local>::from(modified).naive_local()
+        }
+    };
+    Ok(Some(FolderFile {
+        path,
+        media_type,
+        taken_at,
+        exif,
+    }))
+}
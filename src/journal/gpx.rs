@@ -0,0 +1,226 @@
+use anyhow::{anyhow, Context};
+use serde::{Deserialize, Serialize};
+
+/// Mean Earth radius.
+const EARTH_RADIUS_M: f64 = 6_371_008.8;
+/// Tolerance of the line simplification, ~5m.
+const SIMPLIFY_TOLERANCE_M: f64 = 5.0;
+/// Climbs smaller than this are treated as GPS noise.
+const ELEVATION_THRESHOLD_M: f64 = 3.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TrackPoint {
+    pub lat: f64,
+    pub lng: f64,
+    #[serde(skip)]
+    pub ele: Option<f64>,
+}
+
+#[derive(Debug)]
+pub struct GpxTrack {
+    /// From `<trk><name>`, if any.
+    pub name: Option<String>,
+    pub distance_m: f64,
+    pub elevation_gain_m: f64,
+    /// Simplified, one line per segment.
+    pub segments: Vec<Vec<[f64; 2]>>,
+}
+
+/// Parses tracks and routes of a GPX file into simplified lines.
+pub fn parse_gpx(text: &str) -> anyhow::Result<GpxTrack> {
+    let doc = roxmltree::Document::parse(text).context("Invalid XML")?;
+    let root = doc.root_element();
+    if root.tag_name().name() != "gpx" {
+        return Err(anyhow!("Not a GPX file"));
+    }
+
+    let name = root
+        .descendants()
+        .find(|n| n.has_tag_name("trk") || n.has_tag_name("rte"))
+        .and_then(|n| n.children().find(|c| c.has_tag_name("name")))
+        .and_then(|n| n.text())
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty());
+
+    // Track segments and routes are continuous lines, but they're not connected to each other.
+    let mut raw_segments: Vec<Vec<TrackPoint>> = Vec::new();
+    for node in root.descendants() {
+        let point_tag = match node.tag_name().name() {
+            "trkseg" => "trkpt",
+            "rte" => "rtept",
+            _ => continue,
+        };
+        let points = node
+            .children()
+            .filter(|c| c.has_tag_name(point_tag))
+            .map(|c| -> anyhow::Result<TrackPoint> {
+                let attr = |name: &str| -> anyhow::Result<f64> {
+                    c.attribute(name)
+                        .with_context(|| format!("Missing {name}"))?
+                        .parse()
+                        .with_context(|| format!("Invalid {name}"))
+                };
+                let ele = c
+                    .children()
+                    .find(|e| e.has_tag_name("ele"))
+                    .and_then(|e| e.text())
+                    .and_then(|e| e.trim().parse().ok());
+                Ok(TrackPoint {
+                    lat: attr("lat")?,
+                    lng: attr("lon")?,
+                    ele,
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        if !points.is_empty() {
+            raw_segments.push(points);
+        }
+    }
+    if raw_segments.is_empty() {
+        return Err(anyhow!("The GPX file has no track points"));
+    }
+
+    let distance_m = raw_segments
+        .iter()
+        .flat_map(|points| points.windows(2))
+        .map(|w| distance(&w[0], &w[1]))
+        .sum();
+    let elevation_gain_m = raw_segments.iter().map(|s| elevation_gain(s)).sum();
+    let segments = raw_segments
+        .iter()
+        .map(|points| {
+            simplify(points, SIMPLIFY_TOLERANCE_M)
+                .into_iter()
+                .map(|p| [p.lat, p.lng])
+                .collect()
+        })
+        .collect();
+
+    Ok(GpxTrack {
+        name,
+        distance_m,
+        elevation_gain_m,
+        segments,
+    })
+}
+
+/// Haversine.
+fn distance(a: &TrackPoint, b: &TrackPoint) -> f64 {
+    let (lat1, lat2) = (a.lat.to_radians(), b.lat.to_radians());
+    let dlat = lat2 - lat1;
+    let dlng = (b.lng - a.lng).to_radians();
+    let h = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlng / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_M * h.sqrt().asin()
+}
+
+/// Only counts climbs above [`ELEVATION_THRESHOLD_M`], so jittery altitudes don't add up.
+fn elevation_gain(points: &[TrackPoint]) -> f64 {
+    let mut gain = 0.0;
+    let mut reference: Option<f64> = None;
+    for ele in points.iter().filter_map(|p| p.ele) {
+        match reference {
+            None => reference = Some(ele),
+            Some(r) if ele < r => reference = Some(ele),
+            Some(r) if ele - r >= ELEVATION_THRESHOLD_M => {
+                gain += ele - r;
+                reference = Some(ele);
+            }
+            Some(_) => {}
+        }
+    }
+    gain
+}
+
+/// Distance from `p` to the segment `a`-`b`, on a local flat projection (fine at these scales).
+fn distance_to_segment(p: &TrackPoint, a: &TrackPoint, b: &TrackPoint) -> f64 {
+    let scale_x = EARTH_RADIUS_M * a.lat.to_radians().cos();
+    let project = |q: &TrackPoint| {
+        (
+            (q.lng - a.lng).to_radians() * scale_x,
+            (q.lat - a.lat).to_radians() * EARTH_RADIUS_M,
+        )
+    };
+    let (px, py) = project(p);
+    let (bx, by) = project(b);
+    let len2 = bx * bx + by * by;
+    let t = if len2 == 0.0 {
+        0.0
+    } else {
+        ((px * bx + py * by) / len2).clamp(0.0, 1.0)
+    };
+    ((px - t * bx).powi(2) + (py - t * by).powi(2)).sqrt()
+}
+
+/// Ramer-Douglas-Peucker.
+fn simplify(points: &[TrackPoint], tolerance_m: f64) -> Vec<TrackPoint> {
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+    let mut stack = vec![(0, points.len() - 1)];
+    while let Some((start, end)) = stack.pop() {
+        let (index, max) = (start + 1..end)
+            .map(|i| {
+                (
+                    i,
+                    distance_to_segment(&points[i], &points[start], &points[end]),
+                )
+            })
+            .fold((start, 0.0), |acc, x| if x.1 > acc.1 { x } else { acc });
+        if max > tolerance_m {
+            keep[index] = true;
+            stack.push((start, index));
+            stack.push((index, end));
+        }
+    }
+    points
+        .iter()
+        .zip(keep)
+        .filter_map(|(p, keep)| keep.then_some(*p))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_gpx() {
+        let gpx = r#"<?xml version="1.0"?>
+            <gpx version="1.1" xmlns="http://www.topografix.com/GPX/1/1">
+              <trk>
+                <name>Sintra loop</name>
+                <trkseg>
+                  <trkpt lat="38.0" lon="-9.0"><ele>100</ele></trkpt>
+                  <trkpt lat="38.0005" lon="-9.0"><ele>101</ele></trkpt>
+                  <trkpt lat="38.001" lon="-9.0"><ele>110</ele></trkpt>
+                  <trkpt lat="38.002" lon="-9.0"><ele>105</ele></trkpt>
+                  <trkpt lat="38.002" lon="-9.001"><ele>112</ele></trkpt>
+                </trkseg>
+              </trk>
+            </gpx>"#;
+        let track = parse_gpx(gpx).unwrap();
+        assert_eq!(track.name.as_deref(), Some("Sintra loop"));
+        // 0.002° of latitude + 0.001° of longitude at 38°N.
+        assert!(
+            (track.distance_m - 310.0).abs() < 1.0,
+            "{}",
+            track.distance_m
+        );
+        assert_eq!(track.elevation_gain_m, 17.0);
+        // The points in the middle of the straight line are dropped.
+        assert_eq!(
+            track.segments,
+            vec![vec![[38.0, -9.0], [38.002, -9.0], [38.002, -9.001]]]
+        );
+    }
+
+    #[test]
+    fn test_parse_gpx_invalid() {
+        assert!(parse_gpx("<kml></kml>").is_err());
+        assert!(parse_gpx("<gpx></gpx>").is_err());
+        assert!(parse_gpx(r#"<gpx><rte><rtept lat="x" lon="1"/></rte></gpx>"#).is_err());
+    }
+}
@@ -1,2 +1,12 @@
-mod queries;
+mod exif;
+mod folder_import;
+mod gpx;
+pub mod queries;
 pub mod routes;
+pub mod slug_redirect;
+mod static_export;
+pub mod thumbnail;
+pub mod transfer;
+
+pub use folder_import::FolderImport;
+pub use static_export::StaticExport;
@@ -1,29 +1,36 @@
 use std::collections::HashMap;
 
+use anyhow::Context;
+use axum::body::Bytes;
+use itertools::Itertools;
+
 use sea_orm::{
-    ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait, PaginatorTrait,
-    QueryFilter, QueryOrder, Statement, TransactionTrait,
+    sea_query::SelectStatement, ActiveModelTrait, ActiveValue, ColumnTrait, Condition,
+    ConnectionTrait, DatabaseConnection, DbErr, EntityTrait, PaginatorTrait, QueryFilter,
+    QueryOrder, QuerySelect, QueryTrait, Statement, TransactionTrait,
 };
 
 use entities::{prelude::*, *};
 use serde::Serialize;
+use tracing::info;
 
 use crate::{
-    storage::FileStore,
+    storage::{Bucket, FileStore},
     video_transcoding::{daemon::VideoTranscoder, manager::VideoTranscodingManager},
-    NotFound, RouteError,
+    AuthSession, NotFound, RouteError,
 };
 
+use super::exif::{read_exif_from_storage, ExifData};
+use super::gpx::parse_gpx;
 use super::routes::{Direction, JournalEntryMediaCommitBody, JournalEntryMediaReorder};
 
 pub async fn query_journal_by_slug(
     slug: String,
     db: &DatabaseConnection,
-) -> Result<Result<journal::Model, NotFound>, DbErr> {
-    let journal = Journal::find()
-        .filter(journal::Column::Slug.eq(slug))
-        .one(db)
-        .await?;
+    auth: &AuthSession,
+) -> Result<Result<journal::Model, NotFound>, anyhow::Error> {
+    let q = Journal::find().filter(journal::Column::Slug.eq(slug));
+    let journal = auth.backend.filter_journals(auth, q).await?.one(db).await?;
     match journal {
         Some(journal) => Ok(Ok(journal)),
         None => Ok(Err(NotFound::for_entity("journal"))),
@@ -42,6 +49,34 @@ pub struct MediaFull {
     pub url_thumbnail: String,
     pub width_thumbnail: i32,
     pub height_thumbnail: i32,
+    pub taken_at: Option<chrono::NaiveDateTime>,
+    pub lat: Option<f32>,
+    pub lng: Option<f32>,
+    pub camera_model: Option<String>,
+    pub orientation: Option<i32>,
+    /// Only for videos, once transcoded.
+    pub duration_s: Option<f64>,
+    /// Only for videos, from their latest transcode task.
+    pub transcode_status: Option<TranscodeStatus>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TranscodeStatus {
+    Pending,
+    Failed,
+    Done,
+}
+
+impl From<video_transcode_task::TaskStatus> for TranscodeStatus {
+    fn from(status: video_transcode_task::TaskStatus) -> Self {
+        use video_transcode_task::TaskStatus;
+        match status {
+            TaskStatus::Pending | TaskStatus::Dispatched => TranscodeStatus::Pending,
+            TaskStatus::Failed => TranscodeStatus::Failed,
+            TaskStatus::Completed => TranscodeStatus::Done,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -102,6 +137,21 @@ pub async fn query_media_for_journal_entry(
         files_db_by_id.insert(file.id, file);
     }
 
+    // Collect transcode tasks, the latest wins.
+    let video_file_ids = medias_db
+        .iter()
+        .filter(|media| media.media_type == journal_entry_media::MediaType::Video)
+        .map(|media| media.file_id);
+    let tasks_db = VideoTranscodeTask::find()
+        .filter(video_transcode_task::Column::FileId.is_in(video_file_ids))
+        .order_by_asc(video_transcode_task::Column::Id)
+        .all(db)
+        .await?;
+    let mut transcode_status_by_file_id: HashMap<i32, TranscodeStatus> = HashMap::new();
+    for task in tasks_db {
+        transcode_status_by_file_id.insert(task.file_id, task.status.into());
+    }
+
     let mut media_list: Vec<MediaFull> = Vec::new();
     for media in medias_db {
         // Use .remove to take ownership and avoid copying.
@@ -132,16 +182,87 @@ pub async fn query_media_for_journal_entry(
             url_thumbnail,
             width_thumbnail: media.thumbnail_width,
             height_thumbnail: media.thumbnail_height,
+            taken_at: media.taken_at,
+            lat: media.lat,
+            lng: media.lng,
+            camera_model: media.camera_model,
+            orientation: media.orientation,
+            duration_s: media.duration_s,
+            transcode_status: transcode_status_by_file_id.get(&media.file_id).copied(),
         };
         media_list.push(m);
     }
     Ok(media_list)
 }
 
+/// Missing or broken EXIF is common (screenshots, edited photos...), so this never fails.
+async fn read_media_exif(file_id: i32, db: &DatabaseConnection, storage: &FileStore) -> ExifData {
+    let r: anyhow::Result<ExifData> = async {
+        let file = File::find_by_id(file_id)
+            .one(db)
+            .await?
+            .with_context(|| format!("File {file_id} not found"))?;
+        read_exif_from_storage(&file, storage).await
+    }
+    .await;
+    r.unwrap_or_else(|err| {
+        info!("No EXIF for file {file_id}: {err:#}");
+        ExifData::default()
+    })
+}
+
+/// Files can only be committed once, by the user they were issued to. Otherwise anyone could
+/// attach the files of journals they can't see to their own entries, and read them from there.
+pub async fn can_commit_journal_entry_media(
+    input: &JournalEntryMediaCommitBody,
+    user_id: i32,
+    db: &DatabaseConnection,
+) -> Result<bool, DbErr> {
+    let file_ids = input
+        .items
+        .iter()
+        .flat_map(|item| [item.file_id_original, item.file_id_thumbnail])
+        .collect_vec();
+    if !file_ids.iter().all_unique() {
+        return Ok(false);
+    }
+
+    let issued = File::find()
+        .filter(file::Column::Id.is_in(file_ids.clone()))
+        .filter(file::Column::UploadedBy.eq(user_id))
+        .count(db)
+        .await?;
+    if issued != file_ids.len() as u64 {
+        return Ok(false);
+    }
+
+    let in_media = JournalEntryMedia::find()
+        .filter(
+            Condition::any()
+                .add(journal_entry_media::Column::FileId.is_in(file_ids.clone()))
+                .add(journal_entry_media::Column::ThumbnailFileId.is_in(file_ids.clone())),
+        )
+        .count(db)
+        .await?;
+    let in_covers = Journal::find()
+        .filter(journal::Column::CoverId.is_in(file_ids.clone()))
+        .count(db)
+        .await?;
+    let in_tracks = JournalTrack::find()
+        .filter(journal_track::Column::FileId.is_in(file_ids))
+        .count(db)
+        .await?;
+    Ok(in_media + in_covers + in_tracks == 0)
+}
+
+/// `exif` has the photos that were already parsed, by original file ID. The EXIF of the
+/// others is read from storage.
 pub async fn append_journal_entry_media(
     // Don't like referencing upper layers here, but this is easier.
     input: &JournalEntryMediaCommitBody,
+    mut exif: HashMap<i32, ExifData>,
     db: &DatabaseConnection,
+    storage: &FileStore,
     video_transcoder: &VideoTranscoder,
 ) -> anyhow::Result<()> {
     let next_order = JournalEntryMedia::find()
@@ -150,11 +271,18 @@ pub async fn append_journal_entry_media(
         .await?;
     let next_order = next_order as usize;
 
-    let mut transcode_tasks = Vec::new();
+    let mut video_file_ids = Vec::new();
 
     let mut data: Vec<journal_entry_media::ActiveModel> = Vec::with_capacity(input.items.len());
 
     for (index, item) in input.items.iter().enumerate() {
+        let exif = match item.media_type {
+            journal_entry_media::MediaType::Image => match exif.remove(&item.file_id_original) {
+                Some(exif) => exif,
+                None => read_media_exif(item.file_id_original, db, storage).await,
+            },
+            journal_entry_media::MediaType::Video => ExifData::default(),
+        };
         data.push(journal_entry_media::ActiveModel {
             journal_entry_id: sea_orm::ActiveValue::Set(input.entry_id),
             media_type: sea_orm::ActiveValue::Set(item.media_type),
@@ -165,24 +293,35 @@ pub async fn append_journal_entry_media(
             thumbnail_file_id: sea_orm::ActiveValue::Set(item.file_id_thumbnail),
             thumbnail_width: sea_orm::ActiveValue::Set(item.width_thumbnail),
             thumbnail_height: sea_orm::ActiveValue::Set(item.height_thumbnail),
+            taken_at: sea_orm::ActiveValue::Set(exif.taken_at),
+            lat: sea_orm::ActiveValue::Set(exif.lat),
+            lng: sea_orm::ActiveValue::Set(exif.lng),
+            camera_model: sea_orm::ActiveValue::Set(exif.camera_model),
+            orientation: sea_orm::ActiveValue::Set(exif.orientation),
+            // Filled in by the transcoder.
+            duration_s: sea_orm::ActiveValue::Set(None),
+            video_codec: sea_orm::ActiveValue::Set(None),
+            rotation: sea_orm::ActiveValue::Set(None),
             caption: sea_orm::ActiveValue::NotSet,
             id: sea_orm::ActiveValue::NotSet, // Auto-incremented.
         });
         match item.media_type {
             journal_entry_media::MediaType::Image => {}
-            journal_entry_media::MediaType::Video => {
-                let task = VideoTranscodingManager::enqueue_task(db, item.file_id_original).await?;
-                transcode_tasks.push(task);
-            }
+            journal_entry_media::MediaType::Video => video_file_ids.push(item.file_id_original),
         };
     }
 
+    JournalEntryMedia::insert_many(data).exec(db).await?;
+
+    // The transcoder updates the media, so they must exist before the tasks do.
+    let mut transcode_tasks = Vec::with_capacity(video_file_ids.len());
+    for file_id in video_file_ids {
+        transcode_tasks.push(VideoTranscodingManager::enqueue_task(db, file_id).await?);
+    }
     if !transcode_tasks.is_empty() {
         video_transcoder.process(transcode_tasks).await?;
     }
 
-    JournalEntryMedia::insert_many(data).exec(db).await?;
-
     Ok(())
 }
 
@@ -246,3 +385,278 @@ pub async fn reorder_journal_entry_media(
 
     Ok(())
 }
+
+/// Returns a user-facing message if another journal already uses this name or slug.
+pub async fn check_journal_conflict(
+    name: &str,
+    slug: &str,
+    exclude_id: Option<i32>,
+    db: &impl ConnectionTrait,
+) -> Result<Option<&'static str>, DbErr> {
+    let mut q = Journal::find().filter(
+        Condition::any()
+            .add(journal::Column::Name.eq(name))
+            .add(journal::Column::Slug.eq(slug)),
+    );
+    if let Some(id) = exclude_id {
+        q = q.filter(journal::Column::Id.ne(id));
+    }
+    let conflict = q.one(db).await?.map(|journal| {
+        if journal.name == name {
+            "A journal with this name already exists"
+        } else {
+            "A journal with this slug already exists"
+        }
+    });
+    Ok(conflict)
+}
+
+/// Frees the slug from any redirect, so it points to the journal that now uses it.
+pub async fn release_journal_slug(slug: &str, db: &impl ConnectionTrait) -> Result<(), DbErr> {
+    JournalSlugRedirect::delete_many()
+        .filter(journal_slug_redirect::Column::Slug.eq(slug))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+/// Updates the journal, and keeps its old slug as a redirect when it changes.
+pub async fn update_journal(
+    journal: journal::Model,
+    data: journal::ActiveModel,
+    db: &DatabaseConnection,
+) -> Result<journal::Model, DbErr> {
+    let tx = db.begin().await?;
+
+    let journal_id = journal.id;
+    let old_slug = journal.slug;
+    let journal = data.update(&tx).await?;
+
+    if journal.slug != old_slug {
+        release_journal_slug(&journal.slug, &tx).await?;
+        let redirect = journal_slug_redirect::ActiveModel {
+            slug: ActiveValue::Set(old_slug),
+            journal_id: ActiveValue::Set(journal_id),
+            id: ActiveValue::NotSet,
+        };
+        JournalSlugRedirect::insert(redirect).exec(&tx).await?;
+    }
+
+    tx.commit().await?;
+    Ok(journal)
+}
+
+/// Deletes the media (and their transcoding tasks) of the given entries, but not their files.
+async fn delete_media_for_journal_entries(
+    entry_ids: SelectStatement,
+    db: &impl ConnectionTrait,
+) -> Result<(), DbErr> {
+    let media_file_ids = JournalEntryMedia::find()
+        .select_only()
+        .column(journal_entry_media::Column::FileId)
+        .filter(journal_entry_media::Column::JournalEntryId.in_subquery(entry_ids.clone()))
+        .into_query();
+
+    VideoTranscodeTask::delete_many()
+        .filter(video_transcode_task::Column::FileId.in_subquery(media_file_ids))
+        .exec(db)
+        .await?;
+    JournalEntryMedia::delete_many()
+        .filter(journal_entry_media::Column::JournalEntryId.in_subquery(entry_ids))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+/// Deletes the entry and its media.
+/// Files are left to [`crate::storage::StorageCleanup`], which picks up orphans.
+pub async fn delete_journal_entry(entry_id: i32, db: &DatabaseConnection) -> Result<(), DbErr> {
+    let tx = db.begin().await?;
+
+    let entry_ids = JournalEntry::find_by_id(entry_id)
+        .select_only()
+        .column(journal_entry::Column::Id)
+        .into_query();
+    delete_media_for_journal_entries(entry_ids, &tx).await?;
+    JournalEntry::delete_by_id(entry_id).exec(&tx).await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Deletes the journal along with its entries, media, comments and transcoding tasks.
+/// Files are left to [`crate::storage::StorageCleanup`], which picks up orphans.
+pub async fn delete_journal(journal_id: i32, db: &DatabaseConnection) -> Result<(), DbErr> {
+    let tx = db.begin().await?;
+
+    let entry_ids = JournalEntry::find()
+        .select_only()
+        .column(journal_entry::Column::Id)
+        .filter(journal_entry::Column::JournalId.eq(journal_id))
+        .into_query();
+    delete_media_for_journal_entries(entry_ids, &tx).await?;
+    JournalComment::delete_many()
+        .filter(journal_comment::Column::JournalId.eq(journal_id))
+        .exec(&tx)
+        .await?;
+    JournalEntry::delete_many()
+        .filter(journal_entry::Column::JournalId.eq(journal_id))
+        .exec(&tx)
+        .await?;
+    // Members, slug redirects and tracks cascade.
+    Journal::delete_by_id(journal_id).exec(&tx).await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+pub struct AddJournalTrack {
+    pub journal_id: i32,
+    pub date: Option<chrono::NaiveDate>,
+    /// Used when the GPX has no name.
+    pub filename: String,
+    pub body: Bytes,
+}
+
+/// Stores the raw GPX, and its parsed version.
+/// Returns an error message for the user if the file can't be parsed.
+pub async fn add_journal_track(
+    input: AddJournalTrack,
+    db: &DatabaseConnection,
+    storage: &FileStore,
+) -> anyhow::Result<Result<journal_track::Model, String>> {
+    let text = match std::str::from_utf8(&input.body) {
+        Ok(text) => text,
+        Err(_) => return Ok(Err("The GPX file is not valid UTF-8".into())),
+    };
+    let track = match parse_gpx(text) {
+        Ok(track) => track,
+        Err(err) => return Ok(Err(format!("Failed to read the GPX file: {err:#}"))),
+    };
+    let name = track.name.unwrap_or_else(|| {
+        input
+            .filename
+            .rsplit_once('.')
+            .map_or(input.filename.as_str(), |(stem, _)| stem)
+            .to_string()
+    });
+
+    let bucket = Bucket::Media.to_name(storage.conf()).clone();
+    let key = format!("{}.gpx", nanoid::nanoid!());
+    storage
+        .upload(bucket.clone(), key.clone(), input.body)
+        .await?;
+
+    let txn = db.begin().await?;
+    let file = file::ActiveModel {
+        bucket: ActiveValue::Set(bucket),
+        key: ActiveValue::Set(key),
+        ..Default::default()
+    }
+    .insert(&txn)
+    .await?;
+    let track = journal_track::ActiveModel {
+        journal_id: ActiveValue::Set(input.journal_id),
+        date: ActiveValue::Set(input.date),
+        name: ActiveValue::Set(name),
+        file_id: ActiveValue::Set(file.id),
+        distance_m: ActiveValue::Set(track.distance_m),
+        elevation_gain_m: ActiveValue::Set(track.elevation_gain_m),
+        polyline: ActiveValue::Set(serde_json::to_string(&track.segments)?),
+        created_at: ActiveValue::Set(chrono::Utc::now()),
+        id: ActiveValue::NotSet,
+    }
+    .insert(&txn)
+    .await?;
+    txn.commit().await?;
+
+    Ok(Ok(track))
+}
+
+/// Tracks of the day, or of the whole journal when `date` is `None`.
+pub async fn query_journal_tracks(
+    journal_id: i32,
+    date: Option<chrono::NaiveDate>,
+    db: &DatabaseConnection,
+) -> Result<Vec<journal_track::Model>, DbErr> {
+    let date_filter = match date {
+        Some(date) => journal_track::Column::Date.eq(date),
+        None => journal_track::Column::Date.is_null(),
+    };
+    JournalTrack::find()
+        .filter(journal_track::Column::JournalId.eq(journal_id))
+        .filter(date_filter)
+        .order_by_asc(journal_track::Column::CreatedAt)
+        .all(db)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        journal::routes::JournalEntryMediaCommitItem,
+        utils::testing::{
+            insert_entry, insert_file, insert_journal, insert_media, insert_user, test_db,
+        },
+    };
+
+    fn commit_body(
+        entry_id: i32,
+        file_id_original: i32,
+        file_id_thumbnail: i32,
+    ) -> JournalEntryMediaCommitBody {
+        JournalEntryMediaCommitBody {
+            entry_id,
+            items: vec![JournalEntryMediaCommitItem {
+                media_type: journal_entry_media::MediaType::Image,
+                file_id_original,
+                width_original: 1280,
+                height_original: 960,
+                file_id_thumbnail,
+                width_thumbnail: 640,
+                height_thumbnail: 480,
+            }],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_can_commit_journal_entry_media() {
+        let db = test_db().await;
+        let date = chrono::NaiveDate::from_ymd_opt(2024, 5, 3).unwrap();
+        let alice = insert_user(&db, "alice@example.com", false).await;
+        let bob = insert_user(&db, "bob@example.com", false).await;
+        let journal_alice = insert_journal(&db, "private").await;
+        let entry_alice = insert_entry(&db, journal_alice.id, Some(alice.id), date).await;
+        let journal_bob = insert_journal(&db, "lisbon").await;
+        let entry_bob = insert_entry(&db, journal_bob.id, Some(bob.id), date).await;
+
+        let original = insert_file(&db, "a.jpeg", Some(alice.id)).await;
+        let thumbnail = insert_file(&db, "a_thumbnail.jpeg", Some(alice.id)).await;
+        let body = commit_body(entry_alice.id, original.id, thumbnail.id);
+        assert!(can_commit_journal_entry_media(&body, alice.id, &db)
+            .await
+            .unwrap());
+        insert_media(&db, entry_alice.id, original.id, thumbnail.id).await;
+
+        // Bob attaches the files of Alice's journal to his own entry.
+        let body = commit_body(entry_bob.id, original.id, thumbnail.id);
+        assert!(!can_commit_journal_entry_media(&body, bob.id, &db)
+            .await
+            .unwrap());
+        // Even if Alice hasn't committed them yet.
+        let original = insert_file(&db, "b.jpeg", Some(alice.id)).await;
+        let thumbnail = insert_file(&db, "b_thumbnail.jpeg", Some(alice.id)).await;
+        let body = commit_body(entry_bob.id, original.id, thumbnail.id);
+        assert!(!can_commit_journal_entry_media(&body, bob.id, &db)
+            .await
+            .unwrap());
+
+        // Nor the same file twice.
+        let original = insert_file(&db, "c.jpeg", Some(alice.id)).await;
+        let body = commit_body(entry_alice.id, original.id, original.id);
+        assert!(!can_commit_journal_entry_media(&body, alice.id, &db)
+            .await
+            .unwrap());
+    }
+}
@@ -7,7 +7,7 @@ use crate::{
     comment::routes::CommentList,
     journal::{
         queries::{query_journal_by_slug, query_media_for_journal_entry, MediaFull},
-        routes::{JournalEntryNewPath, JournalEntryNewQuery},
+        routes::{JournalEntryNewPath, JournalEntryNewQuery, TrackList},
     },
     storage::FileStore,
     AppState, AuthSession, Route, RouteResult, Templ,
@@ -26,7 +26,7 @@ pub async fn journal_day_get(
     session: AuthSession,
     Path(JournalDayGetPath { slug, date }): Path<JournalDayGetPath>,
 ) -> RouteResult {
-    let journal = query_journal_by_slug(slug.clone(), &state.db).await?;
+    let journal = query_journal_by_slug(slug.clone(), &state.db, &session).await?;
     let journal = match journal {
         Ok(journal) => journal,
         Err(err) => {
@@ -34,6 +34,10 @@ pub async fn journal_day_get(
         }
     };
 
+    let can_write = session
+        .backend
+        .can_write_journal(&session, journal.id, &state.db)
+        .await?;
     let entries =
         query_entries_for_day(&journal, &date, &state.db, &session, &state.storage).await?;
     let comments = CommentList {
@@ -44,6 +48,14 @@ pub async fn journal_day_get(
     .query_and_render(&state.db, &templ, &session)
     .await?;
 
+    let tracks = TrackList {
+        journal_id: journal.id,
+        date: Some(date),
+        partial: false,
+    }
+    .query_and_render(&state.db, &state.storage, &templ, &session)
+    .await?;
+
     let (day_prev, day_next) = query_surrounding_days(&journal, &date, &state.db, &session).await?;
 
     let datetime = chrono::NaiveDateTime::new(date, Default::default());
@@ -72,7 +84,9 @@ pub async fn journal_day_get(
         journal,
         datetime,
         entries,
+        can_write,
         comments_fragment => comments.0,
+        tracks_fragment => tracks.0,
         href_journal_detail,
         href_journal_entry_new,
         href_journal_day_prev,
@@ -91,6 +105,7 @@ struct Entry {
     address: String,
     text: String,
     draft: bool,
+    can_edit: bool,
     href_edit: String,
     media: Vec<MediaFull>,
 }
@@ -122,12 +137,14 @@ async fn query_entries_for_day(
         .into_owned();
 
         let media = query_media_for_journal_entry(e.id, db, storage).await?;
+        let can_edit = auth.backend.can_edit_journal_entry(auth, &e, db).await?;
 
         let entry = Entry {
             title: e.title,
             address: e.address,
             text: e.text,
             draft: e.draft,
+            can_edit,
             datetime,
             time,
             href_edit,
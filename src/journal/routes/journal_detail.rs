@@ -9,8 +9,9 @@ use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QuerySe
 use serde::Serialize;
 
 use crate::{
-    comment::routes::CommentList, journal::queries::query_journal_by_slug, AppState, AuthSession,
-    Route, RouteResult, Templ,
+    comment::routes::CommentList, feed::routes::render_journal_feed_link,
+    journal::queries::query_journal_by_slug, notification::routes::render_journal_subscription,
+    AppState, AuthSession, Route, RouteResult, Templ,
 };
 use entities::{prelude::*, *};
 
@@ -22,7 +23,7 @@ pub async fn journal_detail_get(
     session: AuthSession,
     Path(slug): Path<String>,
 ) -> RouteResult {
-    let journal = query_journal_by_slug(slug.clone(), &state.db).await?;
+    let journal = query_journal_by_slug(slug.clone(), &state.db, &session).await?;
     let journal = match journal {
         Ok(journal) => journal,
         Err(err) => {
@@ -30,6 +31,10 @@ pub async fn journal_detail_get(
         }
     };
 
+    let can_write = session
+        .backend
+        .can_write_journal(&session, journal.id, &state.db)
+        .await?;
     let entries_by_day = query_entries_by_day(&journal, &state.db, &session).await?;
     let comments = CommentList {
         journal_id: journal.id,
@@ -39,6 +44,16 @@ pub async fn journal_detail_get(
     .query_and_render(&state.db, &templ, &session)
     .await?;
 
+    let feed_link = render_journal_feed_link(&state, &templ, &session, &journal).await?;
+    // Subscriptions would never be sent without a mailer.
+    let subscription = match state.mailer {
+        Some(_) => Some(render_journal_subscription(&state, &templ, &session, &journal).await?),
+        None => None,
+    };
+
+    let href_journal_edit = Route::JournalEditGet { slug: Some(&slug) }.as_path();
+    let href_journal_map = Route::JournalMapGet { slug: Some(&slug) }.as_path();
+    let href_journal_members = Route::JournalMembersGet { slug: Some(&slug) }.as_path();
     let href_journal_entry_new = Route::JournalEntryNewGet(Some((
         &JournalEntryNewPath { slug },
         &JournalEntryNewQuery::default(),
@@ -48,8 +63,14 @@ pub async fn journal_detail_get(
     let ctx = context! {
         journal,
         entries_by_day,
+        can_write,
         href_journal_entry_new,
+        href_journal_edit,
+        href_journal_map,
+        href_journal_members,
         comments_fragment => comments.0,
+        feed_link_fragment => feed_link.0,
+        subscription_fragment => subscription.map(|html| html.0),
     };
     let html = templ.render_ctx("journal_detail.html", ctx)?;
     Ok(html.into_response())
@@ -0,0 +1,108 @@
+use axum::{
+    extract::{rejection::FormRejection, Path, State},
+    response::{IntoResponse, Response},
+    Form,
+};
+use minijinja::context;
+use sea_orm::ActiveValue;
+
+use crate::{
+    journal::queries::{
+        check_journal_conflict, delete_journal, query_journal_by_slug, update_journal,
+    },
+    AppState, AuthSession, FormError, Route, RouteError, RouteResult, Templ, Toast,
+};
+use entities::*;
+
+use super::JournalNew;
+
+pub async fn journal_edit_get(
+    state: State<AppState>,
+    templ: Templ,
+    session: AuthSession,
+    Path(slug): Path<String>,
+) -> RouteResult {
+    let journal = query_journal_by_slug(slug, &state.db, &session).await?;
+    let journal = match journal {
+        Ok(journal) => journal,
+        Err(err) => {
+            return Ok(err.render(&templ).into_response());
+        }
+    };
+
+    let ctx = context! {
+        href_journal_detail => Route::JournalDetailGet { slug: Some(&journal.slug) }.as_path(),
+        href_delete => Route::JournalDeletePost { slug: Some(&journal.slug) }.as_path(),
+        journal,
+    };
+    let html = templ.render_ctx("journal_edit.html", ctx)?;
+    Ok(html.into_response())
+}
+
+pub async fn journal_edit_post(
+    state: State<AppState>,
+    templ: Templ,
+    session: AuthSession,
+    Path(slug): Path<String>,
+    form: Result<Form<JournalNew>, FormRejection>,
+) -> Result<Response, RouteError> {
+    let form = match form {
+        Err(err) => {
+            let resp = FormError::from(err).render(&state)?;
+            return Ok(resp.into_response());
+        }
+        Ok(form) => form,
+    };
+    let journal = query_journal_by_slug(slug, &state.db, &session).await?;
+    let journal = match journal {
+        Ok(journal) => journal,
+        Err(err) => {
+            return Ok(err.render(&templ).into_response());
+        }
+    };
+
+    let conflict =
+        check_journal_conflict(&form.name, &form.slug, Some(journal.id), &state.db).await?;
+    if let Some(msg) = conflict {
+        let resp = FormError::new(msg).render(&state)?;
+        return Ok(resp.into_response());
+    }
+
+    let data = journal::ActiveModel {
+        id: ActiveValue::Unchanged(journal.id),
+        name: ActiveValue::Set(form.name.clone()),
+        slug: ActiveValue::Set(form.slug.clone()),
+        start_date: ActiveValue::Set(form.start_date),
+        end_date: ActiveValue::Set(form.end_date),
+        cover_id: ActiveValue::NotSet,
+    };
+    let journal = update_journal(journal, data, &state.db).await?;
+
+    let href = Route::JournalDetailGet {
+        slug: Some(&journal.slug),
+    }
+    .as_path();
+    let resp = [("HX-Location", href.as_ref())];
+    Ok(resp.into_response())
+}
+
+/// Deletes the journal and everything in it, except for the files in storage.
+pub async fn journal_delete_post(
+    state: State<AppState>,
+    session: AuthSession,
+    Path(slug): Path<String>,
+) -> Result<Response, Toast> {
+    let r: RouteResult = async {
+        let journal = match query_journal_by_slug(slug, &state.db, &session).await? {
+            Ok(journal) => journal,
+            Err(_) => return Ok(Toast::danger("Journal not found").into_response()),
+        };
+        delete_journal(journal.id, &state.db).await?;
+
+        let href = Route::JournalListGet.as_path();
+        let resp = [("HX-Location", href.as_ref())];
+        Ok(resp.into_response())
+    }
+    .await;
+    r.map_err(Toast::error)
+}
@@ -1,21 +1,25 @@
+use std::collections::HashMap;
+
 use axum::{
     extract::{rejection::FormRejection, Path, State},
     response::{Html, IntoResponse},
     Form,
 };
 use minijinja::context;
-use sea_orm::EntityTrait;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
 use serde::Deserialize;
 
 use crate::{
     journal::queries::{
-        append_journal_entry_media, delete_journal_entry_media, query_journal_entry_by_id,
-        query_media_for_journal_entry, reorder_journal_entry_media, MediaFull,
+        append_journal_entry_media, can_commit_journal_entry_media, delete_journal_entry,
+        delete_journal_entry_media, query_journal_entry_by_id, query_media_for_journal_entry,
+        reorder_journal_entry_media, MediaFull, TranscodeStatus,
     },
-    utils::serde_utils::string_trim,
-    AppState, FormError, Route, RouteError, RouteResult, Templ, Toast,
+    notification::queries::notify_entry_published,
+    utils::serde_utils::{empty_string_as_none, string_trim},
+    AppState, AuthSession, FormError, NotFound, Route, RouteError, RouteResult, Templ, Toast,
 };
-use entities::{prelude::*, *};
+use entities::{journal_member::JournalMemberRole, prelude::*, *};
 
 #[derive(Deserialize, Debug)]
 pub struct JournalEntryEdit {
@@ -27,11 +31,82 @@ pub struct JournalEntryEdit {
     time: chrono::NaiveTime,
     #[serde(deserialize_with = "string_trim")]
     text: String,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    lat: Option<f32>,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    lng: Option<f32>,
+}
+
+/// Both coordinates or none, within range.
+fn validate_coordinates(lat: Option<f32>, lng: Option<f32>) -> Result<(), &'static str> {
+    match (lat, lng) {
+        (None, None) => Ok(()),
+        (Some(lat), Some(lng)) => {
+            if !(-90.0..=90.0).contains(&lat) {
+                Err("Latitude must be between -90 and 90")
+            } else if !(-180.0..=180.0).contains(&lng) {
+                Err("Longitude must be between -180 and 180")
+            } else {
+                Ok(())
+            }
+        }
+        _ => Err("Both latitude and longitude are required"),
+    }
+}
+
+const FORBIDDEN_MSG: &str = "You are not allowed to edit this entry";
+
+/// Whether the entry exists and the user is allowed to edit it.
+async fn can_edit_entry(
+    state: &AppState,
+    session: &AuthSession,
+    entry_id: i32,
+) -> Result<bool, RouteError> {
+    let entry = match JournalEntry::find_by_id(entry_id).one(&state.db).await? {
+        Some(entry) => entry,
+        None => return Ok(false),
+    };
+    let can_edit = session
+        .backend
+        .can_edit_journal_entry(session, &entry, &state.db)
+        .await?;
+    Ok(can_edit)
+}
+
+/// Like [`can_edit_entry`], but also checks that the media belongs to the entry.
+async fn can_edit_entry_media(
+    state: &AppState,
+    session: &AuthSession,
+    entry_id: i32,
+    media_id: i32,
+) -> Result<bool, RouteError> {
+    let media = JournalEntryMedia::find_by_id(media_id)
+        .filter(journal_entry_media::Column::JournalEntryId.eq(entry_id))
+        .one(&state.db)
+        .await?;
+    if media.is_none() {
+        return Ok(false);
+    }
+    can_edit_entry(state, session, entry_id).await
+}
+
+/// The cover belongs to the journal, so only its owners can change it.
+async fn can_set_journal_cover(
+    state: &AppState,
+    session: &AuthSession,
+    journal_id: i32,
+) -> Result<bool, RouteError> {
+    let role = session
+        .backend
+        .get_journal_role(session, journal_id, &state.db)
+        .await?;
+    Ok(role == Some(JournalMemberRole::Owner))
 }
 
 pub async fn journal_entry_edit_get(
     state: State<AppState>,
     templ: Templ,
+    session: AuthSession,
     Path(entry_id): Path<i32>,
 ) -> RouteResult {
     let result = query_journal_entry_by_id(entry_id, &state.db, &state.storage).await?;
@@ -41,10 +116,21 @@ pub async fn journal_entry_edit_get(
             return Ok(err.render(&templ).into_response());
         }
     };
+    if !session
+        .backend
+        .can_edit_journal_entry(&session, &entry_full.entry, &state.db)
+        .await?
+    {
+        return Ok(NotFound::for_entity("entry")
+            .render(&templ)?
+            .into_response());
+    }
+    let can_set_cover = can_set_journal_cover(&state, &session, entry_full.journal.id).await?;
+    let entry_has_location = entry_full.entry.lat.is_some();
 
     let href_get_upload_url = Route::MediaUploadUrlPost.as_path();
     let href_commit_upload = Route::JournalEntryMediaCommitPost.as_path();
-    let href_publish = Route::JournalEntryPublishPost {
+    let href_delete_entry = Route::JournalEntryDeletePost {
         entry_id: Some(entry_id),
     }
     .as_path();
@@ -57,12 +143,16 @@ pub async fn journal_entry_edit_get(
         ..context! {
             href_get_upload_url,
             href_commit_upload,
-            href_publish,
+            href_delete_entry,
             href_journal_detail,
+            lat => entry_full.entry.lat,
+            lng => entry_full.entry.lng,
+            location_oob => false,
             entry => entry_full.entry,
             journal => entry_full.journal,
         },
-        ..get_media_list_ctx(entry_full.media_list, entry_id)
+        ..get_publish_ctx(entry_id, entry_full.entry.draft),
+        ..get_media_list_ctx(entry_full.media_list, entry_id, can_set_cover, entry_has_location)
     };
     let html = templ.render_ctx("journal_entry_edit.html", ctx)?;
     Ok(html.into_response())
@@ -70,9 +160,13 @@ pub async fn journal_entry_edit_get(
 
 pub async fn journal_entry_edit_post(
     state: State<AppState>,
+    session: AuthSession,
     Path(entry_id): Path<i32>,
     form: Result<Form<JournalEntryEdit>, FormRejection>,
 ) -> RouteResult {
+    if !can_edit_entry(&state, &session, entry_id).await? {
+        return Ok(Toast::danger(FORBIDDEN_MSG).into_response());
+    }
     match form {
         Err(err) => {
             let resp = FormError::from(err).render(&state)?;
@@ -84,7 +178,13 @@ pub async fn journal_entry_edit_post(
             date,
             time,
             text,
+            lat,
+            lng,
         })) => {
+            if let Err(msg) = validate_coordinates(lat, lng) {
+                let resp = FormError::new(msg).render(&state)?;
+                return Ok(resp.into_response());
+            }
             let data = journal_entry::ActiveModel {
                 id: sea_orm::ActiveValue::Set(entry_id),
                 title: sea_orm::ActiveValue::Set(title),
@@ -92,6 +192,8 @@ pub async fn journal_entry_edit_post(
                 date: sea_orm::ActiveValue::Set(date),
                 time: sea_orm::ActiveValue::Set(time),
                 text: sea_orm::ActiveValue::Set(text),
+                lat: sea_orm::ActiveValue::Set(lat),
+                lng: sea_orm::ActiveValue::Set(lng),
                 ..Default::default()
             };
             JournalEntry::update(data).exec(&state.db).await?;
@@ -101,21 +203,97 @@ pub async fn journal_entry_edit_post(
     }
 }
 
-pub async fn journal_entry_publish_post(state: AppState, Path(entry_id): Path<i32>) -> RouteResult {
+fn get_publish_ctx(entry_id: i32, draft: bool) -> minijinja::Value {
+    let href_publish = Route::JournalEntryPublishPost {
+        entry_id: Some(entry_id),
+    }
+    .as_path();
+    let href_unpublish = Route::JournalEntryUnpublishPost {
+        entry_id: Some(entry_id),
+    }
+    .as_path();
+    context! { draft, href_publish, href_unpublish }
+}
+
+/// Publishes the entry, or reverts it to a draft.
+async fn set_entry_draft(
+    state: &AppState,
+    templ: &Templ,
+    session: &AuthSession,
+    entry_id: i32,
+    draft: bool,
+) -> RouteResult {
+    if !can_edit_entry(state, session, entry_id).await? {
+        return Ok(Toast::danger(FORBIDDEN_MSG).into_response());
+    }
     let data = journal_entry::ActiveModel {
         id: sea_orm::ActiveValue::Set(entry_id),
-        draft: sea_orm::ActiveValue::Set(false),
+        draft: sea_orm::ActiveValue::Set(draft),
         ..Default::default()
     };
-    JournalEntry::update(data).exec(&state.db).await?;
+    let entry = JournalEntry::update(data).exec(&state.db).await?;
+    if !draft && state.mailer.is_some() {
+        notify_entry_published(&entry, &state.db).await?;
+        state.notifier.wake();
+    }
 
-    let toast = Toast::success("Published");
-    // Simply wipes the button.
-    let html = Html("");
+    let toast = Toast::success(if draft { "Unpublished" } else { "Published" });
+    let ctx = get_publish_ctx(entry_id, draft);
+    let html =
+        templ.render_ctx_fragment("journal_entry_edit.html", ctx, Some("fragment_publish"))?;
     let resp = (toast.into_headers(), html);
     Ok(resp.into_response())
 }
 
+pub async fn journal_entry_publish_post(
+    state: State<AppState>,
+    templ: Templ,
+    session: AuthSession,
+    Path(entry_id): Path<i32>,
+) -> RouteResult {
+    set_entry_draft(&state, &templ, &session, entry_id, false).await
+}
+
+pub async fn journal_entry_unpublish_post(
+    state: State<AppState>,
+    templ: Templ,
+    session: AuthSession,
+    Path(entry_id): Path<i32>,
+) -> RouteResult {
+    set_entry_draft(&state, &templ, &session, entry_id, true).await
+}
+
+/// Deletes the entry and its media, then goes back to the journal.
+pub async fn journal_entry_delete_post(
+    state: State<AppState>,
+    session: AuthSession,
+    Path(entry_id): Path<i32>,
+) -> RouteResult {
+    let entry = JournalEntry::find_by_id(entry_id)
+        .find_also_related(Journal)
+        .one(&state.db)
+        .await?;
+    let (entry, journal) = match entry {
+        Some((entry, Some(journal))) => (entry, journal),
+        _ => return Ok(Toast::danger("Entry not found").into_response()),
+    };
+    if !session
+        .backend
+        .can_edit_journal_entry(&session, &entry, &state.db)
+        .await?
+    {
+        return Ok(Toast::danger(FORBIDDEN_MSG).into_response());
+    }
+    delete_journal_entry(entry.id, &state.db).await?;
+
+    let href = Route::JournalDetailGet {
+        slug: Some(&journal.slug),
+    }
+    .as_path();
+    let resp = [("HX-Location", href.as_ref())];
+    Ok(resp.into_response())
+}
+
 // SYNC
 #[derive(Deserialize, Debug)]
 pub struct JournalEntryMediaCommitItem {
@@ -144,6 +322,7 @@ pub struct JournalEntryMediaCommitForm {
 pub async fn journal_entry_media_commit_post(
     state: State<AppState>,
     templ: Templ,
+    session: AuthSession,
     form: Form<JournalEntryMediaCommitForm>,
 ) -> RouteResult {
     let body: JournalEntryMediaCommitBody = match serde_json::from_str(&form.json) {
@@ -152,9 +331,23 @@ pub async fn journal_entry_media_commit_post(
             return Ok((FormError::STATUS, err.to_string()).into_response());
         }
     };
-    append_journal_entry_media(&body, &state.db, &state.video_transcoder).await?;
+    if !can_edit_entry(&state, &session, body.entry_id).await? {
+        return Ok(Toast::danger(FORBIDDEN_MSG).into_response());
+    }
+    let user_id = session.user.as_ref().expect("Should be authenticated").0.id;
+    if !can_commit_journal_entry_media(&body, user_id, &state.db).await? {
+        return Ok(Toast::danger("You are not allowed to add these files").into_response());
+    }
+    append_journal_entry_media(
+        &body,
+        HashMap::new(),
+        &state.db,
+        &state.storage,
+        &state.video_transcoder,
+    )
+    .await?;
 
-    let html = render_media_list(body.entry_id, &state, &templ).await?;
+    let html = render_media_list(body.entry_id, &state, &templ, &session).await?;
     Ok(html.into_response())
 }
 
@@ -167,6 +360,7 @@ pub struct JournalEntryMediaDelete {
 pub async fn journal_entry_media_delete(
     state: State<AppState>,
     templ: Templ,
+    session: AuthSession,
     form: Result<Form<JournalEntryMediaDelete>, FormRejection>,
 ) -> RouteResult {
     let form = match form {
@@ -176,10 +370,13 @@ pub async fn journal_entry_media_delete(
             return Ok(resp.into_response());
         }
     };
+    if !can_edit_entry_media(&state, &session, form.entry_id, form.media_id).await? {
+        return Ok(Toast::danger(FORBIDDEN_MSG).into_response());
+    }
     delete_journal_entry_media(form.media_id, &state.db).await?;
 
     let toast = Toast::success("Deleted");
-    let html = render_media_list(form.entry_id, &state, &templ).await?;
+    let html = render_media_list(form.entry_id, &state, &templ, &session).await?;
     let resp = (toast.into_headers(), html);
     Ok(resp.into_response())
 }
@@ -202,6 +399,7 @@ pub struct JournalEntryMediaReorder {
 pub async fn journal_entry_media_reorder(
     state: State<AppState>,
     templ: Templ,
+    session: AuthSession,
     form: Result<Form<JournalEntryMediaReorder>, FormRejection>,
 ) -> RouteResult {
     let form = match form {
@@ -211,23 +409,48 @@ pub async fn journal_entry_media_reorder(
             return Ok(resp.into_response());
         }
     };
+    if !can_edit_entry_media(&state, &session, form.entry_id, form.media_id).await? {
+        return Ok(Toast::danger(FORBIDDEN_MSG).into_response());
+    }
     reorder_journal_entry_media(&form, &state.db).await?;
 
-    let html = render_media_list(form.entry_id, &state, &templ).await?;
+    let html = render_media_list(form.entry_id, &state, &templ, &session).await?;
     Ok(html.into_response())
 }
 
-fn get_media_list_ctx(media_list: Vec<MediaFull>, entry_id: i32) -> minijinja::Value {
+fn get_media_list_ctx(
+    media_list: Vec<MediaFull>,
+    entry_id: i32,
+    can_set_cover: bool,
+    entry_has_location: bool,
+) -> minijinja::Value {
     let href_caption_edit = Route::JournalEntryMediaEditCaptionPost.as_path();
     let href_delete = Route::JournalEntryMediaDelete.as_path();
     let href_reorder = Route::JournalEntryMediaReorder.as_path();
+    let href_set_cover = Route::JournalEntryMediaSetCoverPost.as_path();
+    let href_use_location = Route::JournalEntryMediaUseLocationPost.as_path();
+    // Refreshed until the videos are transcoded.
+    let href_refresh = media_list
+        .iter()
+        .any(|media| media.transcode_status == Some(TranscodeStatus::Pending))
+        .then(|| {
+            Route::JournalEntryMediaListGet {
+                entry_id: Some(entry_id),
+            }
+            .as_path()
+        });
 
     let ctx = context! {
         media_list,
         entry_id,
+        can_set_cover,
+        entry_has_location,
         href_caption_edit,
         href_delete,
         href_reorder,
+        href_set_cover,
+        href_use_location,
+        href_refresh,
     };
     ctx
 }
@@ -236,14 +459,36 @@ async fn render_media_list(
     entry_id: i32,
     state: &AppState,
     templ: &Templ,
+    session: &AuthSession,
 ) -> Result<Html<String>, RouteError> {
     let media_list = query_media_for_journal_entry(entry_id, &state.db, &state.storage).await?;
-    let ctx = get_media_list_ctx(media_list, entry_id);
+    let (can_set_cover, entry_has_location) =
+        match JournalEntry::find_by_id(entry_id).one(&state.db).await? {
+            Some(entry) => (
+                can_set_journal_cover(state, session, entry.journal_id).await?,
+                entry.lat.is_some(),
+            ),
+            None => (false, false),
+        };
+    let ctx = get_media_list_ctx(media_list, entry_id, can_set_cover, entry_has_location);
     let html =
         templ.render_ctx_fragment("journal_entry_edit.html", ctx, Some("fragment_media_list"))?;
     Ok(html)
 }
 
+pub async fn journal_entry_media_list_get(
+    state: State<AppState>,
+    templ: Templ,
+    session: AuthSession,
+    Path(entry_id): Path<i32>,
+) -> RouteResult {
+    if !can_edit_entry(&state, &session, entry_id).await? {
+        return Ok(Toast::danger(FORBIDDEN_MSG).into_response());
+    }
+    let html = render_media_list(entry_id, &state, &templ, &session).await?;
+    Ok(html.into_response())
+}
+
 #[derive(Deserialize, Debug)]
 pub struct JournalEntryMediaCaptionEdit {
     media_id: i32,
@@ -253,6 +498,7 @@ pub struct JournalEntryMediaCaptionEdit {
 
 pub async fn journal_entry_media_caption_edit(
     state: State<AppState>,
+    session: AuthSession,
     form: Result<Form<JournalEntryMediaCaptionEdit>, FormRejection>,
 ) -> RouteResult {
     let form = match form {
@@ -262,6 +508,16 @@ pub async fn journal_entry_media_caption_edit(
             return Ok(resp.into_response());
         }
     };
+    let media = JournalEntryMedia::find_by_id(form.media_id)
+        .one(&state.db)
+        .await?;
+    let can_edit = match media {
+        Some(media) => can_edit_entry(&state, &session, media.journal_entry_id).await?,
+        None => false,
+    };
+    if !can_edit {
+        return Ok(Toast::danger(FORBIDDEN_MSG).into_response());
+    }
 
     let data = journal_entry_media::ActiveModel {
         id: sea_orm::ActiveValue::Set(form.media_id),
@@ -273,3 +529,96 @@ pub async fn journal_entry_media_caption_edit(
     let resp = Toast::success("Caption saved");
     Ok(resp.into_response())
 }
+
+#[derive(Deserialize, Debug)]
+pub struct JournalEntryMediaSetCover {
+    media_id: i32,
+    entry_id: i32,
+}
+
+/// Uses the media's thumbnail as the journal's cover.
+pub async fn journal_entry_media_set_cover_post(
+    state: State<AppState>,
+    session: AuthSession,
+    form: Result<Form<JournalEntryMediaSetCover>, FormRejection>,
+) -> RouteResult {
+    let form = match form {
+        Ok(form) => form,
+        Err(err) => {
+            let resp = Toast::error(err);
+            return Ok(resp.into_response());
+        }
+    };
+
+    let media = JournalEntryMedia::find_by_id(form.media_id)
+        .filter(journal_entry_media::Column::JournalEntryId.eq(form.entry_id))
+        .find_also_related(JournalEntry)
+        .one(&state.db)
+        .await?;
+    let (media, entry) = match media {
+        Some((media, Some(entry))) => (media, entry),
+        _ => return Ok(Toast::danger("Media not found").into_response()),
+    };
+    if !can_set_journal_cover(&state, &session, entry.journal_id).await? {
+        return Ok(Toast::danger("Only the journal's owners can change its cover").into_response());
+    }
+
+    let data = journal::ActiveModel {
+        id: sea_orm::ActiveValue::Unchanged(entry.journal_id),
+        cover_id: sea_orm::ActiveValue::Set(Some(media.thumbnail_file_id)),
+        ..Default::default()
+    };
+    Journal::update(data).exec(&state.db).await?;
+
+    let resp = Toast::success("Cover updated");
+    Ok(resp.into_response())
+}
+
+#[derive(Deserialize, Debug)]
+pub struct JournalEntryMediaUseLocation {
+    media_id: i32,
+    entry_id: i32,
+}
+
+/// Uses the media's GPS coordinates as the entry's location.
+pub async fn journal_entry_media_use_location_post(
+    state: State<AppState>,
+    templ: Templ,
+    session: AuthSession,
+    form: Result<Form<JournalEntryMediaUseLocation>, FormRejection>,
+) -> RouteResult {
+    let form = match form {
+        Ok(form) => form,
+        Err(err) => {
+            let resp = Toast::error(err);
+            return Ok(resp.into_response());
+        }
+    };
+    if !can_edit_entry_media(&state, &session, form.entry_id, form.media_id).await? {
+        return Ok(Toast::danger(FORBIDDEN_MSG).into_response());
+    }
+    let media = JournalEntryMedia::find_by_id(form.media_id)
+        .one(&state.db)
+        .await?;
+    let (lat, lng) = match media.and_then(|media| media.lat.zip(media.lng)) {
+        Some(coords) => coords,
+        None => return Ok(Toast::danger("This media has no location").into_response()),
+    };
+
+    let data = journal_entry::ActiveModel {
+        id: sea_orm::ActiveValue::Unchanged(form.entry_id),
+        lat: sea_orm::ActiveValue::Set(Some(lat)),
+        lng: sea_orm::ActiveValue::Set(Some(lng)),
+        ..Default::default()
+    };
+    JournalEntry::update(data).exec(&state.db).await?;
+
+    let toast = Toast::success("Location updated");
+    let html = render_media_list(form.entry_id, &state, &templ, &session).await?;
+    // Also refresh the inputs, otherwise saving the form would overwrite the location.
+    let ctx = context! { lat, lng, location_oob => true };
+    let html_location =
+        templ.render_ctx_fragment("journal_entry_edit.html", ctx, Some("fragment_location"))?;
+    let resp = (toast.into_headers(), Html(html.0 + &html_location.0));
+    Ok(resp.into_response())
+}
@@ -10,8 +10,8 @@ use sea_orm::EntityTrait;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    journal::queries::query_journal_by_slug, utils::serde_utils::string_trim, AppState, FormError,
-    Route, RouteError, RouteResult, Templ,
+    journal::queries::query_journal_by_slug, utils::serde_utils::string_trim, AppState,
+    AuthSession, FormError, NotFound, Route, RouteError, RouteResult, Templ,
 };
 use entities::{prelude::*, *};
 
@@ -37,16 +37,26 @@ pub struct JournalEntryNew {
 pub async fn journal_entry_new_get(
     state: State<AppState>,
     templ: Templ,
+    session: AuthSession,
     Path(slug): Path<String>,
     Query(query): Query<JournalEntryNewQuery>,
 ) -> RouteResult {
-    let journal = query_journal_by_slug(slug, &state.db).await?;
+    let journal = query_journal_by_slug(slug, &state.db, &session).await?;
     let journal = match journal {
         Ok(journal) => journal,
         Err(err) => {
             return Ok(err.render(&templ).into_response());
         }
     };
+    if !session
+        .backend
+        .can_write_journal(&session, journal.id, &state.db)
+        .await?
+    {
+        return Ok(NotFound::for_entity("journal")
+            .render(&templ)?
+            .into_response());
+    }
     let default_date = query.date.map(format_input_date_value);
 
     let ctx = context! {
@@ -67,6 +77,7 @@ fn format_input_date_value(d: chrono::NaiveDate) -> String {
 
 pub async fn journal_entry_new_post(
     state: State<AppState>,
+    session: AuthSession,
     form: Result<Form<JournalEntryNew>, FormRejection>,
 ) -> Result<Response, RouteError> {
     match form {
@@ -80,8 +91,20 @@ pub async fn journal_entry_new_post(
             date,
             time,
         })) => {
+            if !session
+                .backend
+                .can_write_journal(&session, journal_id, &state.db)
+                .await?
+            {
+                let resp = FormError::new("You are not allowed to write in this journal")
+                    .render(&state)?;
+                return Ok(resp.into_response());
+            }
+
+            let user_id = session.user.as_ref().expect("Should be authenticated").0.id;
             let data = journal_entry::ActiveModel {
                 journal_id: sea_orm::ActiveValue::Set(journal_id),
+                author_id: sea_orm::ActiveValue::Set(Some(user_id)),
                 title: sea_orm::ActiveValue::Set(title),
                 date: sea_orm::ActiveValue::Set(date),
                 time: sea_orm::ActiveValue::Set(time),
@@ -2,9 +2,8 @@ use axum::{extract::State, response::IntoResponse};
 use minijinja::context;
 use sea_orm::{EntityTrait, QueryOrder};
 use serde::Serialize;
-use url::Url;
 
-use crate::{AppState, Route, RouteResult, Templ};
+use crate::{AppState, AuthSession, Route, RouteResult, Templ};
 use entities::{prelude::*, *};
 
 #[derive(Serialize, Debug)]
@@ -14,37 +13,47 @@ struct JournalListItem {
     start_date: chrono::NaiveDateTime,
     end_date: Option<chrono::NaiveDateTime>,
     href: String,
-    cover_url: Option<Url>,
+    cover_url: Option<String>,
 }
 
-pub async fn journal_list(State(state): State<AppState>, templ: Templ) -> RouteResult {
-    let journals = Journal::find()
+pub async fn journal_list(
+    State(state): State<AppState>,
+    templ: Templ,
+    session: AuthSession,
+) -> RouteResult {
+    let q = session
+        .backend
+        .filter_journals(&session, Journal::find())
+        .await?;
+    let journals = q
         .find_also_related(File)
         .order_by_desc(journal::Column::StartDate)
         .all(&state.db)
         .await?;
-    let journals = journals
-        .into_iter()
-        .map(|(journal, _cover)| {
-            let start_date = chrono::NaiveDateTime::new(journal.start_date, Default::default());
-            let end_date = journal
-                .end_date
-                .map(|d| chrono::NaiveDateTime::new(d, Default::default()));
-            JournalListItem {
-                id: journal.id,
-                name: journal.name,
-                start_date,
-                end_date,
-                cover_url: None,
-                href: Route::JournalDetailGet {
-                    slug: Some(&journal.slug),
-                }
-                .as_path()
-                .into_owned(),
+    let mut items: Vec<JournalListItem> = Vec::with_capacity(journals.len());
+    for (journal, cover) in journals {
+        let start_date = chrono::NaiveDateTime::new(journal.start_date, Default::default());
+        let end_date = journal
+            .end_date
+            .map(|d| chrono::NaiveDateTime::new(d, Default::default()));
+        let cover_url = match cover {
+            Some(cover) => Some(state.storage.sign_url(cover.bucket, cover.key).await?),
+            None => None,
+        };
+        items.push(JournalListItem {
+            id: journal.id,
+            name: journal.name,
+            start_date,
+            end_date,
+            cover_url,
+            href: Route::JournalDetailGet {
+                slug: Some(&journal.slug),
             }
-        })
-        .collect::<Vec<_>>();
-    let ctx = context! { journals, href_new => Route::JournalNewGet.as_path() };
+            .as_path()
+            .into_owned(),
+        });
+    }
+    let ctx = context! { journals => items, href_new => Route::JournalNewGet.as_path() };
     let html = templ.render_ctx("journal_list.html", ctx)?;
     Ok(html.into_response())
 }
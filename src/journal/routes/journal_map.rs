@@ -0,0 +1,166 @@
+use anyhow::Context as _;
+use axum::{
+    extract::{Path, State},
+    http::header,
+    response::IntoResponse,
+};
+use itertools::Itertools;
+use minijinja::context;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder};
+use serde_json::json;
+
+use crate::{
+    journal::queries::query_journal_by_slug, AppState, AuthSession, Route, RouteResult, Templ,
+};
+use entities::{prelude::*, *};
+
+use super::{JournalDayGetPath, TrackList};
+
+pub async fn journal_map_get(
+    state: State<AppState>,
+    templ: Templ,
+    session: AuthSession,
+    Path(slug): Path<String>,
+) -> RouteResult {
+    let journal = query_journal_by_slug(slug, &state.db, &session).await?;
+    let journal = match journal {
+        Ok(journal) => journal,
+        Err(err) => {
+            return Ok(err.render(&templ).into_response());
+        }
+    };
+
+    // Tracks of a specific day are listed on that day.
+    let tracks = TrackList {
+        journal_id: journal.id,
+        date: None,
+        partial: false,
+    }
+    .query_and_render(&state.db, &state.storage, &templ, &session)
+    .await?;
+
+    let ctx = context! {
+        tracks_fragment => tracks.0,
+        href_journal_detail => Route::JournalDetailGet { slug: Some(&journal.slug) }.as_path(),
+        href_geojson => Route::JournalMapGeoJsonGet { slug: Some(&journal.slug) }.as_path(),
+        journal,
+    };
+    let html = templ.render_ctx("journal_map.html", ctx)?;
+    Ok(html.into_response())
+}
+
+/// Published entries with a location, as GeoJSON points, plus the route between them,
+/// and the GPX tracks.
+pub async fn journal_map_geojson_get(
+    state: State<AppState>,
+    templ: Templ,
+    session: AuthSession,
+    Path(slug): Path<String>,
+) -> RouteResult {
+    let journal = query_journal_by_slug(slug, &state.db, &session).await?;
+    let journal = match journal {
+        Ok(journal) => journal,
+        Err(err) => {
+            return Ok(err.render(&templ).into_response());
+        }
+    };
+    let entries = query_located_entries(&journal, &state.db, &session).await?;
+
+    let mut features = entries
+        .iter()
+        .filter_map(|entry| {
+            let (lat, lng) = entry.lat.zip(entry.lng)?;
+            let href = Route::JournalDayGet(Some(&JournalDayGetPath {
+                slug: journal.slug.clone(),
+                date: entry.date,
+            }))
+            .as_path();
+            Some(json!({
+                "type": "Feature",
+                // GeoJSON is longitude first.
+                "geometry": { "type": "Point", "coordinates": [lng, lat] },
+                "properties": {
+                    "kind": "entry",
+                    "title": entry.title,
+                    "date": chrono::NaiveDateTime::new(entry.date, entry.time),
+                    "href": href,
+                },
+            }))
+        })
+        .collect_vec();
+    let route = entries
+        .iter()
+        .filter_map(|entry| entry.lat.zip(entry.lng).map(|(lat, lng)| [lng, lat]))
+        .collect_vec();
+    if route.len() > 1 {
+        features.push(json!({
+            "type": "Feature",
+            "geometry": { "type": "LineString", "coordinates": route },
+            "properties": { "kind": "route" },
+        }));
+    }
+
+    let tracks = JournalTrack::find()
+        .filter(journal_track::Column::JournalId.eq(journal.id))
+        .order_by_asc(journal_track::Column::CreatedAt)
+        .all(&state.db)
+        .await?;
+    for track in tracks {
+        let segments: Vec<Vec<[f64; 2]>> =
+            serde_json::from_str(&track.polyline).context("Invalid track polyline")?;
+        let coordinates = segments
+            .into_iter()
+            .map(|line| line.into_iter().map(|[lat, lng]| [lng, lat]).collect_vec())
+            .collect_vec();
+        let href = track.date.map(|date| {
+            Route::JournalDayGet(Some(&JournalDayGetPath {
+                slug: journal.slug.clone(),
+                date,
+            }))
+            .as_path()
+        });
+        features.push(json!({
+            "type": "Feature",
+            "id": format!("track-{}", track.id),
+            "geometry": { "type": "MultiLineString", "coordinates": coordinates },
+            "properties": {
+                "kind": "track",
+                "title": track.name,
+                "date": track.date,
+                "href": href,
+                "distance_m": track.distance_m,
+                "elevation_gain_m": track.elevation_gain_m,
+            },
+        }));
+    }
+
+    let body = json!({ "type": "FeatureCollection", "features": features });
+    let resp = (
+        [(header::CONTENT_TYPE, "application/geo+json")],
+        body.to_string(),
+    );
+    Ok(resp.into_response())
+}
+
+/// Chronological.
+async fn query_located_entries(
+    journal: &journal::Model,
+    db: &DatabaseConnection,
+    auth: &AuthSession,
+) -> Result<Vec<journal_entry::Model>, anyhow::Error> {
+    let q = JournalEntry::find()
+        .filter(journal_entry::Column::JournalId.eq(journal.id))
+        // Drafts are still being written, even for their authors.
+        .filter(journal_entry::Column::Draft.eq(false))
+        .filter(journal_entry::Column::Lat.is_not_null())
+        .filter(journal_entry::Column::Lng.is_not_null())
+        .order_by_asc(journal_entry::Column::Date)
+        .order_by_asc(journal_entry::Column::Time);
+    let entries = auth
+        .backend
+        .filter_journal_entries(auth, q)
+        .await?
+        .all(db)
+        .await?;
+    Ok(entries)
+}
@@ -0,0 +1,180 @@
+use axum::{
+    extract::{Path, State},
+    response::{Html, IntoResponse, Response},
+    Form,
+};
+use itertools::Itertools;
+use minijinja::context;
+use sea_orm::{
+    sea_query::OnConflict, ActiveValue, ColumnTrait, EntityTrait, QueryFilter, QueryOrder,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    journal::queries::query_journal_by_slug, AppState, AuthSession, Route, RouteError, RouteResult,
+    Templ, Toast,
+};
+use entities::{journal_member::JournalMemberRole, prelude::*, *};
+
+#[derive(Serialize, Debug)]
+struct Member {
+    user_id: i32,
+    email: String,
+    first_name: String,
+    last_name: String,
+    role: JournalMemberRole,
+}
+
+async fn query_and_render_member_list(
+    state: &AppState,
+    templ: &Templ,
+    journal: &journal::Model,
+    partial: bool,
+) -> Result<Html<String>, RouteError> {
+    let members_db = JournalMember::find()
+        .filter(journal_member::Column::JournalId.eq(journal.id))
+        .find_also_related(User)
+        .order_by_asc(user::Column::Email)
+        .all(&state.db)
+        .await?;
+    let members = members_db
+        .into_iter()
+        .filter_map(|(member, user)| {
+            user.map(|user| Member {
+                user_id: user.id,
+                email: user.email,
+                first_name: user.first_name,
+                last_name: user.last_name,
+                role: member.role,
+            })
+        })
+        .collect_vec();
+
+    // Admins can see everything anyways.
+    let candidates = User::find()
+        .filter(user::Column::Approved.eq(true))
+        .filter(user::Column::Admin.eq(false))
+        .filter(user::Column::Id.is_not_in(members.iter().map(|m| m.user_id)))
+        .order_by_asc(user::Column::Email)
+        .all(&state.db)
+        .await?;
+
+    let ctx = context! {
+        journal,
+        members,
+        candidates,
+        roles => [
+            JournalMemberRole::Viewer,
+            JournalMemberRole::Contributor,
+            JournalMemberRole::Owner,
+        ],
+        href_journal_detail => Route::JournalDetailGet { slug: Some(&journal.slug) }.as_path(),
+        href_add => Route::JournalMemberAddPost.as_path(),
+        href_remove => Route::JournalMemberRemovePost.as_path(),
+    };
+    templ.render_ctx_fragment(
+        "journal_members.html",
+        ctx,
+        if partial {
+            Some("frag_member_list")
+        } else {
+            None
+        },
+    )
+}
+
+pub async fn journal_members_get(
+    state: State<AppState>,
+    templ: Templ,
+    session: AuthSession,
+    Path(slug): Path<String>,
+) -> RouteResult {
+    let journal = query_journal_by_slug(slug, &state.db, &session).await?;
+    let journal = match journal {
+        Ok(journal) => journal,
+        Err(err) => {
+            return Ok(err.render(&templ).into_response());
+        }
+    };
+
+    let html = query_and_render_member_list(&state, &templ, &journal, false).await?;
+    Ok(html.into_response())
+}
+
+#[derive(Deserialize, Debug)]
+pub struct JournalMemberAddPost {
+    journal_id: i32,
+    user_id: i32,
+    role: JournalMemberRole,
+}
+
+/// Adds the user to the journal, or updates their role if they're already a member.
+pub async fn journal_member_add_post(
+    state: State<AppState>,
+    templ: Templ,
+    form: Form<JournalMemberAddPost>,
+) -> Result<Response, Toast> {
+    let r: RouteResult = async {
+        let journal = match Journal::find_by_id(form.journal_id).one(&state.db).await? {
+            Some(journal) => journal,
+            None => return Ok(Toast::danger("Journal not found").into_response()),
+        };
+
+        let data = journal_member::ActiveModel {
+            journal_id: ActiveValue::Set(form.journal_id),
+            user_id: ActiveValue::Set(form.user_id),
+            role: ActiveValue::Set(form.role),
+            id: ActiveValue::NotSet,
+        };
+        JournalMember::insert(data)
+            .on_conflict(
+                OnConflict::columns([
+                    journal_member::Column::JournalId,
+                    journal_member::Column::UserId,
+                ])
+                .update_column(journal_member::Column::Role)
+                .to_owned(),
+            )
+            .exec(&state.db)
+            .await?;
+
+        let html = query_and_render_member_list(&state, &templ, &journal, true).await?;
+        let toast = Toast::success("Member has been saved");
+        let resp = (toast.into_headers(), html);
+        Ok(resp.into_response())
+    }
+    .await;
+    r.map_err(Toast::error)
+}
+
+#[derive(Deserialize, Debug)]
+pub struct JournalMemberRemovePost {
+    journal_id: i32,
+    user_id: i32,
+}
+
+pub async fn journal_member_remove_post(
+    state: State<AppState>,
+    templ: Templ,
+    form: Form<JournalMemberRemovePost>,
+) -> Result<Response, Toast> {
+    let r: RouteResult = async {
+        let journal = match Journal::find_by_id(form.journal_id).one(&state.db).await? {
+            Some(journal) => journal,
+            None => return Ok(Toast::danger("Journal not found").into_response()),
+        };
+
+        JournalMember::delete_many()
+            .filter(journal_member::Column::JournalId.eq(form.journal_id))
+            .filter(journal_member::Column::UserId.eq(form.user_id))
+            .exec(&state.db)
+            .await?;
+
+        let html = query_and_render_member_list(&state, &templ, &journal, true).await?;
+        let toast = Toast::success("Member has been removed");
+        let resp = (toast.into_headers(), html);
+        Ok(resp.into_response())
+    }
+    .await;
+    r.map_err(Toast::error)
+}
@@ -4,14 +4,17 @@ use axum::{
     Form,
 };
 
-use sea_orm::EntityTrait;
+use sea_orm::{EntityTrait, TransactionTrait};
 use serde::Deserialize;
 
 use crate::{
-    utils::serde_utils::string_trim, AppState, FormError, Route, RouteError, RouteResult, Templ,
+    journal::queries::{check_journal_conflict, release_journal_slug},
+    utils::serde_utils::string_trim,
+    AppState, FormError, Route, RouteError, RouteResult, Templ,
 };
 use entities::{prelude::*, *};
 
+/// Also used to edit journals.
 #[derive(Deserialize, Debug)]
 pub struct JournalNew {
     #[serde(deserialize_with = "string_trim")]
@@ -38,6 +41,12 @@ pub async fn journal_new_post(
         }
         Ok(form) => form,
     };
+    let conflict = check_journal_conflict(&form.name, &form.slug, None, &state.db).await?;
+    if let Some(msg) = conflict {
+        let resp = FormError::new(msg).render(&state)?;
+        return Ok(resp.into_response());
+    }
+
     let data = journal::ActiveModel {
         name: sea_orm::ActiveValue::Set(form.name.clone()),
         slug: sea_orm::ActiveValue::Set(form.slug.clone()),
@@ -45,8 +54,10 @@ pub async fn journal_new_post(
         end_date: sea_orm::ActiveValue::Set(form.end_date),
         ..Default::default()
     };
-    // TODO: Handle conflict (can't use on_conflict, since we have two unique constraints).
-    Journal::insert(data).exec(&state.db).await?;
+    let tx = state.db.begin().await?;
+    release_journal_slug(&form.slug, &tx).await?;
+    Journal::insert(data).exec(&tx).await?;
+    tx.commit().await?;
     let href = Route::JournalListGet.as_path();
     let resp = [("HX-Location", href.as_ref())];
     Ok(resp.into_response())
@@ -0,0 +1,208 @@
+use axum::{
+    extract::{multipart::MultipartError, Multipart},
+    response::{Html, IntoResponse as _},
+    Form,
+};
+use minijinja::context;
+use sea_orm::{DatabaseConnection, EntityTrait};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    journal::queries::{add_journal_track, query_journal_tracks, AddJournalTrack},
+    storage::FileStore,
+    AppState, AuthSession, Route, RouteError, RouteResult, Templ, Toast,
+};
+use entities::prelude::*;
+
+/// GPX files are verbose, a day of hiking is easily a few MB.
+pub const MAX_GPX_SIZE: usize = 20 * 1024 * 1024;
+
+struct UploadForm {
+    journal_id: Option<i32>,
+    date: Option<chrono::NaiveDate>,
+    filename: String,
+    body: Option<axum::body::Bytes>,
+}
+
+async fn read_upload_form(mut multipart: Multipart) -> Result<UploadForm, MultipartError> {
+    let mut form = UploadForm {
+        journal_id: None,
+        date: None,
+        filename: String::new(),
+        body: None,
+    };
+    while let Some(field) = multipart.next_field().await? {
+        match field.name() {
+            Some("journal_id") => form.journal_id = field.text().await?.parse().ok(),
+            Some("date") => form.date = field.text().await?.parse().ok(),
+            Some("file") => {
+                form.filename = field.file_name().unwrap_or_default().to_string();
+                form.body = Some(field.bytes().await?);
+            }
+            _ => {}
+        }
+    }
+    Ok(form)
+}
+
+pub async fn journal_track_upload_post(
+    state: AppState,
+    templ: Templ,
+    session: AuthSession,
+    multipart: Multipart,
+) -> RouteResult {
+    let form = match read_upload_form(multipart).await {
+        Ok(form) => form,
+        Err(err) => return Ok(Toast::danger(err.body_text()).into_response()),
+    };
+    let (journal_id, body) = match (form.journal_id, form.body) {
+        (Some(journal_id), Some(body)) if !body.is_empty() => (journal_id, body),
+        _ => return Ok(Toast::danger("Please select a GPX file").into_response()),
+    };
+    if !session
+        .backend
+        .can_write_journal(&session, journal_id, &state.db)
+        .await?
+    {
+        return Ok(Toast::danger("Journal not found").into_response());
+    }
+
+    let input = AddJournalTrack {
+        journal_id,
+        date: form.date,
+        filename: form.filename,
+        body,
+    };
+    if let Err(message) = add_journal_track(input, &state.db, &state.storage).await? {
+        return Ok(Toast::danger(message).into_response());
+    }
+
+    let html = TrackList {
+        journal_id,
+        date: form.date,
+        partial: true,
+    }
+    .query_and_render(&state.db, &state.storage, &templ, &session)
+    .await?;
+    let toast = Toast::success("Track has been uploaded");
+    Ok((toast.into_headers(), html).into_response())
+}
+
+#[derive(Deserialize, Debug)]
+pub struct JournalTrackDeletePost {
+    track_id: i32,
+}
+
+/// The GPX file is left to `StorageCleanup`.
+pub async fn journal_track_delete_post(
+    state: AppState,
+    templ: Templ,
+    session: AuthSession,
+    form: Form<JournalTrackDeletePost>,
+) -> RouteResult {
+    let track = match JournalTrack::find_by_id(form.track_id)
+        .one(&state.db)
+        .await?
+    {
+        Some(track) => track,
+        None => return Ok(Toast::danger("Track not found").into_response()),
+    };
+    if !session
+        .backend
+        .can_write_journal(&session, track.journal_id, &state.db)
+        .await?
+    {
+        return Ok(Toast::danger("Track not found").into_response());
+    }
+
+    JournalTrack::delete_by_id(track.id).exec(&state.db).await?;
+
+    let html = TrackList {
+        journal_id: track.journal_id,
+        date: track.date,
+        partial: true,
+    }
+    .query_and_render(&state.db, &state.storage, &templ, &session)
+    .await?;
+    let toast = Toast::success("Track has been deleted");
+    Ok((toast.into_headers(), html).into_response())
+}
+
+#[derive(Serialize, Debug)]
+struct Track {
+    id: i32,
+    name: String,
+    distance_km: f64,
+    elevation_gain_m: f64,
+    href_gpx: String,
+    href_map: String,
+}
+
+/// Tracks of the day, or of the whole journal, with the upload form.
+pub struct TrackList {
+    pub journal_id: i32,
+    pub date: Option<chrono::NaiveDate>,
+    pub partial: bool,
+}
+
+impl TrackList {
+    pub async fn query_and_render(
+        &self,
+        db: &DatabaseConnection,
+        storage: &FileStore,
+        templ: &Templ,
+        auth: &AuthSession,
+    ) -> Result<Html<String>, RouteError> {
+        let journal = Journal::find_by_id(self.journal_id)
+            .one(db)
+            .await?
+            .ok_or_else(|| RouteError::Other("Journal not found".into()))?;
+        let tracks_db = query_journal_tracks(self.journal_id, self.date, db).await?;
+        let mut tracks = Vec::with_capacity(tracks_db.len());
+        for track in tracks_db {
+            let file = File::find_by_id(track.file_id)
+                .one(db)
+                .await?
+                .ok_or_else(|| RouteError::Other("Track file not found".into()))?;
+            let href_map = format!(
+                "{}#track-{}",
+                Route::JournalMapGet {
+                    slug: Some(&journal.slug)
+                }
+                .as_path(),
+                track.id
+            );
+            tracks.push(Track {
+                id: track.id,
+                name: track.name,
+                distance_km: track.distance_m / 1000.0,
+                elevation_gain_m: track.elevation_gain_m,
+                href_gpx: storage.sign_url(file.bucket, file.key).await?,
+                href_map,
+            });
+        }
+        let can_write = auth
+            .backend
+            .can_write_journal(auth, self.journal_id, db)
+            .await?;
+
+        let ctx = context! {
+            journal_id => self.journal_id,
+            date => self.date,
+            tracks,
+            can_write,
+            href_upload => Route::JournalTrackUploadPost.as_path(),
+            href_delete => Route::JournalTrackDeletePost.as_path(),
+        };
+        let html = templ.render_ctx_fragment(
+            "journal_track_list.html",
+            ctx,
+            if self.partial {
+                Some("fragment_track_list")
+            } else {
+                None
+            },
+        )?;
+        Ok(html)
+    }
+}
@@ -1,13 +1,21 @@
 mod journal_day;
 mod journal_detail;
+mod journal_edit;
 mod journal_entry_edit;
 mod journal_entry_new;
 mod journal_list;
+mod journal_map;
+mod journal_members;
 mod journal_new;
+mod journal_track;
 
 pub use journal_day::*;
 pub use journal_detail::*;
+pub use journal_edit::*;
 pub use journal_entry_edit::*;
 pub use journal_entry_new::*;
 pub use journal_list::*;
+pub use journal_map::*;
+pub use journal_members::*;
 pub use journal_new::*;
+pub use journal_track::*;
@@ -0,0 +1,50 @@
+use axum::{
+    extract::{Request, State},
+    http::Method,
+    middleware::Next,
+    response::{IntoResponse, Redirect, Response},
+};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+
+use crate::{AppState, RouteError};
+use entities::{prelude::*, *};
+
+const PREFIX: &str = "/journal/";
+
+/// Redirects `/journal/{old-slug}/...` to the journal's current slug, so old links keep working
+/// after a rename.
+pub async fn journal_slug_redirect_middleware(
+    State(state): State<AppState>,
+    req: Request,
+    next: Next,
+) -> Result<Response, RouteError> {
+    if req.method() != Method::GET {
+        return Ok(next.run(req).await);
+    }
+    let (slug, rest) = match req.uri().path().strip_prefix(PREFIX) {
+        Some(path) => path.split_once('/').unwrap_or((path, "")),
+        None => return Ok(next.run(req).await),
+    };
+
+    // Live slugs never have a redirect, see `release_journal_slug`.
+    let redirect = JournalSlugRedirect::find()
+        .filter(journal_slug_redirect::Column::Slug.eq(slug))
+        .find_also_related(Journal)
+        .one(&state.db)
+        .await?;
+    let journal = match redirect {
+        Some((_, Some(journal))) => journal,
+        _ => return Ok(next.run(req).await),
+    };
+
+    let mut location = format!("{PREFIX}{}", journal.slug);
+    if !rest.is_empty() {
+        location.push('/');
+        location.push_str(rest);
+    }
+    if let Some(query) = req.uri().query() {
+        location.push('?');
+        location.push_str(query);
+    }
+    Ok(Redirect::to(&location).into_response())
+}
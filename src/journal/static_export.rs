@@ -0,0 +1,357 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::Context;
+use itertools::Itertools;
+use minijinja::context;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder};
+use serde::Serialize;
+use tracing::info;
+
+use crate::{
+    assets::AssetManifest,
+    journal::queries::{query_media_for_journal_entry, MediaFull},
+    storage::FileStore,
+    template_engine::TemplateEngine,
+};
+use entities::{prelude::*, *};
+
+/// Renders a journal into a self-contained folder, viewable without the server:
+///
+/// ```text
+/// index.html
+/// days/2024-05-18.html
+/// media/{key}
+/// assets/{css,js}
+/// ```
+///
+/// Only published entries are exported.
+pub struct StaticExport {
+    pub db: DatabaseConnection,
+    pub storage: Arc<FileStore>,
+    pub template_engine: Arc<TemplateEngine>,
+    /// With `assets` as URL base, so hrefs are relative to the output folder.
+    pub assets: AssetManifest,
+    /// Where the files of the manifest are, usually [`crate::server::ASSETS_DIR`].
+    pub assets_dir: PathBuf,
+    pub slug: String,
+    pub out_dir: PathBuf,
+}
+
+#[derive(Serialize, Debug)]
+struct ExportAssets {
+    css: String,
+    lightgallery_css: String,
+    js: String,
+}
+
+#[derive(Serialize, Debug)]
+struct Entry {
+    datetime: chrono::NaiveDateTime,
+    title: String,
+    address: String,
+    text: String,
+    media: Vec<MediaFull>,
+}
+
+#[derive(Serialize, Debug)]
+struct Day {
+    date: chrono::NaiveDateTime,
+    /// One-based number of days since the journal's start date
+    day_number: i64,
+    href: String,
+    titles: Vec<String>,
+}
+
+fn day_filename(date: chrono::NaiveDate) -> String {
+    format!("{date}.html")
+}
+
+impl StaticExport {
+    pub async fn run(&self) -> Result<(), anyhow::Error> {
+        let journal = Journal::find()
+            .filter(journal::Column::Slug.eq(&self.slug))
+            .one(&self.db)
+            .await?
+            .with_context(|| format!("Journal not found: {}", self.slug))?;
+
+        for dir in ["days", "media", "assets"] {
+            tokio::fs::create_dir_all(self.out_dir.join(dir))
+                .await
+                .with_context(|| format!("Failed to create {dir} in {:?}", self.out_dir))?;
+        }
+        self.copy_assets().await?;
+
+        let entries = JournalEntry::find()
+            .filter(journal_entry::Column::JournalId.eq(journal.id))
+            .filter(journal_entry::Column::Draft.eq(false))
+            .order_by_asc(journal_entry::Column::Date)
+            .order_by_asc(journal_entry::Column::Time)
+            .all(&self.db)
+            .await?;
+        let entries_by_day = entries
+            .into_iter()
+            .chunk_by(|e| e.date)
+            .into_iter()
+            .map(|(date, chunk)| (date, chunk.collect_vec()))
+            .collect_vec();
+
+        let mut days = Vec::with_capacity(entries_by_day.len());
+        for (i, (date, entries_db)) in entries_by_day.iter().enumerate() {
+            let mut entries = Vec::with_capacity(entries_db.len());
+            for e in entries_db {
+                entries.push(Entry {
+                    datetime: chrono::NaiveDateTime::new(e.date, e.time),
+                    title: e.title.clone(),
+                    address: e.address.clone(),
+                    text: e.text.clone(),
+                    media: self.export_media(e.id).await?,
+                });
+            }
+
+            let href_day = |i: usize| entries_by_day.get(i).map(|(date, _)| day_filename(*date));
+            let ctx = context! {
+                assets => self.assets_ctx("../")?,
+                journal,
+                datetime => chrono::NaiveDateTime::new(*date, Default::default()),
+                entries,
+                href_journal_detail => "../index.html",
+                href_journal_day_prev => i.checked_sub(1).and_then(href_day),
+                href_journal_day_next => href_day(i + 1),
+            };
+            let path = Path::new("days").join(day_filename(*date));
+            self.render("export/journal_day.html", ctx, &path).await?;
+
+            days.push(Day {
+                date: chrono::NaiveDateTime::new(*date, Default::default()),
+                day_number: (*date - journal.start_date).num_days() + 1,
+                href: format!("days/{}", day_filename(*date)),
+                titles: entries_db.iter().map(|e| e.title.clone()).collect(),
+            });
+        }
+
+        let ctx = context! {
+            assets => self.assets_ctx("")?,
+            journal,
+            days,
+        };
+        self.render("export/journal_detail.html", ctx, Path::new("index.html"))
+            .await?;
+
+        info!(
+            "Exported {} days of '{}' to {:?}",
+            entries_by_day.len(),
+            self.slug,
+            self.out_dir
+        );
+        Ok(())
+    }
+
+    async fn copy_assets(&self) -> Result<(), anyhow::Error> {
+        for file in self.assets.files() {
+            let src = self.assets_dir.join(file);
+            let dest = self.out_dir.join("assets").join(file);
+            if let Some(parent) = dest.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            tokio::fs::copy(&src, &dest)
+                .await
+                .with_context(|| format!("Failed to copy {src:?} to {dest:?}"))?;
+        }
+        Ok(())
+    }
+
+    fn assets_ctx(&self, root: &str) -> Result<ExportAssets, anyhow::Error> {
+        let href = |key: &str| {
+            self.assets
+                .get_href(key)
+                .map(|href| format!("{root}{href}"))
+                .with_context(|| format!("Asset not found in manifest: {key}"))
+        };
+        Ok(ExportAssets {
+            css: href("css/app.css")?,
+            lightgallery_css: href("vendor/lightgallery.css")?,
+            js: href("js/app.js")?,
+        })
+    }
+
+    /// Downloads the media of the entry, and rewrites their signed URLs to relative paths
+    /// (from `days/`).
+    async fn export_media(&self, entry_id: i32) -> Result<Vec<MediaFull>, anyhow::Error> {
+        let mut media_list = query_media_for_journal_entry(entry_id, &self.db, &self.storage)
+            .await
+            .context("Failed to query media")?;
+
+        let medias_db: HashMap<i32, journal_entry_media::Model> = JournalEntryMedia::find()
+            .filter(journal_entry_media::Column::JournalEntryId.eq(entry_id))
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .map(|media| (media.id, media))
+            .collect();
+        let file_ids = medias_db
+            .values()
+            .flat_map(|media| [media.file_id, media.thumbnail_file_id]);
+        let files_db: HashMap<i32, file::Model> = File::find()
+            .filter(file::Column::Id.is_in(file_ids))
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .map(|file| (file.id, file))
+            .collect();
+
+        for media in &mut media_list {
+            let media_db = &medias_db[&media.id];
+            media.url_original = self.download(&files_db[&media_db.file_id]).await?;
+            media.url_thumbnail = self
+                .download(&files_db[&media_db.thumbnail_file_id])
+                .await?;
+        }
+        Ok(media_list)
+    }
+
+    /// Returns the path of the file, relative to `days/`.
+    /// Already downloaded files are skipped, so an interrupted export can be resumed.
+    /// Files are only moved in place once complete, so partial downloads are never skipped.
+    async fn download(&self, file: &file::Model) -> Result<String, anyhow::Error> {
+        let path = self.out_dir.join("media").join(&file.key);
+        if !tokio::fs::try_exists(&path).await? {
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            let mut partial = path.clone().into_os_string();
+            partial.push(".part");
+            let partial = PathBuf::from(partial);
+            self.storage
+                .download_to_file(file.bucket.clone(), file.key.clone(), &partial)
+                .await
+                .with_context(|| format!("Failed to download {}", file.key))?;
+            tokio::fs::rename(&partial, &path)
+                .await
+                .with_context(|| format!("Failed to move {partial:?} to {path:?}"))?;
+        }
+        Ok(format!("../media/{}", file.key))
+    }
+
+    async fn render(
+        &self,
+        template_name: &str,
+        ctx: minijinja::Value,
+        path: &Path,
+    ) -> Result<(), anyhow::Error> {
+        let html = self
+            .template_engine
+            .get_template(template_name)?
+            .render(ctx)?;
+        let path = self.out_dir.join(path);
+        tokio::fs::write(&path, html)
+            .await
+            .with_context(|| format!("Failed to write {path:?}"))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Bytes;
+
+    use super::*;
+    use crate::{
+        template_engine::init_templates,
+        utils::testing::{insert_entry, insert_file, insert_journal, insert_media, test_db},
+    };
+
+    #[tokio::test]
+    async fn test_export() {
+        let db = test_db().await;
+        let storage = Arc::new(FileStore::temp());
+
+        let journal = insert_journal(&db, "lisbon").await;
+        let date = chrono::NaiveDate::from_ymd_opt(2024, 5, 3).unwrap();
+        let entry = insert_entry(&db, journal.id, None, date).await;
+        for key in ["a.jpeg", "a_thumbnail.jpeg"] {
+            storage
+                .upload("media".to_string(), key.into(), Bytes::from(key))
+                .await
+                .unwrap();
+        }
+        let original = insert_file(&db, "a.jpeg", None).await;
+        let thumbnail = insert_file(&db, "a_thumbnail.jpeg", None).await;
+        insert_media(&db, entry.id, original.id, thumbnail.id).await;
+
+        // Nested like the output of the asset build.
+        let work_dir =
+            std::env::temp_dir().join(format!("cookie-odyssey-test-export-{}", nanoid::nanoid!()));
+        let assets_dir = work_dir.join("dist");
+        let manifest = HashMap::from([
+            ("css/app.css", "css/app.abc.css"),
+            ("vendor/lightgallery.css", "vendor/lightgallery.abc.css"),
+            ("js/app.js", "js/app.abc.js"),
+        ]);
+        for file in manifest.values() {
+            let path = assets_dir.join(file);
+            tokio::fs::create_dir_all(path.parent().unwrap())
+                .await
+                .unwrap();
+            tokio::fs::write(&path, file).await.unwrap();
+        }
+        let manifest_path = assets_dir.join("manifest.json");
+        tokio::fs::write(&manifest_path, serde_json::to_string(&manifest).unwrap())
+            .await
+            .unwrap();
+        let manifest_path = manifest_path.to_str().unwrap();
+
+        let out_dir = work_dir.join("out");
+        let export = StaticExport {
+            db,
+            storage,
+            template_engine: Arc::new(init_templates(
+                AssetManifest::load("/assets".to_string(), manifest_path)
+                    .await
+                    .unwrap(),
+            )),
+            assets: AssetManifest::load("assets".to_string(), manifest_path)
+                .await
+                .unwrap(),
+            assets_dir,
+            slug: journal.slug,
+            out_dir: out_dir.clone(),
+        };
+        export.run().await.unwrap();
+
+        let day = tokio::fs::read_to_string(out_dir.join("days/2024-05-03.html"))
+            .await
+            .unwrap();
+        // Slashes are HTML-escaped.
+        assert!(day.contains(r#"href="..&#x2f;media&#x2f;a.jpeg""#));
+        assert!(day.contains(r#"href="..&#x2f;assets&#x2f;css&#x2f;app.abc.css""#));
+        let index = tokio::fs::read_to_string(out_dir.join("index.html"))
+            .await
+            .unwrap();
+        assert!(index.contains("days&#x2f;2024-05-03.html"));
+        for (path, content) in [
+            ("media/a.jpeg", "a.jpeg"),
+            ("media/a_thumbnail.jpeg", "a_thumbnail.jpeg"),
+            ("assets/css/app.abc.css", "css/app.abc.css"),
+            (
+                "assets/vendor/lightgallery.abc.css",
+                "vendor/lightgallery.abc.css",
+            ),
+            ("assets/js/app.abc.js", "js/app.abc.js"),
+        ] {
+            let actual = tokio::fs::read_to_string(out_dir.join(path)).await.unwrap();
+            assert_eq!(actual, content);
+        }
+        assert!(!tokio::fs::try_exists(out_dir.join("media/a.jpeg.part"))
+            .await
+            .unwrap());
+
+        // Resuming skips the downloaded media.
+        export.run().await.unwrap();
+
+        tokio::fs::remove_dir_all(&work_dir).await.unwrap();
+    }
+}
@@ -0,0 +1,110 @@
+use std::path::Path;
+
+use anyhow::bail;
+
+use crate::video_transcoding::transcode::{probe_media, run, MediaProbe};
+
+// SYNC with `assets/js/media/thumbnail.ts`.
+pub const THUMBNAIL_WIDTH: i32 = 640;
+pub const THUMBNAIL_EXT: &str = ".jpeg";
+
+/// Skips the first frame of videos, which is often black or blurry.
+const POSTER_SEEK_S: f64 = 1.0;
+
+/// Dimensions are as displayed, i.e. after rotation.
+#[derive(Debug, PartialEq)]
+pub struct Thumbnail {
+    pub width_original: i32,
+    pub height_original: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+/// Generates a JPEG thumbnail of an image, or of the first frame of a video, like the browser
+/// does when uploading.
+///
+/// `orientation` is the EXIF orientation of images, applied unless ffmpeg already does.
+pub async fn generate_thumbnail(
+    input_path: &Path,
+    output_path: &Path,
+    orientation: Option<i32>,
+) -> anyhow::Result<Thumbnail> {
+    let probe = probe_media(input_path).await?;
+    render(input_path, output_path, &probe, orientation, None).await
+}
+
+/// Generates the JPEG poster of a video from a frame a little after its start, or from its
+/// middle for very short videos.
+pub async fn generate_poster(
+    input_path: &Path,
+    output_path: &Path,
+    probe: &MediaProbe,
+) -> anyhow::Result<Thumbnail> {
+    let seek_s = match probe.duration_s {
+        Some(duration_s) => POSTER_SEEK_S.min(duration_s / 2.0),
+        None => 0.0,
+    };
+    render(input_path, output_path, probe, None, Some(seek_s)).await
+}
+
+async fn render(
+    input_path: &Path,
+    output_path: &Path,
+    probe: &MediaProbe,
+    orientation: Option<i32>,
+    seek_s: Option<f64>,
+) -> anyhow::Result<Thumbnail> {
+    // ffmpeg rotates according to the display matrix by itself.
+    let (transpose, filter) = match (probe.rotation, orientation) {
+        (Some(_), _) => (probe.is_transposed(), None),
+        (None, Some(3)) => (false, Some("hflip,vflip")),
+        (None, Some(6)) => (true, Some("transpose=clock")),
+        (None, Some(8)) => (true, Some("transpose=cclock")),
+        (None, _) => (false, None),
+    };
+    let (width_original, height_original) = match transpose {
+        true => (probe.height, probe.width),
+        false => (probe.width, probe.height),
+    };
+    if width_original <= 0 || height_original <= 0 {
+        bail!("Invalid dimensions for {input_path:?}");
+    }
+    let width = THUMBNAIL_WIDTH;
+    let height = ((width as f64) * (height_original as f64) / (width_original as f64)).round();
+    let height = (height as i32).max(1);
+
+    let scale = format!("scale={width}:{height}");
+    let filters = match filter {
+        Some(filter) => format!("{filter},{scale}"),
+        None => scale,
+    };
+    let input = input_path.to_string_lossy();
+    let output = output_path.to_string_lossy();
+    let seek = seek_s.map(|seek_s| format!("{seek_s:.3}"));
+    let mut args = vec!["-v", "error"];
+    if let Some(seek) = &seek {
+        // Before the input, to seek by keyframes rather than decode everything.
+        args.extend(["-ss", seek]);
+    }
+    args.extend([
+        "-i",
+        &input,
+        "-vf",
+        &filters,
+        "-frames:v",
+        "1",
+        // JPEG quality, from 2 (best) to 31.
+        "-q:v",
+        "3",
+        "-y",
+        &output,
+    ]);
+    run("ffmpeg", &args).await?;
+
+    Ok(Thumbnail {
+        width_original,
+        height_original,
+        width,
+        height,
+    })
+}
@@ -0,0 +1,258 @@
+use std::{
+    collections::HashMap,
+    env::temp_dir,
+    io::{self, BufWriter},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::Context;
+use itertools::Itertools;
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder,
+};
+use tracing::{info, warn};
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+use crate::storage::FileStore;
+use entities::{journal_entry_media::MediaType, prelude::*, video_transcode_task::TaskStatus, *};
+
+use super::{
+    file_path, ArchiveComment, ArchiveEntry, ArchiveJournal, ArchiveMedia, ArchiveMember,
+    ArchiveTrack, FORMAT_VERSION, JOURNAL_PATH,
+};
+
+/// Exports a journal for [`super::JournalImport`], drafts included.
+pub struct JournalExport {
+    pub db: DatabaseConnection,
+    pub storage: Arc<FileStore>,
+    pub slug: String,
+    pub out_file: PathBuf,
+}
+
+impl JournalExport {
+    pub async fn run(&self) -> Result<(), anyhow::Error> {
+        let work_dir = temp_dir().join(format!("cookie-odyssey-export-{}", nanoid::nanoid!()));
+        tokio::fs::create_dir_all(&work_dir)
+            .await
+            .context("Failed to create work directory for export")?;
+        let res = self.run_in(&work_dir).await;
+        if let Err(err) = tokio::fs::remove_dir_all(&work_dir).await {
+            warn!("Failed to remove {work_dir:?}: {err}");
+        }
+        res
+    }
+
+    async fn run_in(&self, work_dir: &Path) -> Result<(), anyhow::Error> {
+        let journal = Journal::find()
+            .filter(journal::Column::Slug.eq(&self.slug))
+            .one(&self.db)
+            .await?
+            .with_context(|| format!("Journal not found: {}", self.slug))?;
+        let emails: HashMap<i32, String> = User::find()
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .map(|user| (user.id, user.email))
+            .collect();
+        let email = |user_id: i32| {
+            emails
+                .get(&user_id)
+                .cloned()
+                .with_context(|| format!("User not found: {user_id}"))
+        };
+        // Files to include, by ID.
+        let mut files: HashMap<i32, file::Model> = HashMap::new();
+
+        let cover = match journal.cover_id {
+            Some(file_id) => Some(self.add_file(file_id, &mut files).await?),
+            None => None,
+        };
+
+        let members = JournalMember::find()
+            .filter(journal_member::Column::JournalId.eq(journal.id))
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .map(|member| {
+                Ok(ArchiveMember {
+                    email: email(member.user_id)?,
+                    role: member.role,
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let entries_db = JournalEntry::find()
+            .filter(journal_entry::Column::JournalId.eq(journal.id))
+            .order_by_asc(journal_entry::Column::Date)
+            .order_by_asc(journal_entry::Column::Time)
+            .all(&self.db)
+            .await?;
+        let mut entries = Vec::with_capacity(entries_db.len());
+        for entry in entries_db {
+            let medias_db = JournalEntryMedia::find()
+                .filter(journal_entry_media::Column::JournalEntryId.eq(entry.id))
+                .order_by_asc(journal_entry_media::Column::Order)
+                .all(&self.db)
+                .await?;
+            let mut media = Vec::with_capacity(medias_db.len());
+            for m in medias_db {
+                let transcode = match m.media_type {
+                    MediaType::Image => false,
+                    MediaType::Video => !self.is_transcoded(m.file_id).await?,
+                };
+                media.push(ArchiveMedia {
+                    caption: m.caption,
+                    media_type: m.media_type,
+                    file: self.add_file(m.file_id, &mut files).await?,
+                    width: m.width,
+                    height: m.height,
+                    thumbnail_file: self.add_file(m.thumbnail_file_id, &mut files).await?,
+                    thumbnail_width: m.thumbnail_width,
+                    thumbnail_height: m.thumbnail_height,
+                    taken_at: m.taken_at,
+                    lat: m.lat,
+                    lng: m.lng,
+                    camera_model: m.camera_model,
+                    orientation: m.orientation,
+                    duration_s: m.duration_s,
+                    video_codec: m.video_codec,
+                    rotation: m.rotation,
+                    transcode,
+                });
+            }
+            entries.push(ArchiveEntry {
+                date: entry.date,
+                time: entry.time,
+                title: entry.title,
+                text: entry.text,
+                draft: entry.draft,
+                address: entry.address,
+                lat: entry.lat,
+                lng: entry.lng,
+                author_email: entry.author_id.map(email).transpose()?,
+                media,
+            });
+        }
+
+        let comments = JournalComment::find()
+            .filter(journal_comment::Column::JournalId.eq(journal.id))
+            .order_by_asc(journal_comment::Column::Id)
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .map(|comment| {
+                Ok(ArchiveComment {
+                    author_email: email(comment.user_id)?,
+                    created_at: comment.created_at,
+                    date: comment.date,
+                    text: comment.text,
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let tracks_db = JournalTrack::find()
+            .filter(journal_track::Column::JournalId.eq(journal.id))
+            .order_by_asc(journal_track::Column::Id)
+            .all(&self.db)
+            .await?;
+        let mut tracks = Vec::with_capacity(tracks_db.len());
+        for track in tracks_db {
+            tracks.push(ArchiveTrack {
+                date: track.date,
+                name: track.name,
+                file: self.add_file(track.file_id, &mut files).await?,
+                distance_m: track.distance_m,
+                elevation_gain_m: track.elevation_gain_m,
+                polyline: track.polyline,
+                created_at: track.created_at,
+            });
+        }
+
+        let archive = ArchiveJournal {
+            version: FORMAT_VERSION,
+            exported_at: chrono::Utc::now(),
+            name: journal.name,
+            slug: journal.slug,
+            start_date: journal.start_date,
+            end_date: journal.end_date,
+            cover,
+            members,
+            entries,
+            comments,
+            tracks,
+        };
+
+        let mut downloads = Vec::with_capacity(files.len());
+        // Each key is archived once, even if several `file` rows use it.
+        for file in files.values().unique_by(|file| &file.key) {
+            let path = work_dir.join(file.id.to_string());
+            self.storage
+                .download_to_file(file.bucket.clone(), file.key.clone(), &path)
+                .await
+                .with_context(|| format!("Failed to download {}", file.key))?;
+            downloads.push((file_path(&file.key), path));
+        }
+        self.write_archive(&archive, &downloads)?;
+
+        info!(
+            "Exported '{}' ({} entries, {} files) to {:?}",
+            self.slug,
+            archive.entries.len(),
+            downloads.len(),
+            self.out_file
+        );
+        Ok(())
+    }
+
+    /// Returns the path of the file in the archive.
+    async fn add_file(
+        &self,
+        file_id: i32,
+        files: &mut HashMap<i32, file::Model>,
+    ) -> Result<String, anyhow::Error> {
+        if let Some(file) = files.get(&file_id) {
+            return Ok(file_path(&file.key));
+        }
+        let file = File::find_by_id(file_id)
+            .one(&self.db)
+            .await?
+            .with_context(|| format!("File not found: {file_id}"))?;
+        let path = file_path(&file.key);
+        files.insert(file_id, file);
+        Ok(path)
+    }
+
+    async fn is_transcoded(&self, file_id: i32) -> Result<bool, anyhow::Error> {
+        let completed = VideoTranscodeTask::find()
+            .filter(video_transcode_task::Column::FileId.eq(file_id))
+            .filter(video_transcode_task::Column::Status.eq(TaskStatus::Completed))
+            .count(&self.db)
+            .await?;
+        Ok(completed > 0)
+    }
+
+    /// Files are stored as they are, photos and videos don't compress.
+    fn write_archive(
+        &self,
+        archive: &ArchiveJournal,
+        downloads: &[(String, PathBuf)],
+    ) -> Result<(), anyhow::Error> {
+        let out = std::fs::File::create(&self.out_file)
+            .with_context(|| format!("Failed to create {:?}", self.out_file))?;
+        let mut zip = ZipWriter::new(BufWriter::new(out));
+        let options = SimpleFileOptions::default()
+            .compression_method(CompressionMethod::Stored)
+            .large_file(true);
+
+        zip.start_file(JOURNAL_PATH, options)?;
+        serde_json::to_writer_pretty(&mut zip, archive)?;
+        for (name, path) in downloads {
+            let mut file = std::fs::File::open(path)?;
+            zip.start_file(name.as_str(), options)?;
+            io::copy(&mut file, &mut zip)?;
+        }
+        zip.finish()?;
+        Ok(())
+    }
+}
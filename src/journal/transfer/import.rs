@@ -0,0 +1,360 @@
+use std::{
+    collections::{HashMap, HashSet},
+    env::temp_dir,
+    io::{self, BufReader},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{bail, Context};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter,
+    TransactionTrait,
+};
+use tracing::{info, warn};
+use zip::{result::ZipError, ZipArchive};
+
+use crate::{
+    journal::queries::{check_journal_conflict, release_journal_slug},
+    storage::{Bucket, FileStore},
+    video_transcoding::manager::VideoTranscodingManager,
+};
+use entities::{prelude::*, *};
+
+use super::{ArchiveJournal, FORMAT_VERSION, JOURNAL_PATH};
+
+/// Imports a journal from [`super::JournalExport`], with new IDs and file keys, so
+/// instances can be merged.
+/// Members, authors and comments of users unknown to this instance are dropped.
+pub struct JournalImport {
+    pub db: DatabaseConnection,
+    pub storage: Arc<FileStore>,
+    pub archive: PathBuf,
+    /// Overrides, when the journal conflicts with an existing one.
+    pub name: Option<String>,
+    pub slug: Option<String>,
+}
+
+impl JournalImport {
+    pub async fn run(&self) -> Result<(), anyhow::Error> {
+        let work_dir = temp_dir().join(format!("cookie-odyssey-import-{}", nanoid::nanoid!()));
+        tokio::fs::create_dir_all(&work_dir)
+            .await
+            .context("Failed to create work directory for import")?;
+        let res = self.run_in(&work_dir).await;
+        if let Err(err) = tokio::fs::remove_dir_all(&work_dir).await {
+            warn!("Failed to remove {work_dir:?}: {err}");
+        }
+        res
+    }
+
+    async fn run_in(&self, work_dir: &Path) -> Result<(), anyhow::Error> {
+        let archive = std::fs::File::open(&self.archive)
+            .with_context(|| format!("Failed to open {:?}", self.archive))?;
+        let mut zip = ZipArchive::new(BufReader::new(archive)).context("Not a zip archive")?;
+
+        let mut journal: ArchiveJournal = match zip.by_name(JOURNAL_PATH) {
+            Ok(json) => serde_json::from_reader(json).context("Invalid journal archive")?,
+            Err(ZipError::FileNotFound) => bail!("Not a journal archive: {JOURNAL_PATH} not found"),
+            Err(err) => return Err(err.into()),
+        };
+        if journal.version != FORMAT_VERSION {
+            bail!("Unsupported journal archive version: {}", journal.version);
+        }
+        if let Some(name) = &self.name {
+            journal.name = name.clone();
+        }
+        if let Some(slug) = &self.slug {
+            journal.slug = slug.clone();
+        }
+        if let Some(message) =
+            check_journal_conflict(&journal.name, &journal.slug, None, &self.db).await?
+        {
+            bail!("{message}, use --name or --slug to import it under another one");
+        }
+
+        // Blobs first, since they can't be rolled back. Leftovers of a failed import are
+        // collected by `cleanup-storage`.
+        let mut referenced: HashSet<&str> = journal.cover.iter().map(String::as_str).collect();
+        for entry in &journal.entries {
+            for media in &entry.media {
+                referenced.insert(&media.file);
+                referenced.insert(&media.thumbnail_file);
+            }
+        }
+        referenced.extend(journal.tracks.iter().map(|track| track.file.as_str()));
+
+        let bucket = Bucket::Media.to_name(self.storage.conf()).clone();
+        let mut keys: HashMap<String, String> = HashMap::new();
+        for name in referenced {
+            let path = work_dir.join("file");
+            match zip.by_name(name) {
+                Ok(mut data) => {
+                    let mut file = std::fs::File::create(&path)?;
+                    io::copy(&mut data, &mut file)?;
+                }
+                Err(ZipError::FileNotFound) => {
+                    bail!("The journal archive is incomplete: {name} not found")
+                }
+                Err(err) => return Err(err.into()),
+            }
+            let key = match Path::new(name).extension() {
+                Some(ext) => format!("{}.{}", nanoid::nanoid!(), ext.to_string_lossy()),
+                None => nanoid::nanoid!(),
+            };
+            self.storage
+                .upload_file(bucket.clone(), key.clone(), &path)
+                .await
+                .with_context(|| format!("Failed to upload {name}"))?;
+            keys.insert(name.to_string(), key);
+        }
+
+        let emails: HashSet<&str> = journal
+            .members
+            .iter()
+            .map(|member| member.email.as_str())
+            .chain(
+                journal
+                    .entries
+                    .iter()
+                    .filter_map(|e| e.author_email.as_deref()),
+            )
+            .chain(journal.comments.iter().map(|c| c.author_email.as_str()))
+            .collect();
+        let users: HashMap<String, i32> = User::find()
+            .filter(user::Column::Email.is_in(emails.iter().copied()))
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .map(|user| (user.email, user.id))
+            .collect();
+        for email in emails.iter().filter(|email| !users.contains_key(**email)) {
+            warn!("User {email} not found, their membership and comments are skipped");
+        }
+
+        let txn = self.db.begin().await?;
+
+        let mut file_ids: HashMap<&str, i32> = HashMap::new();
+        for (path, key) in &keys {
+            let data = file::ActiveModel {
+                bucket: ActiveValue::Set(bucket.clone()),
+                key: ActiveValue::Set(key.clone()),
+                uploaded_by: ActiveValue::Set(None),
+                id: ActiveValue::NotSet,
+            };
+            let file = data.insert(&txn).await?;
+            file_ids.insert(path, file.id);
+        }
+
+        let data = journal::ActiveModel {
+            name: ActiveValue::Set(journal.name.clone()),
+            slug: ActiveValue::Set(journal.slug.clone()),
+            start_date: ActiveValue::Set(journal.start_date),
+            end_date: ActiveValue::Set(journal.end_date),
+            cover_id: ActiveValue::Set(journal.cover.as_deref().map(|path| file_ids[path])),
+            id: ActiveValue::NotSet,
+        };
+        release_journal_slug(&journal.slug, &txn).await?;
+        let journal_id = data.insert(&txn).await?.id;
+
+        for member in &journal.members {
+            if let Some(user_id) = users.get(&member.email) {
+                let data = journal_member::ActiveModel {
+                    journal_id: ActiveValue::Set(journal_id),
+                    user_id: ActiveValue::Set(*user_id),
+                    role: ActiveValue::Set(member.role),
+                    id: ActiveValue::NotSet,
+                };
+                data.insert(&txn).await?;
+            }
+        }
+
+        let mut transcode_file_ids = Vec::new();
+        for entry in &journal.entries {
+            let data = journal_entry::ActiveModel {
+                journal_id: ActiveValue::Set(journal_id),
+                date: ActiveValue::Set(entry.date),
+                time: ActiveValue::Set(entry.time),
+                title: ActiveValue::Set(entry.title.clone()),
+                text: ActiveValue::Set(entry.text.clone()),
+                draft: ActiveValue::Set(entry.draft),
+                address: ActiveValue::Set(entry.address.clone()),
+                lat: ActiveValue::Set(entry.lat),
+                lng: ActiveValue::Set(entry.lng),
+                author_id: ActiveValue::Set(
+                    entry
+                        .author_email
+                        .as_ref()
+                        .and_then(|email| users.get(email).copied()),
+                ),
+                id: ActiveValue::NotSet,
+            };
+            let entry_id = data.insert(&txn).await?.id;
+
+            for (order, media) in entry.media.iter().enumerate() {
+                let file_id = file_ids[media.file.as_str()];
+                if media.transcode {
+                    transcode_file_ids.push(file_id);
+                }
+                let data = journal_entry_media::ActiveModel {
+                    journal_entry_id: ActiveValue::Set(entry_id),
+                    order: ActiveValue::Set(order as i32),
+                    caption: ActiveValue::Set(media.caption.clone()),
+                    media_type: ActiveValue::Set(media.media_type),
+                    file_id: ActiveValue::Set(file_id),
+                    width: ActiveValue::Set(media.width),
+                    height: ActiveValue::Set(media.height),
+                    thumbnail_file_id: ActiveValue::Set(file_ids[media.thumbnail_file.as_str()]),
+                    thumbnail_width: ActiveValue::Set(media.thumbnail_width),
+                    thumbnail_height: ActiveValue::Set(media.thumbnail_height),
+                    taken_at: ActiveValue::Set(media.taken_at),
+                    lat: ActiveValue::Set(media.lat),
+                    lng: ActiveValue::Set(media.lng),
+                    camera_model: ActiveValue::Set(media.camera_model.clone()),
+                    orientation: ActiveValue::Set(media.orientation),
+                    duration_s: ActiveValue::Set(media.duration_s),
+                    video_codec: ActiveValue::Set(media.video_codec.clone()),
+                    rotation: ActiveValue::Set(media.rotation),
+                    id: ActiveValue::NotSet,
+                };
+                data.insert(&txn).await?;
+            }
+        }
+
+        for comment in &journal.comments {
+            if let Some(user_id) = users.get(&comment.author_email) {
+                let data = journal_comment::ActiveModel {
+                    journal_id: ActiveValue::Set(journal_id),
+                    user_id: ActiveValue::Set(*user_id),
+                    created_at: ActiveValue::Set(comment.created_at),
+                    date: ActiveValue::Set(comment.date),
+                    text: ActiveValue::Set(comment.text.clone()),
+                    id: ActiveValue::NotSet,
+                };
+                data.insert(&txn).await?;
+            }
+        }
+
+        for track in &journal.tracks {
+            let data = journal_track::ActiveModel {
+                journal_id: ActiveValue::Set(journal_id),
+                date: ActiveValue::Set(track.date),
+                name: ActiveValue::Set(track.name.clone()),
+                file_id: ActiveValue::Set(file_ids[track.file.as_str()]),
+                distance_m: ActiveValue::Set(track.distance_m),
+                elevation_gain_m: ActiveValue::Set(track.elevation_gain_m),
+                polyline: ActiveValue::Set(track.polyline.clone()),
+                created_at: ActiveValue::Set(track.created_at),
+                id: ActiveValue::NotSet,
+            };
+            data.insert(&txn).await?;
+        }
+
+        txn.commit().await?;
+
+        // Picked up by the server's transcoder on its next poll.
+        for file_id in &transcode_file_ids {
+            VideoTranscodingManager::enqueue_task(&self.db, *file_id).await?;
+        }
+
+        info!(
+            "Imported '{}' ({} entries, {} files, {} videos to transcode)",
+            journal.slug,
+            journal.entries.len(),
+            keys.len(),
+            transcode_file_ids.len()
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Bytes;
+
+    use super::*;
+    use crate::{
+        journal::transfer::JournalExport,
+        utils::testing::{insert_entry, insert_file, insert_journal, insert_media, test_db},
+    };
+
+    #[tokio::test]
+    async fn test_roundtrip() {
+        let db = test_db().await;
+        let storage = Arc::new(FileStore::temp());
+        let journal = insert_journal(&db, "lisbon").await;
+        let date = chrono::NaiveDate::from_ymd_opt(2024, 5, 3).unwrap();
+        let entry = insert_entry(&db, journal.id, None, date).await;
+        for key in ["a.jpeg", "a_thumbnail.jpeg"] {
+            storage
+                .upload("media".to_string(), key.into(), Bytes::from(key))
+                .await
+                .unwrap();
+        }
+        let original = insert_file(&db, "a.jpeg", None).await;
+        let thumbnail = insert_file(&db, "a_thumbnail.jpeg", None).await;
+        insert_media(&db, entry.id, original.id, thumbnail.id).await;
+
+        // Another journal used to have the slug of the import.
+        let renamed = insert_journal(&db, "porto").await;
+        let data = journal_slug_redirect::ActiveModel {
+            slug: ActiveValue::Set("lisbon-2".to_string()),
+            journal_id: ActiveValue::Set(renamed.id),
+            id: ActiveValue::NotSet,
+        };
+        data.insert(&db).await.unwrap();
+
+        let archive = temp_dir().join(format!("cookie-odyssey-test-{}.zip", nanoid::nanoid!()));
+        JournalExport {
+            db: db.clone(),
+            storage: storage.clone(),
+            slug: journal.slug,
+            out_file: archive.clone(),
+        }
+        .run()
+        .await
+        .unwrap();
+        JournalImport {
+            db: db.clone(),
+            storage: storage.clone(),
+            archive: archive.clone(),
+            name: Some("Lisbon again".to_string()),
+            slug: Some("lisbon-2".to_string()),
+        }
+        .run()
+        .await
+        .unwrap();
+        tokio::fs::remove_file(&archive).await.unwrap();
+
+        let redirects = JournalSlugRedirect::find().all(&db).await.unwrap();
+        assert!(redirects.is_empty());
+        let imported = Journal::find()
+            .filter(journal::Column::Slug.eq("lisbon-2"))
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        let (_, media) = JournalEntry::find()
+            .filter(journal_entry::Column::JournalId.eq(imported.id))
+            .find_also_related(JournalEntryMedia)
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        let media = media.unwrap();
+        assert_eq!(media.width, 1280);
+        let file = File::find_by_id(media.file_id)
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_ne!(file.key, "a.jpeg");
+        let path = temp_dir().join(format!("cookie-odyssey-test-{}", nanoid::nanoid!()));
+        storage
+            .download_to_file(file.bucket, file.key, &path)
+            .await
+            .unwrap();
+        assert_eq!(tokio::fs::read(&path).await.unwrap(), b"a.jpeg");
+        tokio::fs::remove_file(&path).await.unwrap();
+    }
+}
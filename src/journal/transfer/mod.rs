@@ -0,0 +1,105 @@
+//! Moves a journal between instances, as a zip with the journal as JSON, and its files:
+//!
+//! ```text
+//! journal.json
+//! files/{key}
+//! ```
+//!
+//! Users are referenced by email, and must exist on the target instance.
+mod export;
+mod import;
+
+use serde::{Deserialize, Serialize};
+
+use entities::{journal_entry_media::MediaType, journal_member::JournalMemberRole};
+
+pub use export::JournalExport;
+pub use import::JournalImport;
+
+const FORMAT_VERSION: u32 = 1;
+const JOURNAL_PATH: &str = "journal.json";
+
+fn file_path(key: &str) -> String {
+    format!("files/{key}")
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ArchiveJournal {
+    version: u32,
+    exported_at: chrono::DateTime<chrono::Utc>,
+    name: String,
+    slug: String,
+    start_date: chrono::NaiveDate,
+    end_date: Option<chrono::NaiveDate>,
+    /// Path of the file in the archive, like all `file` fields below.
+    cover: Option<String>,
+    members: Vec<ArchiveMember>,
+    entries: Vec<ArchiveEntry>,
+    comments: Vec<ArchiveComment>,
+    tracks: Vec<ArchiveTrack>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ArchiveMember {
+    email: String,
+    role: JournalMemberRole,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ArchiveEntry {
+    date: chrono::NaiveDate,
+    time: chrono::NaiveTime,
+    title: String,
+    text: String,
+    draft: bool,
+    address: String,
+    lat: Option<f32>,
+    lng: Option<f32>,
+    author_email: Option<String>,
+    media: Vec<ArchiveMedia>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ArchiveMedia {
+    caption: String,
+    media_type: MediaType,
+    file: String,
+    width: i32,
+    height: i32,
+    thumbnail_file: String,
+    thumbnail_width: i32,
+    thumbnail_height: i32,
+    taken_at: Option<chrono::NaiveDateTime>,
+    lat: Option<f32>,
+    lng: Option<f32>,
+    camera_model: Option<String>,
+    orientation: Option<i32>,
+    /// Missing in archives exported before videos were probed.
+    #[serde(default)]
+    duration_s: Option<f64>,
+    #[serde(default)]
+    video_codec: Option<String>,
+    #[serde(default)]
+    rotation: Option<i32>,
+    /// Videos which were not transcoded yet.
+    transcode: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ArchiveComment {
+    author_email: String,
+    created_at: i64,
+    date: Option<chrono::NaiveDate>,
+    text: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ArchiveTrack {
+    date: Option<chrono::NaiveDate>,
+    name: String,
+    file: String,
+    distance_m: f64,
+    elevation_gain_m: f64,
+    polyline: String,
+    created_at: chrono::DateTime<chrono::Utc>,
+}
@@ -1,9 +1,14 @@
 pub mod assets;
 pub mod auth;
+pub mod backup;
 pub mod comment;
 pub mod demo;
+pub mod feed;
 pub mod journal;
+pub mod mail;
+pub mod notification;
 pub mod router;
+pub mod search;
 pub mod server;
 pub mod state;
 pub mod storage;
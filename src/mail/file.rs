@@ -0,0 +1,29 @@
+use anyhow::Context;
+use lettre::{message::Mailbox, AsyncFileTransport, AsyncTransport as _, Tokio1Executor};
+use tracing::info;
+
+use crate::mail::{traits::Mailer, Email};
+
+/// Drops emails as `.eml` files in a directory instead of sending them.
+/// Meant for development and tests.
+#[derive(Debug)]
+pub struct FileMailer {
+    pub from: Mailbox,
+    pub transport: AsyncFileTransport<Tokio1Executor>,
+}
+
+#[async_trait::async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, email: Email) -> anyhow::Result<()> {
+        let to = email.to.clone();
+        let subject = email.subject.clone();
+        let message = email.into_message(&self.from)?;
+        let id = self
+            .transport
+            .send(message)
+            .await
+            .context("Failed to write email")?;
+        info!("Wrote email {id} to '{to}': {subject}");
+        Ok(())
+    }
+}
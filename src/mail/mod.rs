@@ -0,0 +1,29 @@
+pub mod file;
+pub mod smtp;
+pub mod traits;
+
+use anyhow::Context;
+use lettre::{message::Mailbox, Message};
+
+/// A plain-text email.
+#[derive(Debug, Clone)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+impl Email {
+    pub fn into_message(self, from: &Mailbox) -> anyhow::Result<Message> {
+        let to: Mailbox = self
+            .to
+            .parse()
+            .with_context(|| format!("Invalid recipient: {}", self.to))?;
+        Message::builder()
+            .from(from.clone())
+            .to(to)
+            .subject(self.subject)
+            .body(self.body)
+            .context("Failed to build email")
+    }
+}
@@ -0,0 +1,48 @@
+use anyhow::Context;
+use lettre::{
+    message::Mailbox, transport::smtp::authentication::Credentials, AsyncSmtpTransport,
+    AsyncTransport as _, Tokio1Executor,
+};
+
+use crate::mail::{traits::Mailer, Email};
+
+#[derive(Debug)]
+pub struct SmtpMailer {
+    pub from: Mailbox,
+    pub transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpMailer {
+    /// Connects with STARTTLS, which is what most providers expect on port 587.
+    pub fn new(
+        from: Mailbox,
+        host: &str,
+        port: Option<u16>,
+        credentials: Option<(String, String)>,
+    ) -> anyhow::Result<Self> {
+        let mut builder = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)
+            .with_context(|| format!("Failed to setup SMTP relay: {host}"))?;
+        if let Some(port) = port {
+            builder = builder.port(port);
+        }
+        if let Some((username, password)) = credentials {
+            builder = builder.credentials(Credentials::new(username, password));
+        }
+        Ok(Self {
+            from,
+            transport: builder.build(),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, email: Email) -> anyhow::Result<()> {
+        let message = email.into_message(&self.from)?;
+        self.transport
+            .send(message)
+            .await
+            .context("Failed to send email")?;
+        Ok(())
+    }
+}
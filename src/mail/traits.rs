@@ -0,0 +1,8 @@
+use std::fmt::Debug;
+
+use super::Email;
+
+#[async_trait::async_trait]
+pub trait Mailer: Send + Sync + 'static + Debug {
+    async fn send(&self, email: Email) -> anyhow::Result<()>;
+}
@@ -1,11 +1,20 @@
-use std::io::Write;
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
 
 use anyhow::Context;
 use clap::{Parser, Subcommand};
 use cookie_odyssey::{
+    assets::AssetManifest,
     auth::sessions::AuthBackend,
-    server::{init_db, init_state},
-    storage::StorageCleanup,
+    backup::{Backup, Restore},
+    journal::{
+        transfer::{JournalExport, JournalImport},
+        FolderImport, StaticExport,
+    },
+    server::{init_db, init_state, manifest_path, ASSETS_DIR},
+    storage::{init_storage, StorageCleanup},
 };
 use sea_orm::EntityTrait;
 use tracing::info;
@@ -65,6 +74,43 @@ enum Commands {
         #[arg(long)]
         dry_run: bool,
     },
+    /// Snapshots the database and every blob into a single archive.
+    Backup {
+        out_file: PathBuf,
+    },
+    /// Restores a backup archive on a fresh instance.
+    Restore {
+        archive: PathBuf,
+    },
+    /// Exports a journal with its files, for `import-journal` on another instance.
+    ExportJournal {
+        slug: String,
+        out_file: PathBuf,
+    },
+    /// Imports a journal from `export-journal`, with its files.
+    ImportJournal {
+        archive: PathBuf,
+        /// Imports the journal under another name.
+        #[arg(long)]
+        name: Option<String>,
+        /// Imports the journal under another slug.
+        #[arg(long)]
+        slug: Option<String>,
+    },
+    /// Imports a folder of photos and videos as draft entries, one per day.
+    ImportFolder {
+        slug: String,
+        dir: PathBuf,
+        /// Email of the user the entries are attributed to.
+        #[arg(long)]
+        author: Option<String>,
+    },
+    /// Renders a journal and its media into a static folder, for archiving.
+    ExportHtml {
+        slug: String,
+        #[arg(long)]
+        out_dir: PathBuf,
+    },
     Server,
     Check,
 }
@@ -80,6 +126,26 @@ impl Cli {
             Commands::Conf => self.print_conf(),
             Commands::CreateAdmin => self.create_admin().await,
             Commands::CleanupStorage { dry_run } => self.cleanup_storage(dry_run).await,
+            Commands::Backup { ref out_file } => self.backup(out_file).await,
+            Commands::Restore { ref archive } => self.restore(archive).await,
+            Commands::ExportJournal {
+                ref slug,
+                ref out_file,
+            } => self.export_journal(slug, out_file).await,
+            Commands::ImportJournal {
+                ref archive,
+                ref name,
+                ref slug,
+            } => self.import_journal(archive, name, slug).await,
+            Commands::ImportFolder {
+                ref slug,
+                ref dir,
+                ref author,
+            } => self.import_folder(slug, dir, author).await,
+            Commands::ExportHtml {
+                ref slug,
+                ref out_dir,
+            } => self.export_html(slug, out_dir).await,
             Commands::Server => self.server().await,
             Commands::Check => self.check().await,
         }
@@ -153,6 +219,90 @@ impl Cli {
         cleanup.run(confirm).await
     }
 
+    async fn backup(&self, out_file: &Path) -> Result<(), anyhow::Error> {
+        let (state, _) = init_state(&self.conf, false).await?;
+        let backup = Backup {
+            db: state.db,
+            storage: state.storage,
+            out_file: out_file.to_path_buf(),
+        };
+        backup.run().await
+    }
+
+    async fn restore(&self, archive: &Path) -> Result<(), anyhow::Error> {
+        // Not `init_state`, which needs the database.
+        let storage = init_storage(&self.conf).await?;
+        let restore = Restore {
+            storage,
+            database_file: PathBuf::from(&self.conf.database_file),
+            archive: archive.to_path_buf(),
+        };
+        restore.run().await
+    }
+
+    async fn export_journal(&self, slug: &str, out_file: &Path) -> Result<(), anyhow::Error> {
+        let (state, _) = init_state(&self.conf, false).await?;
+        let export = JournalExport {
+            db: state.db,
+            storage: state.storage,
+            slug: slug.to_string(),
+            out_file: out_file.to_path_buf(),
+        };
+        export.run().await
+    }
+
+    async fn import_journal(
+        &self,
+        archive: &Path,
+        name: &Option<String>,
+        slug: &Option<String>,
+    ) -> Result<(), anyhow::Error> {
+        let (state, _) = init_state(&self.conf, false).await?;
+        let import = JournalImport {
+            db: state.db,
+            storage: state.storage,
+            archive: archive.to_path_buf(),
+            name: name.clone(),
+            slug: slug.clone(),
+        };
+        import.run().await
+    }
+
+    async fn import_folder(
+        &self,
+        slug: &str,
+        dir: &Path,
+        author: &Option<String>,
+    ) -> Result<(), anyhow::Error> {
+        let (state, _) = init_state(&self.conf, false).await?;
+        let import = FolderImport {
+            db: state.db,
+            storage: state.storage,
+            video_transcoder: state.video_transcoder,
+            slug: slug.to_string(),
+            dir: dir.to_path_buf(),
+            author_email: author.as_deref().map(AuthBackend::normalize_email),
+        };
+        import.run().await
+    }
+
+    async fn export_html(&self, slug: &str, out_dir: &Path) -> Result<(), anyhow::Error> {
+        let (state, _) = init_state(&self.conf, false).await?;
+        let assets = AssetManifest::load("assets".to_string(), &manifest_path())
+            .await
+            .context("Failed to load asset manifest")?;
+        let export = StaticExport {
+            db: state.db,
+            storage: state.storage,
+            template_engine: state.template_engine,
+            assets,
+            assets_dir: PathBuf::from(ASSETS_DIR),
+            slug: slug.to_string(),
+            out_dir: out_dir.to_path_buf(),
+        };
+        export.run().await
+    }
+
     async fn check(&self) -> Result<(), anyhow::Error> {
         let (state, _) = init_state(&self.conf, false).await?;
         let containers = state.storage.list_containers().await?;
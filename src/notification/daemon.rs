@@ -0,0 +1,267 @@
+use anyhow::Context;
+use std::{sync::Arc, time::Duration};
+
+use itertools::Itertools;
+use minijinja::context;
+use sea_orm::{ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder};
+use serde::Serialize;
+use tokio::{select, sync::Notify, task::JoinHandle, time};
+use tracing::{debug, error, info};
+
+use crate::{
+    journal::routes::JournalDayGetPath,
+    mail::{traits::Mailer, Email},
+    template_engine::TemplateEngine,
+    Route,
+};
+use entities::{prelude::*, *};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+/// Digests are sent once the oldest pending notification is this old.
+const DIGEST_INTERVAL: chrono::Duration = chrono::Duration::days(1);
+/// Failed emails are retried on every poll, up to this many times.
+const MAX_ATTEMPTS: i32 = 5;
+/// Entries and comments are cut in emails, the rest is one click away.
+const MAX_TEXT_CHARS: usize = 500;
+
+/// Sends the queued [`notification`]s by email, in the background.
+#[derive(Debug)]
+pub struct NotificationSender {
+    ctx: Arc<SenderContext>,
+    wake: Arc<Notify>,
+    worker_handle: Option<JoinHandle<()>>,
+}
+
+#[derive(Debug)]
+struct SenderContext {
+    db: DatabaseConnection,
+    mailer: Option<Arc<dyn Mailer>>,
+    template_engine: Arc<TemplateEngine>,
+    server_name: String,
+}
+
+impl NotificationSender {
+    pub fn new(
+        db: DatabaseConnection,
+        mailer: Option<Arc<dyn Mailer>>,
+        template_engine: Arc<TemplateEngine>,
+        server_name: String,
+    ) -> Self {
+        Self {
+            ctx: Arc::new(SenderContext {
+                db,
+                mailer,
+                template_engine,
+                server_name,
+            }),
+            wake: Arc::new(Notify::new()),
+            worker_handle: None,
+        }
+    }
+
+    /// Without a mailer, there is nothing to send: notifications aren't queued either.
+    pub fn start(&mut self) {
+        if self.ctx.mailer.is_none() {
+            return;
+        }
+        let ctx = self.ctx.clone();
+        let wake = self.wake.clone();
+        self.worker_handle = Some(tokio::spawn(async move {
+            run(&ctx, &wake).await;
+        }));
+    }
+
+    /// Processes the queue now, for instant notifications.
+    pub fn wake(&self) {
+        self.wake.notify_one();
+    }
+}
+
+async fn run(ctx: &SenderContext, wake: &Notify) {
+    let mut ticker = time::interval(POLL_INTERVAL);
+    ticker.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+    loop {
+        select! {
+            _ = ticker.tick() => {}
+            _ = wake.notified() => {}
+        }
+        if let Err(err) = send_pending(ctx).await {
+            error!("Failed to send notifications: {err:#?}");
+        }
+    }
+}
+
+async fn send_pending(ctx: &SenderContext) -> anyhow::Result<()> {
+    let pending = Notification::find()
+        .filter(notification::Column::SentAt.is_null())
+        .filter(notification::Column::Attempts.lt(MAX_ATTEMPTS))
+        .order_by_asc(notification::Column::Id)
+        .all(&ctx.db)
+        .await?;
+    debug!("Found {} pending notifications.", pending.len());
+
+    let (digests, instants): (Vec<_>, Vec<_>) = pending.into_iter().partition(|n| n.digest);
+    let mut batches = instants.into_iter().map(|n| vec![n]).collect_vec();
+    let digest_before = chrono::Utc::now() - DIGEST_INTERVAL;
+    for (_, group) in digests.into_iter().into_group_map_by(|n| n.user_id) {
+        if group.iter().any(|n| n.created_at <= digest_before) {
+            batches.push(group);
+        }
+    }
+    batches.sort_by_key(|batch| batch[0].id);
+
+    for batch in batches {
+        let ids = batch.iter().map(|n| n.id).collect_vec();
+        match send_batch(ctx, batch).await {
+            Ok(()) => {
+                let data = notification::ActiveModel {
+                    sent_at: ActiveValue::Set(Some(chrono::Utc::now())),
+                    ..Default::default()
+                };
+                Notification::update_many()
+                    .set(data)
+                    .filter(notification::Column::Id.is_in(ids))
+                    .exec(&ctx.db)
+                    .await?;
+            }
+            Err(err) => {
+                error!("Failed to send notifications {ids:?}: {err:#?}");
+                Notification::update_many()
+                    .col_expr(
+                        notification::Column::Attempts,
+                        sea_orm::sea_query::Expr::col(notification::Column::Attempts).add(1),
+                    )
+                    .col_expr(
+                        notification::Column::Error,
+                        sea_orm::sea_query::Expr::value(err.to_string()),
+                    )
+                    .filter(notification::Column::Id.is_in(ids))
+                    .exec(&ctx.db)
+                    .await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[derive(Serialize, Debug)]
+struct NotificationItem {
+    journal_name: String,
+    subject: String,
+    text: String,
+    href: String,
+}
+
+/// Returns `None` when there is nothing to announce anymore (e.g. unpublished entry).
+async fn describe(
+    ctx: &SenderContext,
+    n: &notification::Model,
+) -> anyhow::Result<Option<NotificationItem>> {
+    let journal = match Journal::find_by_id(n.journal_id).one(&ctx.db).await? {
+        Some(journal) => journal,
+        None => return Ok(None),
+    };
+    let day_href = |date| {
+        Route::JournalDayGet(Some(&JournalDayGetPath {
+            slug: journal.slug.clone(),
+            date,
+        }))
+        .as_url(&ctx.server_name)
+    };
+
+    let item = if let Some(entry_id) = n.entry_id {
+        let entry = JournalEntry::find_by_id(entry_id).one(&ctx.db).await?;
+        entry
+            .filter(|entry| !entry.draft)
+            .map(|entry| NotificationItem {
+                subject: format!("New entry: {}", entry.title),
+                text: truncate(&entry.text),
+                href: day_href(entry.date),
+                journal_name: journal.name.clone(),
+            })
+    } else if let Some(comment_id) = n.comment_id {
+        let comment = JournalComment::find_by_id(comment_id)
+            .find_also_related(User)
+            .one(&ctx.db)
+            .await?;
+        comment.map(|(comment, author)| {
+            let author = author.map_or("Someone".to_string(), |author| author.first_name);
+            NotificationItem {
+                subject: format!("New comment from {author}"),
+                text: truncate(&comment.text),
+                href: match comment.date {
+                    Some(date) => day_href(date),
+                    None => Route::JournalDetailGet {
+                        slug: Some(&journal.slug),
+                    }
+                    .as_url(&ctx.server_name),
+                },
+                journal_name: journal.name.clone(),
+            }
+        })
+    } else {
+        None
+    };
+    Ok(item)
+}
+
+fn truncate(text: &str) -> String {
+    let text = text.trim();
+    match text.char_indices().nth(MAX_TEXT_CHARS) {
+        Some((index, _)) => format!("{}…", &text[..index]),
+        None => text.to_string(),
+    }
+}
+
+async fn send_batch(ctx: &SenderContext, batch: Vec<notification::Model>) -> anyhow::Result<()> {
+    let user_id = batch[0].user_id;
+    let digest = batch[0].digest;
+    let user = match User::find_by_id(user_id).one(&ctx.db).await? {
+        Some(user) => user,
+        None => return Ok(()),
+    };
+
+    let mut items = Vec::with_capacity(batch.len());
+    for n in &batch {
+        if let Some(item) = describe(ctx, n).await? {
+            items.push(item);
+        }
+    }
+    let subject = match (digest, items.as_slice()) {
+        (_, []) => {
+            debug!("Nothing left to notify {user_id} about");
+            return Ok(());
+        }
+        (false, [item]) => format!("{}: {}", item.journal_name, item.subject),
+        (_, [_]) => "Your daily digest: 1 update".to_string(),
+        (_, items) => format!("Your daily digest: {} updates", items.len()),
+    };
+
+    let ctx_email = context! { user, items, digest };
+    let body = ctx
+        .template_engine
+        .get_template("email/notification.txt")?
+        .render(ctx_email)?;
+    let mailer = ctx.mailer.as_ref().context("No mailer")?;
+    mailer
+        .send(Email {
+            to: user.email.clone(),
+            subject,
+            body,
+        })
+        .await?;
+    info!("Sent {} notifications to user {user_id}", batch.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate(" short \n"), "short");
+        let long = "é".repeat(MAX_TEXT_CHARS + 1);
+        assert_eq!(truncate(&long), format!("{}…", "é".repeat(MAX_TEXT_CHARS)));
+    }
+}
@@ -0,0 +1,3 @@
+pub mod daemon;
+pub mod queries;
+pub mod routes;
@@ -0,0 +1,163 @@
+use std::collections::HashSet;
+
+use sea_orm::{
+    sea_query::OnConflict, ActiveValue, ColumnTrait, DatabaseConnection, DbErr, EntityTrait,
+    QueryFilter, QuerySelect,
+};
+
+use entities::{journal_subscription::SubscriptionFrequency, prelude::*, *};
+
+pub async fn query_subscription(
+    journal_id: i32,
+    user_id: i32,
+    db: &DatabaseConnection,
+) -> Result<Option<SubscriptionFrequency>, DbErr> {
+    let subscription = JournalSubscription::find()
+        .filter(journal_subscription::Column::JournalId.eq(journal_id))
+        .filter(journal_subscription::Column::UserId.eq(user_id))
+        .one(db)
+        .await?;
+    Ok(subscription.map(|s| s.frequency))
+}
+
+/// Subscribes the user to the journal, or unsubscribes them with `None`.
+pub async fn set_subscription(
+    journal_id: i32,
+    user_id: i32,
+    frequency: Option<SubscriptionFrequency>,
+    db: &DatabaseConnection,
+) -> Result<(), DbErr> {
+    match frequency {
+        Some(frequency) => {
+            let data = journal_subscription::ActiveModel {
+                journal_id: ActiveValue::Set(journal_id),
+                user_id: ActiveValue::Set(user_id),
+                frequency: ActiveValue::Set(frequency),
+                id: ActiveValue::NotSet,
+            };
+            JournalSubscription::insert(data)
+                .on_conflict(
+                    OnConflict::columns([
+                        journal_subscription::Column::JournalId,
+                        journal_subscription::Column::UserId,
+                    ])
+                    .update_column(journal_subscription::Column::Frequency)
+                    .to_owned(),
+                )
+                .exec(db)
+                .await?;
+        }
+        None => {
+            JournalSubscription::delete_many()
+                .filter(journal_subscription::Column::JournalId.eq(journal_id))
+                .filter(journal_subscription::Column::UserId.eq(user_id))
+                .exec(db)
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Subscribers who can (still) see the journal.
+async fn query_subscribers(
+    journal_id: i32,
+    db: &DatabaseConnection,
+) -> Result<Vec<(journal_subscription::Model, user::Model)>, DbErr> {
+    let member_ids: HashSet<i32> = JournalMember::find()
+        .select_only()
+        .column(journal_member::Column::UserId)
+        .filter(journal_member::Column::JournalId.eq(journal_id))
+        .into_tuple()
+        .all(db)
+        .await?
+        .into_iter()
+        .collect();
+    let subscribers = JournalSubscription::find()
+        .filter(journal_subscription::Column::JournalId.eq(journal_id))
+        .find_also_related(User)
+        .all(db)
+        .await?
+        .into_iter()
+        .filter_map(|(subscription, user)| user.map(|user| (subscription, user)))
+        .filter(|(_, user)| user.approved && (user.admin || member_ids.contains(&user.id)))
+        .collect();
+    Ok(subscribers)
+}
+
+async fn enqueue(
+    data: Vec<notification::ActiveModel>,
+    db: &DatabaseConnection,
+) -> Result<(), DbErr> {
+    if data.is_empty() {
+        return Ok(());
+    }
+    Notification::insert_many(data)
+        .on_conflict(
+            OnConflict::columns([notification::Column::UserId, notification::Column::EntryId])
+                .do_nothing()
+                .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await?;
+    Ok(())
+}
+
+fn new_notification(
+    subscription: &journal_subscription::Model,
+    entry_id: Option<i32>,
+    comment_id: Option<i32>,
+) -> notification::ActiveModel {
+    notification::ActiveModel {
+        user_id: ActiveValue::Set(subscription.user_id),
+        journal_id: ActiveValue::Set(subscription.journal_id),
+        entry_id: ActiveValue::Set(entry_id),
+        comment_id: ActiveValue::Set(comment_id),
+        digest: ActiveValue::Set(subscription.frequency == SubscriptionFrequency::Daily),
+        created_at: ActiveValue::Set(chrono::Utc::now()),
+        sent_at: ActiveValue::Set(None),
+        attempts: ActiveValue::Set(0),
+        error: ActiveValue::Set(None),
+        id: ActiveValue::NotSet,
+    }
+}
+
+/// Queues notifications for the subscribers of the journal, except the author.
+/// Entries are only announced once, even if they're unpublished and published again.
+pub async fn notify_entry_published(
+    entry: &journal_entry::Model,
+    db: &DatabaseConnection,
+) -> Result<(), DbErr> {
+    let data = query_subscribers(entry.journal_id, db)
+        .await?
+        .into_iter()
+        .filter(|(_, user)| Some(user.id) != entry.author_id)
+        .map(|(subscription, _)| new_notification(&subscription, Some(entry.id), None))
+        .collect();
+    enqueue(data, db).await
+}
+
+/// Queues notifications for the subscribers who commented on the same day (or on the journal
+/// itself), except the author.
+pub async fn notify_comment_added(
+    comment: &journal_comment::Model,
+    db: &DatabaseConnection,
+) -> Result<(), DbErr> {
+    let q = JournalComment::find()
+        .select_only()
+        .column(journal_comment::Column::UserId)
+        .distinct()
+        .filter(journal_comment::Column::JournalId.eq(comment.journal_id));
+    let q = match comment.date {
+        Some(date) => q.filter(journal_comment::Column::Date.eq(date)),
+        None => q.filter(journal_comment::Column::Date.is_null()),
+    };
+    let commenters: HashSet<i32> = q.into_tuple().all(db).await?.into_iter().collect();
+
+    let data = query_subscribers(comment.journal_id, db)
+        .await?
+        .into_iter()
+        .filter(|(_, user)| user.id != comment.user_id && commenters.contains(&user.id))
+        .map(|(subscription, _)| new_notification(&subscription, None, Some(comment.id)))
+        .collect();
+    enqueue(data, db).await
+}
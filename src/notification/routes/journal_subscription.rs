@@ -0,0 +1,71 @@
+use axum::{
+    response::{Html, IntoResponse as _},
+    Form,
+};
+use minijinja::context;
+use serde::Deserialize;
+
+use crate::{
+    notification::queries::{query_subscription, set_subscription},
+    utils::serde_utils::empty_string_as_none,
+    AppState, AuthSession, Route, RouteError, RouteResult, Templ, Toast,
+};
+use entities::{journal_subscription::SubscriptionFrequency, prelude::*, *};
+use sea_orm::EntityTrait;
+
+/// Email notifications settings of the current user, for the journal.
+pub async fn render_journal_subscription(
+    state: &AppState,
+    templ: &Templ,
+    session: &AuthSession,
+    journal: &journal::Model,
+) -> Result<Html<String>, RouteError> {
+    let user = session.user.as_ref().expect("Should be authenticated");
+    let frequency = query_subscription(journal.id, user.0.id, &state.db).await?;
+    let ctx = context! {
+        journal,
+        frequency,
+        href_subscribe => Route::JournalSubscriptionPost.as_path(),
+    };
+    templ.render_ctx("notification/journal_subscription.html", ctx)
+}
+
+#[derive(Deserialize, Debug)]
+pub struct JournalSubscriptionPost {
+    journal_id: i32,
+    /// Empty to unsubscribe.
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    frequency: Option<SubscriptionFrequency>,
+}
+
+pub async fn journal_subscription_post(
+    state: AppState,
+    templ: Templ,
+    session: AuthSession,
+    form: Form<JournalSubscriptionPost>,
+) -> RouteResult {
+    if state.mailer.is_none() {
+        return Ok(Toast::danger("Email notifications are disabled").into_response());
+    }
+    if !session
+        .backend
+        .can_view_journal(&session, form.journal_id, &state.db)
+        .await?
+    {
+        return Ok(Toast::danger("Journal not found").into_response());
+    }
+    let journal = Journal::find_by_id(form.journal_id)
+        .one(&state.db)
+        .await?
+        .ok_or_else(|| RouteError::Other("Journal not found".into()))?;
+
+    let user = session.user.as_ref().expect("Should be authenticated");
+    set_subscription(journal.id, user.0.id, form.frequency, &state.db).await?;
+
+    let html = render_journal_subscription(&state, &templ, &session, &journal).await?;
+    let toast = Toast::success(match form.frequency {
+        Some(_) => "Subscribed",
+        None => "Unsubscribed",
+    });
+    Ok((toast.into_headers(), html).into_response())
+}
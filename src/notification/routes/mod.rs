@@ -0,0 +1,3 @@
+mod journal_subscription;
+
+pub use journal_subscription::*;
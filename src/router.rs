@@ -1,6 +1,7 @@
 use std::borrow::Cow;
 
 use axum::{
+    extract::DefaultBodyLimit,
     routing::{get, post, put},
     Router,
 };
@@ -12,7 +13,10 @@ use crate::auth::{
 };
 use crate::comment::routes as comment;
 use crate::demo::routes as demo;
-use crate::journal::routes as journal;
+use crate::feed::routes as feed;
+use crate::journal::{routes as journal, slug_redirect::journal_slug_redirect_middleware};
+use crate::notification::routes as notification;
+use crate::search::routes as search;
 use crate::storage::routes as storage;
 use crate::video_transcoding::routes as video_transcoding;
 use crate::AppState;
@@ -20,12 +24,46 @@ use crate::AppState;
 // Idea stolen from https://github.com/jdevries3133/calcount/blob/main/src/routes.rs
 // Type-safe routes!
 pub enum Route<'a> {
+    FeedTokenResetPost,
     ForgotPasswordGet,
     ForgotPasswordPost,
+    PasswordResetGet {
+        token: Option<&'a str>,
+    },
+    PasswordResetPost {
+        token: Option<&'a str>,
+    },
     JournalDetailGet {
         slug: Option<&'a str>,
     },
+    JournalEditGet {
+        slug: Option<&'a str>,
+    },
+    JournalEditPost {
+        slug: Option<&'a str>,
+    },
+    JournalDeletePost {
+        slug: Option<&'a str>,
+    },
+    JournalFeedGet {
+        slug: Option<&'a str>,
+        query: Option<&'a feed::JournalFeedQuery>,
+    },
     JournalListGet,
+    JournalMapGet {
+        slug: Option<&'a str>,
+    },
+    JournalMapGeoJsonGet {
+        slug: Option<&'a str>,
+    },
+    JournalMembersGet {
+        slug: Option<&'a str>,
+    },
+    JournalMemberAddPost,
+    JournalMemberRemovePost,
+    JournalTrackUploadPost,
+    JournalTrackDeletePost,
+    JournalSubscriptionPost,
     JournalNewGet,
     JournalNewPost,
     JournalEntryNewGet(
@@ -46,11 +84,22 @@ pub enum Route<'a> {
     JournalEntryPublishPost {
         entry_id: Option<i32>,
     },
+    JournalEntryUnpublishPost {
+        entry_id: Option<i32>,
+    },
+    JournalEntryDeletePost {
+        entry_id: Option<i32>,
+    },
     JournalDayGet(Option<&'a journal::JournalDayGetPath>),
     JournalEntryMediaCommitPost,
+    JournalEntryMediaListGet {
+        entry_id: Option<i32>,
+    },
     JournalEntryMediaEditCaptionPost,
     JournalEntryMediaDelete,
     JournalEntryMediaReorder,
+    JournalEntryMediaSetCoverPost,
+    JournalEntryMediaUseLocationPost,
     JournalCommentAddPost(Option<&'a comment::JournalCommentAddQuery>),
     JournalCommentEditPost(Option<&'a comment::JournalCommentEditQuery>),
     JournalCommentDeletePost(Option<&'a comment::JournalCommentDeleteQuery>),
@@ -60,9 +109,16 @@ pub enum Route<'a> {
     MediaUploadUrlPost,
     // TODO: Why do we need this?
     MediaUploadProxyPut(Option<&'a storage::MediaUploadProxyParams>),
+    LocalFileGet(Option<&'a storage::LocalFileParams>),
+    LocalFilePut(Option<&'a storage::LocalFileParams>),
     VideoTranscodeCallbackPost(Option<&'a video_transcoding::VideoTranscodeCallbackQuery>),
+    VideoTranscodeTaskListGet(Option<&'a video_transcoding::VideoTranscodeTaskListQuery>),
+    VideoTranscodeTaskRetryPost,
+    VideoTranscodeTaskCancelPost,
+    VideoTranscodeTaskCompletePost,
     RegisterGet,
     RegisterPost,
+    SearchGet(Option<&'a search::SearchQuery>),
     UserListGet,
     UserListApprovePost,
     UserListDeletePost,
@@ -76,11 +132,54 @@ impl<'a> Route<'a> {
         match self {
             Route::ForgotPasswordGet => "/forgot-password".into(),
             Route::ForgotPasswordPost => "/forgot-password".into(),
+            Route::PasswordResetGet { token } => match token {
+                Some(token) => format!("/reset-password/{token}").into(),
+                None => "/reset-password/{token}".into(),
+            },
+            Route::PasswordResetPost { token } => match token {
+                Some(token) => format!("/reset-password/{token}").into(),
+                None => "/reset-password/{token}".into(),
+            },
             Route::JournalDetailGet { slug } => match slug {
                 Some(slug) => format!("/journal/{slug}").into(),
                 None => "/journal/{slug}".into(),
             },
+            Route::JournalEditGet { slug } => match slug {
+                Some(slug) => format!("/journal/{slug}/edit").into(),
+                None => "/journal/{slug}/edit".into(),
+            },
+            Route::JournalEditPost { slug } => match slug {
+                Some(slug) => format!("/journal/{slug}/edit").into(),
+                None => "/journal/{slug}/edit".into(),
+            },
+            Route::JournalDeletePost { slug } => match slug {
+                Some(slug) => format!("/journal/{slug}/delete").into(),
+                None => "/journal/{slug}/delete".into(),
+            },
+            Route::JournalFeedGet { slug, query } => match (slug, query) {
+                (Some(slug), Some(query)) => {
+                    let qs = serde_qs::to_string(query).expect(EXPECT_QS);
+                    format!("/feed/{slug}?{qs}").into()
+                }
+                _ => "/feed/{slug}".into(),
+            },
             Route::JournalListGet => "/".into(),
+            Route::JournalMapGet { slug } => match slug {
+                Some(slug) => format!("/journal/{slug}/map").into(),
+                None => "/journal/{slug}/map".into(),
+            },
+            Route::JournalMapGeoJsonGet { slug } => match slug {
+                Some(slug) => format!("/journal/{slug}/map.geojson").into(),
+                None => "/journal/{slug}/map.geojson".into(),
+            },
+            Route::JournalMembersGet { slug } => match slug {
+                Some(slug) => format!("/journal/{slug}/members").into(),
+                None => "/journal/{slug}/members".into(),
+            },
+            Route::JournalMemberAddPost => "/hx/journal-members/add".into(),
+            Route::JournalMemberRemovePost => "/hx/journal-members/remove".into(),
+            Route::JournalTrackUploadPost => "/hx/journal-tracks/upload".into(),
+            Route::JournalTrackDeletePost => "/hx/journal-tracks/delete".into(),
             Route::JournalNewGet => "/new-journal".into(),
             Route::JournalNewPost => "/new-journal".into(),
             Route::JournalEntryNewGet(params) => match params {
@@ -108,6 +207,14 @@ impl<'a> Route<'a> {
                 Some(entry_id) => format!("/entry/{entry_id}/publish").into(),
                 None => "/entry/{entry_id}/publish".into(),
             },
+            Route::JournalEntryUnpublishPost { entry_id } => match entry_id {
+                Some(entry_id) => format!("/entry/{entry_id}/unpublish").into(),
+                None => "/entry/{entry_id}/unpublish".into(),
+            },
+            Route::JournalEntryDeletePost { entry_id } => match entry_id {
+                Some(entry_id) => format!("/entry/{entry_id}/delete").into(),
+                None => "/entry/{entry_id}/delete".into(),
+            },
             Route::JournalDayGet(params) => match params {
                 None => "/journal/{slug}/entry/{date}".into(),
                 Some(params) => format!("/journal/{}/entry/{}", params.slug, params.date).into(),
@@ -133,6 +240,8 @@ impl<'a> Route<'a> {
                     format!("/hx/delete-comment?{qs}").into()
                 }
             },
+            Route::FeedTokenResetPost => "/hx/feed-token/reset".into(),
+            Route::JournalSubscriptionPost => "/hx/journal-subscription".into(),
             Route::LoginGet => "/login".into(),
             Route::LoginPost => "/login".into(),
             Route::LogoutPost => "/logout".into(),
@@ -144,6 +253,20 @@ impl<'a> Route<'a> {
                     format!("/api/media-upload?{qs}").into()
                 }
             },
+            Route::LocalFileGet(params) => match params {
+                None => "/files".into(),
+                Some(params) => {
+                    let qs = serde_qs::to_string(params).expect(EXPECT_QS);
+                    format!("/files?{qs}").into()
+                }
+            },
+            Route::LocalFilePut(params) => match params {
+                None => "/files".into(),
+                Some(params) => {
+                    let qs = serde_qs::to_string(params).expect(EXPECT_QS);
+                    format!("/files?{qs}").into()
+                }
+            },
             Route::VideoTranscodeCallbackPost(params) => match params {
                 None => "/api/video-transcode-callback".into(),
                 Some(params) => {
@@ -152,11 +275,34 @@ impl<'a> Route<'a> {
                 }
             },
             Route::JournalEntryMediaCommitPost => "/api/entry-commit".into(),
+            Route::JournalEntryMediaListGet { entry_id } => match entry_id {
+                Some(entry_id) => format!("/hx/entry/{entry_id}/media").into(),
+                None => "/hx/entry/{entry_id}/media".into(),
+            },
             Route::JournalEntryMediaEditCaptionPost => "/api/media-caption-edit".into(),
             Route::JournalEntryMediaDelete => "/api/media-delete".into(),
             Route::JournalEntryMediaReorder => "/api/media-reorder".into(),
+            Route::JournalEntryMediaSetCoverPost => "/api/media-set-cover".into(),
+            Route::JournalEntryMediaUseLocationPost => "/api/media-use-location".into(),
             Route::RegisterGet => "/register".into(),
             Route::RegisterPost => "/register".into(),
+            Route::SearchGet(params) => match params {
+                None => "/search".into(),
+                Some(params) => {
+                    let qs = serde_qs::to_string(params).expect(EXPECT_QS);
+                    format!("/search?{qs}").into()
+                }
+            },
+            Route::VideoTranscodeTaskListGet(params) => match params {
+                None => "/video-transcode-tasks".into(),
+                Some(params) => {
+                    let qs = serde_qs::to_string(params).expect(EXPECT_QS);
+                    format!("/video-transcode-tasks?{qs}").into()
+                }
+            },
+            Route::VideoTranscodeTaskRetryPost => "/hx/video-transcode-tasks/retry".into(),
+            Route::VideoTranscodeTaskCancelPost => "/hx/video-transcode-tasks/cancel".into(),
+            Route::VideoTranscodeTaskCompletePost => "/hx/video-transcode-tasks/complete".into(),
             Route::UserListGet => "/users".into(),
             Route::UserListApprovePost => "/hx/users/approve".into(),
             Route::UserListDeletePost => "/hx/users/delete".into(),
@@ -175,6 +321,12 @@ macro_rules! admin {
     };
 }
 
+macro_rules! contributor {
+    ($route:expr) => {
+        $route.route_layer(permission_required!(AuthBackend, Permission::Contributor))
+    };
+}
+
 fn get_protected_routes() -> Router<AppState> {
     Router::new()
         .route(&Route::LogoutPost.as_path(), get(auth::logout_post))
@@ -187,6 +339,32 @@ fn get_protected_routes() -> Router<AppState> {
             &Route::JournalDayGet(None).as_path(),
             get(journal::journal_day_get),
         )
+        .route(
+            &Route::JournalMapGet { slug: None }.as_path(),
+            get(journal::journal_map_get),
+        )
+        .route(
+            &Route::JournalMapGeoJsonGet { slug: None }.as_path(),
+            get(journal::journal_map_geojson_get),
+        )
+        .route(
+            &Route::JournalTrackUploadPost.as_path(),
+            contributor!(post(journal::journal_track_upload_post)
+                .layer(DefaultBodyLimit::max(journal::MAX_GPX_SIZE))),
+        )
+        .route(
+            &Route::JournalTrackDeletePost.as_path(),
+            contributor!(post(journal::journal_track_delete_post)),
+        )
+        .route(&Route::SearchGet(None).as_path(), get(search::search_get))
+        .route(
+            &Route::FeedTokenResetPost.as_path(),
+            post(feed::feed_token_reset_post),
+        )
+        .route(
+            &Route::JournalSubscriptionPost.as_path(),
+            post(notification::journal_subscription_post),
+        )
         .route(
             &Route::JournalCommentAddPost(None).as_path(),
             post(comment::journal_comment_add_post),
@@ -207,49 +385,93 @@ fn get_protected_routes() -> Router<AppState> {
             &Route::JournalNewPost.as_path(),
             admin!(post(journal::journal_new_post)),
         )
+        .route(
+            &Route::JournalEditGet { slug: None }.as_path(),
+            admin!(get(journal::journal_edit_get)),
+        )
+        .route(
+            &Route::JournalEditPost { slug: None }.as_path(),
+            admin!(post(journal::journal_edit_post)),
+        )
+        .route(
+            &Route::JournalDeletePost { slug: None }.as_path(),
+            admin!(post(journal::journal_delete_post)),
+        )
+        .route(
+            &Route::JournalMembersGet { slug: None }.as_path(),
+            admin!(get(journal::journal_members_get)),
+        )
+        .route(
+            &Route::JournalMemberAddPost.as_path(),
+            admin!(post(journal::journal_member_add_post)),
+        )
+        .route(
+            &Route::JournalMemberRemovePost.as_path(),
+            admin!(post(journal::journal_member_remove_post)),
+        )
         .route(
             &Route::JournalEntryNewGet(None).as_path(),
-            admin!(get(journal::journal_entry_new_get)),
+            contributor!(get(journal::journal_entry_new_get)),
         )
         .route(
             &Route::JournalEntryNewPost { slug: None }.as_path(),
-            admin!(post(journal::journal_entry_new_post)),
+            contributor!(post(journal::journal_entry_new_post)),
         )
         .route(
             &Route::JournalEntryEditGet { entry_id: None }.as_path(),
-            admin!(get(journal::journal_entry_edit_get)),
+            contributor!(get(journal::journal_entry_edit_get)),
         )
         .route(
             &Route::JournalEntryEditPost { entry_id: None }.as_path(),
-            admin!(post(journal::journal_entry_edit_post)),
+            contributor!(post(journal::journal_entry_edit_post)),
         )
         .route(
             &Route::JournalEntryPublishPost { entry_id: None }.as_path(),
-            admin!(post(journal::journal_entry_publish_post)),
+            contributor!(post(journal::journal_entry_publish_post)),
+        )
+        .route(
+            &Route::JournalEntryUnpublishPost { entry_id: None }.as_path(),
+            contributor!(post(journal::journal_entry_unpublish_post)),
+        )
+        .route(
+            &Route::JournalEntryDeletePost { entry_id: None }.as_path(),
+            contributor!(post(journal::journal_entry_delete_post)),
         )
         .route(
             &Route::JournalEntryMediaCommitPost.as_path(),
-            admin!(post(journal::journal_entry_media_commit_post)),
+            contributor!(post(journal::journal_entry_media_commit_post)),
+        )
+        .route(
+            &Route::JournalEntryMediaListGet { entry_id: None }.as_path(),
+            contributor!(get(journal::journal_entry_media_list_get)),
         )
         .route(
             &Route::JournalEntryMediaEditCaptionPost.as_path(),
-            admin!(post(journal::journal_entry_media_caption_edit)),
+            contributor!(post(journal::journal_entry_media_caption_edit)),
         )
         .route(
             &Route::JournalEntryMediaDelete.as_path(),
-            admin!(post(journal::journal_entry_media_delete)),
+            contributor!(post(journal::journal_entry_media_delete)),
         )
         .route(
             &Route::JournalEntryMediaReorder.as_path(),
-            admin!(post(journal::journal_entry_media_reorder)),
+            contributor!(post(journal::journal_entry_media_reorder)),
+        )
+        .route(
+            &Route::JournalEntryMediaSetCoverPost.as_path(),
+            contributor!(post(journal::journal_entry_media_set_cover_post)),
+        )
+        .route(
+            &Route::JournalEntryMediaUseLocationPost.as_path(),
+            contributor!(post(journal::journal_entry_media_use_location_post)),
         )
         .route(
             &Route::MediaUploadUrlPost.as_path(),
-            admin!(post(storage::media_upload_url_post)),
+            contributor!(post(storage::media_upload_url_post)),
         )
         .route(
             &Route::MediaUploadProxyPut(None).as_path(),
-            admin!(put(storage::media_upload_proxy)),
+            contributor!(put(storage::media_upload_proxy)),
         )
         .route(
             &Route::UserListGet.as_path(),
@@ -263,6 +485,22 @@ fn get_protected_routes() -> Router<AppState> {
             &Route::UserListDeletePost.as_path(),
             admin!(post(auth::user_delete_post)),
         )
+        .route(
+            &Route::VideoTranscodeTaskListGet(None).as_path(),
+            admin!(get(video_transcoding::video_transcode_task_list_get)),
+        )
+        .route(
+            &Route::VideoTranscodeTaskRetryPost.as_path(),
+            admin!(post(video_transcoding::video_transcode_task_retry_post)),
+        )
+        .route(
+            &Route::VideoTranscodeTaskCancelPost.as_path(),
+            admin!(post(video_transcoding::video_transcode_task_cancel_post)),
+        )
+        .route(
+            &Route::VideoTranscodeTaskCompletePost.as_path(),
+            admin!(post(video_transcoding::video_transcode_task_complete_post)),
+        )
 }
 
 fn get_public_routes() -> Router<AppState> {
@@ -279,10 +517,32 @@ fn get_public_routes() -> Router<AppState> {
             &Route::ForgotPasswordPost.as_path(),
             post(auth::forgot_password_post),
         )
+        .route(
+            &Route::PasswordResetGet { token: None }.as_path(),
+            get(auth::password_reset_get),
+        )
+        .route(
+            &Route::PasswordResetPost { token: None }.as_path(),
+            post(auth::password_reset_post),
+        )
         .route(
             &Route::DemoThumbnailGet.as_path(),
             get(demo::demo_thumbnail_get),
         )
+        // Authorized by the feed token in the query string.
+        .route(
+            &Route::JournalFeedGet {
+                slug: None,
+                query: None,
+            }
+            .as_path(),
+            get(feed::journal_feed_get),
+        )
+        // Authorized by the signature in the query string.
+        .route(
+            &Route::LocalFileGet(None).as_path(),
+            get(storage::local_file_get).put(storage::local_file_put),
+        )
 }
 
 fn get_github_action_routes() -> Router<AppState> {
@@ -294,6 +554,10 @@ fn get_github_action_routes() -> Router<AppState> {
 
 pub fn init_router(state: AppState) -> Router<AppState> {
     get_protected_routes()
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            journal_slug_redirect_middleware,
+        ))
         .route_layer(login_required!(
             AuthBackend,
             login_url = &Route::LoginGet.as_path()
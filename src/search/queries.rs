@@ -0,0 +1,403 @@
+use std::collections::HashMap;
+
+use itertools::Itertools;
+use sea_orm::{
+    sea_query::{SelectStatement, SqliteQueryBuilder},
+    ColumnTrait, DatabaseConnection, EntityTrait, FromQueryResult, QueryFilter, QuerySelect,
+    QueryTrait, Statement,
+};
+use serde::Serialize;
+
+use entities::{prelude::*, *};
+
+use crate::{
+    auth::sessions::AuthBackend, journal::routes::JournalDayGetPath, AuthSession, AuthUser, Route,
+};
+
+/// Maximum number of hits, per table and overall.
+const LIMIT: u64 = 50;
+/// Number of tokens in snippets.
+const SNIPPET_TOKENS: u64 = 16;
+// Control characters, so we can tell highlights apart from user content when escaping.
+const MARK_START: &str = "\u{2}";
+const MARK_END: &str = "\u{3}";
+
+/// Turns free text into an FTS5 query, so users don't have to learn the syntax (and can't trip
+/// on it). Every word must match, and the last one may be incomplete (search-as-you-type).
+pub fn to_fts_query(input: &str) -> Option<String> {
+    let words = input
+        .split_whitespace()
+        .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+        .collect_vec();
+    let (last, rest) = words.split_last()?;
+    let mut q = rest.join(" ");
+    if !q.is_empty() {
+        q.push(' ');
+    }
+    q.push_str(last);
+    q.push('*');
+    Some(q)
+}
+
+/// Escapes the snippet for HTML, and wraps highlights with `<mark>`.
+fn highlight(snippet: &str) -> String {
+    minijinja::HtmlEscape(snippet)
+        .to_string()
+        .replace(MARK_START, "<mark>")
+        .replace(MARK_END, "</mark>")
+}
+
+#[derive(FromQueryResult, Debug)]
+struct FtsMatch {
+    id: i32,
+    snippet: String,
+    rank: f64,
+}
+
+/// Which matches of an FTS table the user can see: those where `key` is in `visible`.
+struct Visibility<'a> {
+    /// Joins the table `key` belongs to, if it isn't the FTS table.
+    join: &'a str,
+    key: &'a str,
+    visible: SelectStatement,
+}
+
+/// Filters on visibility before the limit, so hidden matches can't crowd out visible ones.
+async fn query_fts(
+    table: &str,
+    visibility: Visibility<'_>,
+    fts_query: &str,
+    db: &DatabaseConnection,
+) -> Result<Vec<FtsMatch>, sea_orm::DbErr> {
+    let Visibility { join, key, visible } = visibility;
+    let (visible, visible_values) = visible.build(SqliteQueryBuilder);
+    let mut values = vec![
+        MARK_START.into(),
+        MARK_END.into(),
+        SNIPPET_TOKENS.into(),
+        fts_query.into(),
+    ];
+    values.extend(visible_values);
+    values.push(LIMIT.into());
+    let q = Statement::from_sql_and_values(
+        sea_orm::DatabaseBackend::Sqlite,
+        format!(
+            r#"
+            SELECT
+                {table}.rowid AS id,
+                snippet({table}, -1, ?, ?, '…', ?) AS snippet,
+                {table}.rank AS rank
+            FROM {table}
+            {join}
+            WHERE {table} MATCH ? AND {key} IN ({visible})
+            ORDER BY {table}.rank
+            LIMIT ?
+            "#
+        ),
+        values,
+    );
+    FtsMatch::find_by_statement(q).all(db).await
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchHitKind {
+    Entry,
+    Caption,
+    Comment,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SearchHit {
+    pub kind: SearchHitKind,
+    pub journal_name: String,
+    pub date: Option<chrono::NaiveDateTime>,
+    pub title: Option<String>,
+    /// Safe HTML.
+    pub snippet: String,
+    pub href: String,
+    #[serde(skip)]
+    rank: f64,
+}
+
+fn day_href(journal: &journal::Model, date: chrono::NaiveDate) -> String {
+    Route::JournalDayGet(Some(&JournalDayGetPath {
+        slug: journal.slug.clone(),
+        date,
+    }))
+    .as_path()
+    .into_owned()
+}
+
+async fn visible_entry_ids(
+    backend: &AuthBackend,
+    user: &AuthUser,
+) -> Result<SelectStatement, anyhow::Error> {
+    let q = JournalEntry::find()
+        .select_only()
+        .column(journal_entry::Column::Id);
+    let q = backend.filter_journal_entries_for_user(user, q).await?;
+    Ok(q.into_query())
+}
+
+async fn visible_journal_ids(
+    backend: &AuthBackend,
+    user: &AuthUser,
+) -> Result<SelectStatement, anyhow::Error> {
+    let q = Journal::find().select_only().column(journal::Column::Id);
+    let q = backend.filter_journals_for_user(user, q).await?;
+    Ok(q.into_query())
+}
+
+/// Queries the entries (and their journal) by ID.
+async fn query_entries(
+    ids: impl IntoIterator<Item = i32>,
+    db: &DatabaseConnection,
+) -> Result<HashMap<i32, (journal_entry::Model, journal::Model)>, anyhow::Error> {
+    let q = JournalEntry::find().filter(journal_entry::Column::Id.is_in(ids));
+    let entries = q
+        .find_also_related(Journal)
+        .all(db)
+        .await?
+        .into_iter()
+        .filter_map(|(entry, journal)| journal.map(|journal| (entry.id, (entry, journal))))
+        .collect();
+    Ok(entries)
+}
+
+/// Searches entries, media captions and comments, most relevant first.
+pub async fn search(
+    input: &str,
+    db: &DatabaseConnection,
+    auth: &AuthSession,
+) -> Result<Vec<SearchHit>, anyhow::Error> {
+    let user = auth.user.as_ref().expect("Should be authenticated");
+    search_for_user(input, db, &auth.backend, user).await
+}
+
+pub async fn search_for_user(
+    input: &str,
+    db: &DatabaseConnection,
+    backend: &AuthBackend,
+    user: &AuthUser,
+) -> Result<Vec<SearchHit>, anyhow::Error> {
+    let fts_query = match to_fts_query(input) {
+        Some(q) => q,
+        None => return Ok(Vec::new()),
+    };
+    let mut hits: Vec<SearchHit> = Vec::new();
+
+    // Entries
+    let visibility = Visibility {
+        join: "",
+        key: "journal_entry_fts.rowid",
+        visible: visible_entry_ids(backend, user).await?,
+    };
+    let matches = query_fts("journal_entry_fts", visibility, &fts_query, db).await?;
+    let entries = query_entries(matches.iter().map(|m| m.id), db).await?;
+    for m in matches {
+        if let Some((entry, journal)) = entries.get(&m.id) {
+            hits.push(SearchHit {
+                kind: SearchHitKind::Entry,
+                journal_name: journal.name.clone(),
+                date: Some(chrono::NaiveDateTime::new(entry.date, entry.time)),
+                title: Some(entry.title.clone()),
+                snippet: highlight(&m.snippet),
+                href: day_href(journal, entry.date),
+                rank: m.rank,
+            });
+        }
+    }
+
+    // Captions
+    let visibility = Visibility {
+        join: "JOIN journal_entry_media ON journal_entry_media.id = journal_entry_media_fts.rowid",
+        key: "journal_entry_media.journal_entry_id",
+        visible: visible_entry_ids(backend, user).await?,
+    };
+    let matches = query_fts("journal_entry_media_fts", visibility, &fts_query, db).await?;
+    let medias = JournalEntryMedia::find()
+        .filter(journal_entry_media::Column::Id.is_in(matches.iter().map(|m| m.id)))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|media| (media.id, media))
+        .collect::<HashMap<_, _>>();
+    let entries = query_entries(medias.values().map(|m| m.journal_entry_id), db).await?;
+    for m in matches {
+        let entry = medias
+            .get(&m.id)
+            .and_then(|media| entries.get(&media.journal_entry_id));
+        if let Some((entry, journal)) = entry {
+            hits.push(SearchHit {
+                kind: SearchHitKind::Caption,
+                journal_name: journal.name.clone(),
+                date: Some(chrono::NaiveDateTime::new(entry.date, entry.time)),
+                title: Some(entry.title.clone()),
+                snippet: highlight(&m.snippet),
+                href: day_href(journal, entry.date),
+                rank: m.rank,
+            });
+        }
+    }
+
+    // Comments
+    let visibility = Visibility {
+        join: "JOIN journal_comment ON journal_comment.id = journal_comment_fts.rowid",
+        key: "journal_comment.journal_id",
+        visible: visible_journal_ids(backend, user).await?,
+    };
+    let matches = query_fts("journal_comment_fts", visibility, &fts_query, db).await?;
+    let comments = JournalComment::find()
+        .filter(journal_comment::Column::Id.is_in(matches.iter().map(|m| m.id)))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|comment| (comment.id, comment))
+        .collect::<HashMap<_, _>>();
+    let journals = Journal::find()
+        .filter(journal::Column::Id.is_in(comments.values().map(|c| c.journal_id)))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|journal| (journal.id, journal))
+        .collect::<HashMap<_, _>>();
+    for m in matches {
+        let comment = match comments.get(&m.id) {
+            Some(comment) => comment,
+            None => continue,
+        };
+        if let Some(journal) = journals.get(&comment.journal_id) {
+            let href = match comment.date {
+                Some(date) => day_href(journal, date),
+                None => Route::JournalDetailGet {
+                    slug: Some(&journal.slug),
+                }
+                .as_path()
+                .into_owned(),
+            };
+            hits.push(SearchHit {
+                kind: SearchHitKind::Comment,
+                journal_name: journal.name.clone(),
+                date: comment
+                    .date
+                    .map(|date| chrono::NaiveDateTime::new(date, Default::default())),
+                title: None,
+                snippet: highlight(&m.snippet),
+                href,
+                rank: m.rank,
+            });
+        }
+    }
+
+    // bm25: lower is better.
+    hits.sort_by(|a, b| a.rank.total_cmp(&b.rank));
+    hits.truncate(LIMIT as usize);
+    Ok(hits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        comment::queries::{add_comment_to_journal, AddCommentToJournal},
+        utils::testing::{insert_entry, insert_journal, insert_member, insert_user, test_db},
+    };
+    use entities::journal_member::JournalMemberRole;
+    use sea_orm::{ActiveModelTrait, ActiveValue};
+
+    #[tokio::test]
+    async fn test_search_visibility() {
+        let db = test_db().await;
+        let date = chrono::NaiveDate::from_ymd_opt(2024, 5, 3).unwrap();
+        let alice = insert_user(&db, "alice@example.com", false).await;
+        let bob = insert_user(&db, "bob@example.com", false).await;
+
+        // More relevant than everything Bob can see, and more than the limit.
+        let secret = insert_journal(&db, "secret").await;
+        insert_member(&db, secret.id, alice.id, JournalMemberRole::Owner).await;
+        for _ in 0..LIMIT + 10 {
+            let entry = insert_entry(&db, secret.id, Some(alice.id), date).await;
+            let data = journal_entry::ActiveModel {
+                id: ActiveValue::Unchanged(entry.id),
+                text: ActiveValue::Set("Lisbon Lisbon Lisbon".to_string()),
+                ..Default::default()
+            };
+            data.update(&db).await.unwrap();
+        }
+        let comment = AddCommentToJournal {
+            journal_id: secret.id,
+            user_id: alice.id,
+            text: "Lisbon Lisbon".to_string(),
+            date: None,
+        };
+        add_comment_to_journal(comment, &db).await.unwrap();
+
+        let shared = insert_journal(&db, "shared").await;
+        insert_member(&db, shared.id, alice.id, JournalMemberRole::Owner).await;
+        insert_member(&db, shared.id, bob.id, JournalMemberRole::Contributor).await;
+        let mut visible = Vec::new();
+        for (author, draft) in [(alice.id, false), (alice.id, true), (bob.id, true)] {
+            let entry = insert_entry(&db, shared.id, Some(author), date).await;
+            let data = journal_entry::ActiveModel {
+                id: ActiveValue::Unchanged(entry.id),
+                title: ActiveValue::Set(format!("Lisbon {}", entry.id)),
+                draft: ActiveValue::Set(draft),
+                ..Default::default()
+            };
+            data.update(&db).await.unwrap();
+            // Drafts are only visible to their author.
+            if !draft || author == bob.id {
+                visible.push(format!("Lisbon {}", entry.id));
+            }
+        }
+        let comment = AddCommentToJournal {
+            journal_id: shared.id,
+            user_id: alice.id,
+            text: "Lisbon".to_string(),
+            date: None,
+        };
+        add_comment_to_journal(comment, &db).await.unwrap();
+
+        let backend = AuthBackend::new(db.clone());
+        let hits = search_for_user("lisb", &db, &backend, &AuthUser(bob))
+            .await
+            .unwrap();
+        assert!(hits.iter().all(|hit| hit.journal_name == "shared"));
+        let mut titles = hits
+            .iter()
+            .filter_map(|hit| hit.title.clone())
+            .collect_vec();
+        titles.sort();
+        assert_eq!(titles, visible);
+        assert_eq!(
+            hits.iter()
+                .filter(|hit| matches!(hit.kind, SearchHitKind::Comment))
+                .count(),
+            1
+        );
+
+        let hits = search_for_user("lisb", &db, &backend, &AuthUser(alice))
+            .await
+            .unwrap();
+        assert_eq!(hits.len(), LIMIT as usize);
+    }
+
+    #[test]
+    fn test_to_fts_query() {
+        assert_eq!(to_fts_query("  "), None);
+        assert_eq!(to_fts_query("lisb"), Some("\"lisb\"*".to_string()));
+        assert_eq!(
+            to_fts_query("pastel \"de nata"),
+            Some("\"pastel\" \"\"\"de\" \"nata\"*".to_string())
+        );
+    }
+
+    #[test]
+    fn test_highlight() {
+        assert_eq!(
+            highlight("<b>\u{2}Lisbon\u{3}</b>"),
+            "&lt;b&gt;<mark>Lisbon</mark>&lt;&#x2f;b&gt;"
+        );
+    }
+}
@@ -0,0 +1,3 @@
+mod search;
+
+pub use search::*;
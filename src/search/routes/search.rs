@@ -0,0 +1,35 @@
+use axum::{extract::Query, response::IntoResponse as _};
+use minijinja::{context, Value};
+use serde::{Deserialize, Serialize};
+
+use crate::{search::queries::search, AppState, AuthSession, Route, RouteResult, Templ};
+
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct SearchQuery {
+    #[serde(default)]
+    pub q: String,
+}
+
+pub async fn search_get(
+    state: AppState,
+    templ: Templ,
+    session: AuthSession,
+    Query(query): Query<SearchQuery>,
+) -> RouteResult {
+    let hits = search(&query.q, &state.db, &session).await?;
+    let hits = hits
+        .into_iter()
+        .map(|hit| {
+            let snippet = Value::from_safe_string(hit.snippet.clone());
+            context! { snippet, ..Value::from_serialize(&hit) }
+        })
+        .collect::<Vec<_>>();
+
+    let ctx = context! {
+        q => query.q,
+        hits,
+        href_search => Route::SearchGet(None).as_path(),
+    };
+    let html = templ.render_ctx("search.html", ctx)?;
+    Ok(html.into_response())
+}
@@ -1,13 +1,16 @@
 use std::{env::temp_dir, sync::Arc};
 
 use anyhow::Context;
-use app_config::{AppConfig, AppEnv};
+use app_config::{AppConfig, AppEnv, MailBackend};
 use axum::Router;
 use tower_http::{catch_panic::CatchPanicLayer, services::ServeDir};
+use tracing::warn;
 
 use crate::{
     assets::AssetManifest,
     auth::sessions::init_session,
+    mail::{file::FileMailer, smtp::SmtpMailer, traits::Mailer},
+    notification::daemon::NotificationSender,
     state::AppState,
     storage::{init_storage, FileStore},
     template_engine::init_templates,
@@ -16,11 +19,13 @@ use crate::{
             github_action::GithubActionVideoTranscoder, in_process::InProcessVideoTranscoder,
             traits::VideoTranscodingBackend,
         },
-        daemon::VideoTranscoder,
+        daemon::{TaskPolicy, VideoTranscoder},
     },
 };
 
 const ASSETS_URL_BASE: &str = "/_assets";
+/// Bundled CSS and JS, with their manifest.
+pub const ASSETS_DIR: &str = "assets/dist";
 
 pub async fn mkapp(state: AppState, pool: &sqlx::SqlitePool) -> Result<Router, anyhow::Error> {
     // FIXME customize 404
@@ -31,20 +36,25 @@ pub async fn mkapp(state: AppState, pool: &sqlx::SqlitePool) -> Result<Router, a
     let router = crate::router::init_router(state.clone())
         .with_state(state)
         .layer(auth_layer)
-        .nest_service(ASSETS_URL_BASE, ServeDir::new("assets/dist"))
+        .nest_service(ASSETS_URL_BASE, ServeDir::new(ASSETS_DIR))
         // TODO: Propagate error message in AppEnv::Dev
         .layer(CatchPanicLayer::new());
     Ok(router)
 }
 
+/// Manifest of the files in [`ASSETS_DIR`].
+pub fn manifest_path() -> String {
+    format!("{ASSETS_DIR}/manifest.json")
+}
+
 pub async fn init_state(
     conf: &AppConfig,
     start: bool,
 ) -> Result<(AppState, sqlx::SqlitePool), anyhow::Error> {
-    let assets = AssetManifest::load(ASSETS_URL_BASE.to_string(), "assets/dist/manifest.json")
+    let assets = AssetManifest::load(ASSETS_URL_BASE.to_string(), &manifest_path())
         .await
         .context("Failed to load asset manifest")?;
-    let template_engine = init_templates(assets);
+    let template_engine = Arc::new(init_templates(assets));
 
     let (pool, db) = init_db(conf).await?;
 
@@ -55,12 +65,27 @@ pub async fn init_state(
         video_transcoder.start().await;
     }
 
+    let mailer = init_mailer(conf).await?;
+
+    let mut notifier = NotificationSender::new(
+        db.clone(),
+        mailer.clone(),
+        template_engine.clone(),
+        conf.server_name.clone(),
+    );
+    if start {
+        notifier.start();
+    }
+
     let state = AppState {
+        server_name: conf.server_name.clone(),
         github_client_token: conf.video_transcoding.github_client_token.clone(),
-        template_engine: Arc::new(template_engine),
+        template_engine,
         db,
         storage,
         video_transcoder: Arc::new(video_transcoder),
+        mailer,
+        notifier: Arc::new(notifier),
         dev: conf.env == AppEnv::Dev,
     };
     Ok((state, pool))
@@ -126,6 +151,42 @@ async fn init_video_transcoder(
         }
     };
 
-    let video_transcoder = VideoTranscoder::new(db.clone(), backend);
+    let policy = TaskPolicy::from_conf(&conf.video_transcoding);
+    let video_transcoder = VideoTranscoder::new(db.clone(), backend, policy);
     Ok(video_transcoder)
 }
+
+async fn init_mailer(conf: &AppConfig) -> anyhow::Result<Option<Arc<dyn Mailer>>> {
+    let Some(mc) = &conf.mail else {
+        warn!("No mail configuration, password resets and notifications are disabled");
+        return Ok(None);
+    };
+    let from = mc
+        .from
+        .parse()
+        .with_context(|| format!("Invalid sender address: {}", mc.from))?;
+
+    let mailer: Arc<dyn Mailer> = match mc.backend {
+        MailBackend::Smtp => {
+            let host = mc.smtp_host.clone().context("SMTP host is required")?;
+            let credentials = match (&mc.smtp_username, &mc.smtp_password) {
+                (Some(username), Some(password)) => Some((username.clone(), password.clone())),
+                _ => None,
+            };
+            let mailer = SmtpMailer::new(from, &host, mc.smtp_port, credentials)?;
+            Arc::new(mailer)
+        }
+        MailBackend::File => {
+            let dir = mc.file_dir.clone().context("Mail directory is required")?;
+            tokio::fs::create_dir_all(&dir)
+                .await
+                .context("Failed to create mail directory")?;
+            let mailer = FileMailer {
+                from,
+                transport: lettre::AsyncFileTransport::new(dir),
+            };
+            Arc::new(mailer)
+        }
+    };
+    Ok(Some(mailer))
+}
@@ -2,16 +2,22 @@ use axum::extract::{FromRef, FromRequestParts};
 use std::{convert::Infallible, sync::Arc};
 
 use crate::{
-    storage::FileStore, template_engine::TemplateEngine, video_transcoding::daemon::VideoTranscoder,
+    mail::traits::Mailer, notification::daemon::NotificationSender, storage::FileStore,
+    template_engine::TemplateEngine, video_transcoding::daemon::VideoTranscoder,
 };
 
 #[derive(Debug, Clone)]
 pub struct AppState {
+    /// Base URL for the server, for building absolute URLs (e.g. in emails).
+    pub server_name: String,
     pub github_client_token: Option<String>,
     pub template_engine: Arc<TemplateEngine>,
     pub db: sea_orm::DatabaseConnection,
     pub storage: Arc<FileStore>,
     pub video_transcoder: Arc<VideoTranscoder>,
+    /// None when mail isn't configured.
+    pub mailer: Option<Arc<dyn Mailer>>,
+    pub notifier: Arc<NotificationSender>,
     pub dev: bool,
 }
 
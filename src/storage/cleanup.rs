@@ -85,7 +85,7 @@ impl StorageCleanup {
         for file_key in storage_files_to_delete {
             info!("Deleting storage file: {file_key}");
             self.storage
-                .delete_file(bucket.to_name(&self.storage.conf), &file_key)
+                .delete_file(bucket.to_name(self.storage.conf()), &file_key)
                 .await?;
         }
         for db_file_id in db_files_to_delete {
@@ -99,7 +99,7 @@ impl StorageCleanup {
     }
 
     async fn list_db_files(&self, bucket: &Bucket) -> Result<Vec<ListDbFilesItem>, sea_orm::DbErr> {
-        let bucket = bucket.to_name(&self.storage.conf);
+        let bucket = bucket.to_name(self.storage.conf());
         let q = Statement::from_sql_and_values(
             sea_orm::DatabaseBackend::Sqlite,
             r#"
@@ -107,13 +107,15 @@ impl StorageCleanup {
                 f.id,
                 f.key,
                 CASE
-                    WHEN m1.id IS NULL AND m2.id IS NULL THEN TRUE
+                    WHEN m1.id IS NULL AND m2.id IS NULL AND j.id IS NULL AND t.id IS NULL THEN TRUE
                     ELSE FALSE
                 END AS orphaned
             FROM
                 file f
                 LEFT JOIN journal_entry_media m1 ON m1.file_id = f.id
                 LEFT JOIN journal_entry_media m2 ON m2.thumbnail_file_id = f.id
+                LEFT JOIN journal j ON j.cover_id = f.id
+                LEFT JOIN journal_track t ON t.file_id = f.id
             WHERE f.bucket = ?
             "#,
             [bucket.into()],
@@ -0,0 +1,341 @@
+//! Local filesystem backend, for small self-hosted deployments and tests.
+//! Files are served by the app itself (see [`Route::LocalFileGet`]), using HMAC-signed
+//! URLs in lieu of SAS tokens.
+//!
+use anyhow::{anyhow, Context};
+use axum::body::{Body, Bytes};
+use futures::stream::StreamExt as _;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::{
+    collections::HashSet,
+    path::{Component, Path, PathBuf},
+};
+use time::OffsetDateTime;
+use tokio::io::AsyncWriteExt;
+
+use app_config::{AppConfig, StorageConfig};
+
+use crate::Route;
+
+use super::{
+    routes::LocalFileParams,
+    store::{Bucket, FileKey, SignedUrl, UploadUrl, UrlPermission},
+};
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Debug)]
+pub struct LocalFileStore {
+    root: PathBuf,
+    secret: String,
+    server_name: String,
+    pub conf: StorageConfig,
+}
+
+impl LocalFileStore {
+    /// Stores in a new temporary directory.
+    #[cfg(test)]
+    pub(crate) fn temp() -> Self {
+        let root = std::env::temp_dir()
+            .join("cookie-odyssey-test-local-storage")
+            .join(nanoid::nanoid!());
+        Self {
+            root,
+            secret: "secret".to_string(),
+            server_name: "http://localhost:4444".to_string(),
+            conf: StorageConfig {
+                container_media: "media".to_string(),
+                local_dir: None,
+                local_secret: None,
+                backend: Default::default(),
+                azure_storage_account: Default::default(),
+                azure_storage_access_key: Default::default(),
+                azure_storage_endpoint: None,
+                emulator: false,
+                s3_endpoint: None,
+                s3_region: None,
+                s3_access_key_id: None,
+                s3_secret_access_key: None,
+            },
+        }
+    }
+
+    pub fn new(conf: &AppConfig) -> anyhow::Result<Self> {
+        let sc = &conf.storage;
+        let root = sc
+            .local_dir
+            .clone()
+            .context("Local storage directory is required")?;
+        let secret = sc
+            .local_secret
+            .clone()
+            .context("Local storage secret is required")?;
+
+        Ok(Self {
+            root: PathBuf::from(root),
+            secret,
+            server_name: conf.server_name.clone(),
+            conf: sc.clone(),
+        })
+    }
+
+    pub async fn list_containers(&self) -> Result<Vec<String>, anyhow::Error> {
+        let mut containers: Vec<String> = Vec::new();
+        if !self.root.is_dir() {
+            return Ok(containers);
+        }
+
+        let mut entries = tokio::fs::read_dir(&self.root)
+            .await
+            .with_context(|| format!("Failed to read directory: {:?}", self.root))?;
+        while let Some(entry) = entries.next_entry().await? {
+            if entry.file_type().await?.is_dir() {
+                containers.push(entry.file_name().to_string_lossy().to_string());
+            }
+        }
+
+        Ok(containers)
+    }
+
+    pub async fn list_files(&self, bucket: &Bucket) -> Result<HashSet<FileKey>, anyhow::Error> {
+        let bucket_dir = self.root.join(bucket.to_name(&self.conf));
+
+        let mut files: HashSet<FileKey> = HashSet::new();
+        if !bucket_dir.is_dir() {
+            return Ok(files);
+        }
+
+        // Keys may contain slashes, so walk the whole tree.
+        let mut dirs = vec![bucket_dir.clone()];
+        while let Some(dir) = dirs.pop() {
+            let mut entries = tokio::fs::read_dir(&dir)
+                .await
+                .with_context(|| format!("Failed to read directory: {:?}", dir))?;
+            while let Some(entry) = entries.next_entry().await? {
+                let path = entry.path();
+                if entry.file_type().await?.is_dir() {
+                    dirs.push(path);
+                } else {
+                    let key = path
+                        .strip_prefix(&bucket_dir)
+                        .expect("Should be under the bucket directory")
+                        .to_string_lossy()
+                        .to_string();
+                    files.insert(key);
+                }
+            }
+        }
+
+        Ok(files)
+    }
+
+    pub async fn download_to_file(
+        &self,
+        bucket: String,
+        key: String,
+        path: &PathBuf,
+    ) -> Result<(), anyhow::Error> {
+        let src = self.get_path(&bucket, &key)?;
+        tokio::fs::copy(&src, path)
+            .await
+            .with_context(|| format!("Failed to copy {:?} to {:?}", src, path))?;
+        Ok(())
+    }
+
+    pub async fn delete_file(&self, bucket: &str, key: &str) -> Result<(), anyhow::Error> {
+        let path = self.get_path(bucket, key)?;
+        tokio::fs::remove_file(&path)
+            .await
+            .with_context(|| format!("Failed to delete file: {bucket}/{key}"))?;
+        Ok(())
+    }
+
+    pub async fn get_upload_url(
+        &self,
+        bucket: Bucket,
+        key: FileKey,
+    ) -> Result<UploadUrl, anyhow::Error> {
+        let bucket = bucket.to_name(&self.conf).to_owned();
+        let expiry = time::OffsetDateTime::now_utc() + time::Duration::hours(12);
+        let url = self.sign_url2(&bucket, &key, UrlPermission::Write, expiry)?;
+
+        Ok(UploadUrl {
+            bucket,
+            key,
+            url,
+            headers: Default::default(),
+            method: "PUT".to_string(),
+        })
+    }
+
+    pub async fn upload(&self, bucket: String, key: FileKey, body: Bytes) -> anyhow::Result<()> {
+        let path = self.prepare_path(&bucket, &key).await?;
+        tokio::fs::write(&path, body)
+            .await
+            .with_context(|| format!("Failed to write file: {:?}", path))?;
+        Ok(())
+    }
+
+    /// Like [`upload`], but streams the request body to disk, since uploads can be
+    /// larger than what we'd like to buffer in memory (videos!).
+    pub async fn upload_body(&self, bucket: &str, key: &str, body: Body) -> anyhow::Result<()> {
+        let path = self.prepare_path(bucket, key).await?;
+        let mut file = tokio::fs::File::create(&path)
+            .await
+            .with_context(|| format!("Failed to create file: {:?}", path))?;
+
+        let mut stream = body.into_data_stream();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.context("Failed to read request body")?;
+            file.write_all(&chunk).await?;
+        }
+        file.flush().await?;
+        Ok(())
+    }
+
+    pub async fn upload_file(
+        &self,
+        bucket: String,
+        key: FileKey,
+        path: &PathBuf,
+    ) -> anyhow::Result<()> {
+        let dst = self.prepare_path(&bucket, &key).await?;
+        tokio::fs::copy(path, &dst)
+            .await
+            .with_context(|| format!("Failed to copy {:?} to {:?}", path, dst))?;
+        Ok(())
+    }
+
+    pub fn sign_url2(
+        &self,
+        bucket: &str,
+        key: &str,
+        perm: UrlPermission,
+        expiry: OffsetDateTime,
+    ) -> Result<SignedUrl, anyhow::Error> {
+        let expires = expiry.unix_timestamp();
+        let params = LocalFileParams {
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+            expires,
+            signature: self.compute_signature(bucket, key, perm, expires),
+        };
+        let route = match perm {
+            UrlPermission::Read => Route::LocalFileGet(Some(&params)),
+            UrlPermission::Write => Route::LocalFilePut(Some(&params)),
+        };
+        Ok(route.as_url(&self.server_name))
+    }
+
+    /// Checks that the signature was produced by [`sign_url2`] and hasn't expired.
+    pub fn verify_signature(&self, params: &LocalFileParams, perm: UrlPermission) -> bool {
+        if params.expires < OffsetDateTime::now_utc().unix_timestamp() {
+            return false;
+        }
+        let signature = match hex::decode(&params.signature) {
+            Ok(signature) => signature,
+            Err(_) => return false,
+        };
+        self.mac(&params.bucket, &params.key, perm, params.expires)
+            .verify_slice(&signature)
+            .is_ok()
+    }
+
+    /// Returns the path where the file would be stored.
+    /// Fails for keys that would escape the bucket directory.
+    pub fn get_path(&self, bucket: &str, key: &str) -> anyhow::Result<PathBuf> {
+        for part in [bucket, key] {
+            let is_safe = !part.is_empty()
+                && Path::new(part)
+                    .components()
+                    .all(|c| matches!(c, Component::Normal(_)));
+            if !is_safe {
+                return Err(anyhow!("Invalid file path: {bucket}/{key}"));
+            }
+        }
+        Ok(self.root.join(bucket).join(key))
+    }
+
+    async fn prepare_path(&self, bucket: &str, key: &str) -> anyhow::Result<PathBuf> {
+        let path = self.get_path(bucket, key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .with_context(|| format!("Failed to create directory: {:?}", parent))?;
+        }
+        Ok(path)
+    }
+
+    fn compute_signature(
+        &self,
+        bucket: &str,
+        key: &str,
+        perm: UrlPermission,
+        expires: i64,
+    ) -> String {
+        hex::encode(self.mac(bucket, key, perm, expires).finalize().into_bytes())
+    }
+
+    fn mac(&self, bucket: &str, key: &str, perm: UrlPermission, expires: i64) -> HmacSha256 {
+        let perm = match perm {
+            UrlPermission::Read => "read",
+            UrlPermission::Write => "write",
+        };
+        let mut mac = HmacSha256::new_from_slice(self.secret.as_bytes())
+            .expect("HMAC can take a key of any size");
+        mac.update(format!("{perm}\n{bucket}\n{key}\n{expires}").as_bytes());
+        mac
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_roundtrip() {
+        let store = LocalFileStore::temp();
+        let bucket = "media".to_string();
+
+        store
+            .upload(bucket.clone(), "a.jpg".into(), Bytes::from("hello"))
+            .await
+            .unwrap();
+        let files = store.list_files(&Bucket::Media).await.unwrap();
+        assert_eq!(files, HashSet::from(["a.jpg".to_string()]));
+
+        let dst = store.root.join("downloaded");
+        store
+            .download_to_file(bucket.clone(), "a.jpg".into(), &dst)
+            .await
+            .unwrap();
+        assert_eq!(tokio::fs::read(&dst).await.unwrap(), b"hello");
+
+        store.delete_file(&bucket, "a.jpg").await.unwrap();
+        assert!(store.list_files(&Bucket::Media).await.unwrap().is_empty());
+
+        assert!(store.get_path(&bucket, "../db.sqlite").is_err());
+    }
+
+    #[test]
+    fn test_signature() {
+        let store = LocalFileStore::temp();
+        let expires = OffsetDateTime::now_utc().unix_timestamp() + 60;
+        let mut params = LocalFileParams {
+            bucket: "media".to_string(),
+            key: "a.jpg".to_string(),
+            expires,
+            signature: store.compute_signature("media", "a.jpg", UrlPermission::Read, expires),
+        };
+        assert!(store.verify_signature(&params, UrlPermission::Read));
+        assert!(!store.verify_signature(&params, UrlPermission::Write));
+
+        params.key = "b.jpg".to_string();
+        assert!(!store.verify_signature(&params, UrlPermission::Read));
+
+        params.key = "a.jpg".to_string();
+        params.expires = expires - 120;
+        assert!(!store.verify_signature(&params, UrlPermission::Read));
+    }
+}
@@ -1,6 +1,9 @@
+mod azure;
 mod cleanup;
+mod local;
 pub mod routes;
+mod s3;
 mod store;
 
 pub use cleanup::StorageCleanup;
-pub use store::{init_storage, Bucket, FileKey, FileStore};
+pub use store::{init_storage, Bucket, FileKey, FileStore, UrlPermission};
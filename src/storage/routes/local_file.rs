@@ -0,0 +1,61 @@
+use axum::{
+    body::Body,
+    extract::{Query, Request},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use tower::ServiceExt as _;
+use tower_http::services::ServeFile;
+
+use crate::{
+    storage::{FileStore, UrlPermission},
+    AppState, RouteError,
+};
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct LocalFileParams {
+    pub bucket: String,
+    pub key: String,
+    pub expires: i64,
+    pub signature: String,
+}
+
+pub async fn local_file_get(
+    state: AppState,
+    Query(params): Query<LocalFileParams>,
+    request: Request,
+) -> Result<Response, RouteError> {
+    let store = match state.storage.as_ref() {
+        FileStore::Local(store) => store,
+        _ => return Ok(StatusCode::NOT_FOUND.into_response()),
+    };
+    if !store.verify_signature(&params, UrlPermission::Read) {
+        return Ok(StatusCode::FORBIDDEN.into_response());
+    }
+
+    let path = store.get_path(&params.bucket, &params.key)?;
+    // ServeFile takes care of content types and range requests (for videos).
+    let resp = ServeFile::new(path)
+        .oneshot(request)
+        .await
+        .map_err(|err| RouteError::Other(err.to_string()))?;
+    Ok(resp.into_response())
+}
+
+pub async fn local_file_put(
+    state: AppState,
+    Query(params): Query<LocalFileParams>,
+    body: Body,
+) -> Result<Response, RouteError> {
+    let store = match state.storage.as_ref() {
+        FileStore::Local(store) => store,
+        _ => return Ok(StatusCode::NOT_FOUND.into_response()),
+    };
+    if !store.verify_signature(&params, UrlPermission::Write) {
+        return Ok(StatusCode::FORBIDDEN.into_response());
+    }
+
+    store.upload_body(&params.bucket, &params.key, body).await?;
+    Ok(StatusCode::CREATED.into_response())
+}
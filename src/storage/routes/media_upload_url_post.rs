@@ -6,7 +6,7 @@ use axum::{
     Json,
 };
 
-use crate::{storage::Bucket, AppState, RouteError};
+use crate::{storage::Bucket, AppState, AuthSession, RouteError};
 use entities::{prelude::*, *};
 use nanoid::nanoid;
 use sea_orm::EntityTrait;
@@ -33,9 +33,11 @@ pub struct MediaUploadUrlResultItem {
 
 pub async fn media_upload_url_post(
     state: State<AppState>,
+    session: AuthSession,
     Json(body): Json<MediaUploadUrlBody>,
 ) -> Result<Response, RouteError> {
     let ext_thumbnail = body.thumbnail_extension;
+    let user_id = session.user.as_ref().expect("Should be authenticated").0.id;
 
     // Note the most efficient algorithm (inserts could be batched), but good enough.
     let mut result: Vec<MediaUploadUrlResultItem> = Vec::with_capacity(body.filenames.len());
@@ -62,6 +64,7 @@ pub async fn media_upload_url_post(
         let file_data_original = file::ActiveModel {
             bucket: sea_orm::ActiveValue::Set(upload_params_original.bucket),
             key: sea_orm::ActiveValue::Set(upload_params_original.key),
+            uploaded_by: sea_orm::ActiveValue::Set(Some(user_id)),
             ..Default::default()
         };
         let file_db_original = File::insert(file_data_original).exec(&state.db).await?;
@@ -70,6 +73,7 @@ pub async fn media_upload_url_post(
         let file_data_thumbnail = file::ActiveModel {
             bucket: sea_orm::ActiveValue::Set(upload_params_thumbnail.bucket),
             key: sea_orm::ActiveValue::Set(upload_params_thumbnail.key),
+            uploaded_by: sea_orm::ActiveValue::Set(Some(user_id)),
             ..Default::default()
         };
         let file_db_thumbnail = File::insert(file_data_thumbnail).exec(&state.db).await?;
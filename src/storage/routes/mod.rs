@@ -1,5 +1,7 @@
+mod local_file;
 mod media_upload_proxy;
 mod media_upload_url_post;
 
+pub use local_file::*;
 pub use media_upload_proxy::*;
 pub use media_upload_url_post::*;
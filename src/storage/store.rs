@@ -1,54 +1,29 @@
 //! Abstraction for object storage.
 //! Mostly uses the S3 terminology because that's what I started with, even though
-//! this now uses Azure Blob Storage.
+//! the default backend is now Azure Blob Storage.
 //!
-use anyhow::Context;
 use axum::body::Bytes;
-use azure_storage::{
-    shared_access_signature::service_sas::BlobSasPermissions, CloudLocation, StorageCredentials,
-};
-use azure_storage_blobs::prelude::{BlobServiceClient, ClientBuilder, ContainerClient};
-use futures::stream::StreamExt as _;
 use std::{
     collections::{HashMap, HashSet},
     path::PathBuf,
     sync::Arc,
 };
 use time::OffsetDateTime;
-use tokio::io::AsyncWriteExt;
-
-use app_config::{AppConfig, StorageConfig};
 
-use crate::Route;
+use app_config::{AppConfig, StorageBackend, StorageConfig};
 
-use super::routes::MediaUploadProxyParams;
+use super::{azure::AzureFileStore, local::LocalFileStore, s3::S3FileStore};
 
 pub async fn init_storage(conf: &AppConfig) -> Result<Arc<FileStore>, anyhow::Error> {
     let sc = &conf.storage;
 
-    let client: BlobServiceClient = match sc.emulator {
-        true => ClientBuilder::emulator().blob_service_client(),
-        false => {
-            let creds = StorageCredentials::access_key(
-                sc.azure_storage_account.clone(),
-                sc.azure_storage_access_key.clone(),
-            );
-            let account = sc.azure_storage_account.clone();
-            let loc = match &sc.azure_storage_endpoint {
-                None => CloudLocation::Public { account },
-                Some(endpoint) => CloudLocation::Custom {
-                    account,
-                    uri: endpoint.clone(),
-                },
-            };
-            ClientBuilder::with_location(loc, creds).blob_service_client()
-        }
+    let store = match sc.backend {
+        StorageBackend::Azure => FileStore::Azure(AzureFileStore::new(sc)),
+        StorageBackend::Local => FileStore::Local(LocalFileStore::new(conf)?),
+        StorageBackend::S3 => FileStore::S3(S3FileStore::new(sc)?),
     };
 
-    Ok(Arc::new(FileStore {
-        client,
-        conf: sc.clone(),
-    }))
+    Ok(Arc::new(store))
 }
 
 pub enum Bucket {
@@ -75,40 +50,49 @@ pub struct UploadUrl {
     pub headers: HashMap<String, String>,
 }
 
+/// What a signed URL lets its bearer do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UrlPermission {
+    Read,
+    Write,
+}
+
 #[derive(Debug)]
-pub struct FileStore {
-    client: BlobServiceClient,
-    pub conf: StorageConfig,
+pub enum FileStore {
+    Azure(AzureFileStore),
+    Local(LocalFileStore),
+    S3(S3FileStore),
 }
 
 impl FileStore {
-    pub async fn list_containers(&self) -> Result<Vec<String>, anyhow::Error> {
-        let mut r = self.client.list_containers().into_stream();
-        let mut containers: Vec<String> = Vec::new();
-        while let Some(page) = r.next().await {
-            let page = page.context("Failed to query containers")?;
-            for container in page.containers {
-                containers.push(container.name);
-            }
+    /// A local store in a new temporary directory.
+    #[cfg(test)]
+    pub(crate) fn temp() -> Self {
+        FileStore::Local(LocalFileStore::temp())
+    }
+
+    pub fn conf(&self) -> &StorageConfig {
+        match self {
+            FileStore::Azure(store) => &store.conf,
+            FileStore::Local(store) => &store.conf,
+            FileStore::S3(store) => &store.conf,
         }
+    }
 
-        Ok(containers)
+    pub async fn list_containers(&self) -> Result<Vec<String>, anyhow::Error> {
+        match self {
+            FileStore::Azure(store) => store.list_containers().await,
+            FileStore::Local(store) => store.list_containers().await,
+            FileStore::S3(store) => store.list_containers().await,
+        }
     }
 
     pub async fn list_files(&self, bucket: &Bucket) -> Result<HashSet<FileKey>, anyhow::Error> {
-        let bucket = bucket.to_name(&self.conf).to_owned();
-        let container_client = self.client.container_client(bucket);
-        let mut file_stream = container_client.list_blobs().into_stream();
-
-        let mut files: HashSet<FileKey> = HashSet::new();
-        while let Some(page) = file_stream.next().await {
-            let page = page.context("Failed to list files")?;
-            for blob in page.blobs.blobs() {
-                files.insert(blob.name.clone());
-            }
+        match self {
+            FileStore::Azure(store) => store.list_files(bucket).await,
+            FileStore::Local(store) => store.list_files(bucket).await,
+            FileStore::S3(store) => store.list_files(bucket).await,
         }
-
-        Ok(files)
     }
 
     pub async fn download_to_file(
@@ -117,35 +101,19 @@ impl FileStore {
         key: String,
         path: &PathBuf,
     ) -> Result<(), anyhow::Error> {
-        let container_client = self.client.container_client(bucket);
-        let blob_client = container_client.blob_client(key);
-
-        let mut file = tokio::fs::File::create(path)
-            .await
-            .with_context(|| format!("Failed to create file: {:?}", path))?;
-
-        let mut stream = blob_client.get().into_stream();
-
-        // Adapted from https://github.com/Azure/azure-sdk-for-rust/blob/25ebe5a599a88f311e28a0b63102ce318998f8b6/sdk/storage_blobs/examples/stream_blob_00.rs
-        while let Some(value) = stream.next().await {
-            let mut body = value?.data;
-            while let Some(value) = body.next().await {
-                let chunk = value?;
-                file.write_all(&chunk).await?;
-            }
+        match self {
+            FileStore::Azure(store) => store.download_to_file(bucket, key, path).await,
+            FileStore::Local(store) => store.download_to_file(bucket, key, path).await,
+            FileStore::S3(store) => store.download_to_file(bucket, key, path).await,
         }
-        Ok(())
     }
 
     pub async fn delete_file(&self, bucket: &str, key: &str) -> Result<(), anyhow::Error> {
-        let container_client = self.client.container_client(bucket);
-        let blob_client = container_client.blob_client(key);
-        blob_client
-            .delete()
-            .await
-            .with_context(|| format!("Failed to delete file: {bucket}/{key}"))?;
-
-        Ok(())
+        match self {
+            FileStore::Azure(store) => store.delete_file(bucket, key).await,
+            FileStore::Local(store) => store.delete_file(bucket, key).await,
+            FileStore::S3(store) => store.delete_file(bucket, key).await,
+        }
     }
 
     pub async fn get_upload_url(
@@ -153,64 +121,19 @@ impl FileStore {
         bucket: Bucket,
         key: FileKey,
     ) -> Result<UploadUrl, anyhow::Error> {
-        let bucket = bucket.to_name(&self.conf).to_owned();
-        if self.conf.emulator {
-            let url = Route::MediaUploadProxyPut(Some(&MediaUploadProxyParams {
-                bucket: bucket.clone(),
-                key: key.clone(),
-            }))
-            .as_path();
-            return Ok(UploadUrl {
-                method: "PUT".into(),
-                url: url.into(),
-                headers: Default::default(),
-                bucket,
-                key,
-            });
+        match self {
+            FileStore::Azure(store) => store.get_upload_url(bucket, key).await,
+            FileStore::Local(store) => store.get_upload_url(bucket, key).await,
+            FileStore::S3(store) => store.get_upload_url(bucket, key).await,
         }
-
-        let client = &self.client;
-        let blob_client = client
-            .container_client(bucket.clone())
-            .blob_client(key.clone());
-
-        let expiry = time::OffsetDateTime::now_utc() + time::Duration::hours(12);
-        let perms = BlobSasPermissions {
-            create: true,
-            ..Default::default()
-        };
-
-        let sas = blob_client
-            .shared_access_signature(perms, expiry)
-            .await
-            .context("Failed to generate SAS")?;
-        let url = blob_client
-            .generate_signed_blob_url(&sas)
-            .context("Failed to generate signed URL")?;
-
-        let headers = HashMap::from([("x-ms-blob-type".to_string(), "BlockBlob".to_string())]);
-        Ok(UploadUrl {
-            bucket,
-            key,
-            headers,
-            url: url.to_string(),
-            method: "PUT".to_string(),
-        })
     }
 
     pub async fn upload(&self, bucket: String, key: FileKey, body: Bytes) -> anyhow::Result<()> {
-        let client = &self.client;
-        let container_client = client.container_client(bucket);
-        self.ensure_container_exists(&container_client)
-            .await
-            .context("Failed to ensure container exists")?;
-
-        let blob_client = container_client.blob_client(key);
-        blob_client
-            .put_block_blob(body)
-            .await
-            .context("Failed to upload blob")?;
-        Ok(())
+        match self {
+            FileStore::Azure(store) => store.upload(bucket, key, body).await,
+            FileStore::Local(store) => store.upload(bucket, key, body).await,
+            FileStore::S3(store) => store.upload(bucket, key, body).await,
+        }
     }
 
     pub async fn upload_file(
@@ -219,27 +142,11 @@ impl FileStore {
         key: FileKey,
         path: &PathBuf,
     ) -> anyhow::Result<()> {
-        let file = tokio::fs::File::open(path)
-            .await
-            .with_context(|| format!("Failed to open file: {:?}", path))?;
-        let client = &self.client;
-        let container_client = client.container_client(bucket);
-        self.ensure_container_exists(&container_client)
-            .await
-            .context("Failed to ensure container exists")?;
-
-        let blob_client = container_client.blob_client(key);
-
-        // Adapted from https://github.com/Azure/azure-sdk-for-rust/blob/25ebe5a599a88f311e28a0b63102ce318998f8b6/sdk/storage_blobs/examples/stream_blob_02.rs
-        let stream = azure_core::tokio::fs::FileStreamBuilder::new(file)
-            .build()
-            .await
-            .context("Failed to build file stream")?;
-        blob_client
-            .put_block_blob(stream)
-            .await
-            .context("Failed to upload blob")?;
-        Ok(())
+        match self {
+            FileStore::Azure(store) => store.upload_file(bucket, key, path).await,
+            FileStore::Local(store) => store.upload_file(bucket, key, path).await,
+            FileStore::S3(store) => store.upload_file(bucket, key, path).await,
+        }
     }
 
     /// Signs a URL for reading the blob from a web browser.
@@ -255,12 +162,8 @@ impl FileStore {
             .replace_time(time::Time::from_hms(0, 0, 0).unwrap());
         let expiry = start + time::Duration::days(30);
 
-        let perms = BlobSasPermissions {
-            read: true,
-            ..Default::default()
-        };
-
-        self.sign_url2(bucket, key, perms, expiry).await
+        self.sign_url2(bucket, key, UrlPermission::Read, expiry)
+            .await
     }
 
     /// Like [`sign_url`], but more customizable.
@@ -268,43 +171,15 @@ impl FileStore {
         &self,
         bucket: impl AsRef<str>,
         key: impl AsRef<str>,
-        perms: BlobSasPermissions,
+        perm: UrlPermission,
         expiry: OffsetDateTime,
     ) -> Result<SignedUrl, anyhow::Error> {
-        let client = &self.client;
-        let blob_client = client
-            .container_client(bucket.as_ref())
-            .blob_client(key.as_ref());
-
-        let sas = blob_client
-            .shared_access_signature(perms, expiry)
-            .await
-            .context("Failed to generate SAS")?;
-        let url = blob_client
-            .generate_signed_blob_url(&sas)
-            .context("Failed to generate signed URL")?;
-        Ok(url.to_string())
-    }
-
-    async fn ensure_container_exists(
-        &self,
-        container_client: &ContainerClient,
-    ) -> Result<(), azure_storage::Error> {
-        match container_client.create().await {
-            Err(err) => match err.kind() {
-                azure_storage::ErrorKind::HttpResponse {
-                    error_code: Some(ref code),
-                    ..
-                } => {
-                    if code == "ContainerAlreadyExists" {
-                        Ok(())
-                    } else {
-                        Err(err)
-                    }
-                }
-                _ => Err(err),
-            },
-            Ok(_) => Ok(()),
+        let bucket = bucket.as_ref();
+        let key = key.as_ref();
+        match self {
+            FileStore::Azure(store) => store.sign_url2(bucket, key, perm, expiry).await,
+            FileStore::Local(store) => store.sign_url2(bucket, key, perm, expiry),
+            FileStore::S3(store) => store.sign_url2(bucket, key, perm, expiry).await,
         }
     }
 }
@@ -22,7 +22,7 @@ pub fn init_templates(assets: AssetManifest) -> TemplateEngine {
     let mut env = minijinja::Environment::new();
     env.set_loader(minijinja::path_loader("templates"));
     env.set_undefined_behavior(minijinja::UndefinedBehavior::Strict);
-    env.add_filter("splitlines", splitlines);
+    env.add_filter("markdown", markdown);
     env.add_filter("clsx", clsx);
     env.add_function(
         "asset",
@@ -40,8 +40,18 @@ pub fn init_templates(assets: AssetManifest) -> TemplateEngine {
     env
 }
 
-fn splitlines(value: &str) -> Vec<String> {
-    value.split("\n\n").map(|s| s.into()).collect_vec()
+/// Renders user-written Markdown to HTML.
+/// Raw HTML is sanitized, since users other than admins can write comments.
+pub fn render_markdown(value: &str) -> String {
+    let options = pulldown_cmark::Options::ENABLE_STRIKETHROUGH;
+    let parser = pulldown_cmark::Parser::new_ext(value, options);
+    let mut html = String::new();
+    pulldown_cmark::html::push_html(&mut html, parser);
+    ammonia::clean(&html)
+}
+
+fn markdown(value: &str) -> minijinja::Value {
+    minijinja::Value::from_safe_string(render_markdown(value))
 }
 
 fn clsx(value: &str) -> minijinja::Value {
@@ -77,13 +87,17 @@ impl From<AuthUser> for TemplContextUser {
 pub struct TemplContextLinks {
     home: Cow<'static, str>,
     admin_users_list: Cow<'static, str>,
+    admin_video_transcode_tasks: Cow<'static, str>,
     logout: Cow<'static, str>,
+    search: Cow<'static, str>,
 }
 
 static TEMPL_CONTEXT_LINKS: Lazy<TemplContextLinks> = Lazy::new(|| TemplContextLinks {
     home: "/".into(),
     admin_users_list: Route::UserListGet.as_path(),
+    admin_video_transcode_tasks: Route::VideoTranscodeTaskListGet(None).as_path(),
     logout: Route::LogoutPost.as_path(),
+    search: Route::SearchGet(None).as_path(),
 });
 
 /// Template renderer, which is pre-populated with common context variables (see [TemplContext]).
@@ -180,3 +194,21 @@ where
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markdown() {
+        assert_eq!(
+            markdown("Hello *there*\n\n- [link](https://example.com)").to_string(),
+            "<p>Hello <em>there</em></p>\n<ul>\n<li><a href=\"https://example.com\" rel=\"noopener noreferrer\">link</a></li>\n</ul>\n"
+        );
+        assert_eq!(
+            markdown("Hi <b onclick=\"alert(1)\">there</b><script>alert(1)</script> [x](javascript:alert(1))")
+                .to_string(),
+            "<p>Hi <b>there</b> <a rel=\"noopener noreferrer\">x</a></p>\n"
+        );
+    }
+}
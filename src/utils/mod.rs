@@ -2,4 +2,6 @@ pub mod form_error;
 pub mod not_found;
 pub mod route_error;
 pub mod serde_utils;
+#[cfg(test)]
+pub mod testing;
 pub mod toast;
@@ -8,3 +8,18 @@ where
     let ret = String::deserialize(d)?;
     Ok(ret)
 }
+
+/// For optional form fields, which browsers send as empty strings.
+/// Use with `#[serde(default)]`, so missing fields are also `None`.
+pub fn empty_string_as_none<'de, D, T>(d: D) -> Result<Option<T>, D::Error>
+where
+    D: serde::de::Deserializer<'de>,
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    let s = Option::<String>::deserialize(d)?;
+    match s.as_deref().map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(s) => s.parse().map(Some).map_err(serde::de::Error::custom),
+    }
+}
@@ -0,0 +1,115 @@
+//! Fixtures for tests which need a database.
+use migration::MigratorTrait as _;
+use sea_orm::{ActiveModelTrait, ActiveValue, ConnectOptions, Database, DatabaseConnection};
+
+use entities::{journal_member::JournalMemberRole, *};
+
+/// A migrated in-memory database.
+pub async fn test_db() -> DatabaseConnection {
+    // Each connection would get its own in-memory database.
+    let mut opts = ConnectOptions::new("sqlite::memory:");
+    opts.max_connections(1).sqlx_logging(false);
+    let db = Database::connect(opts).await.unwrap();
+    migration::Migrator::up(&db, None).await.unwrap();
+    db
+}
+
+pub async fn insert_user(db: &DatabaseConnection, email: &str, admin: bool) -> user::Model {
+    let data = user::ActiveModel {
+        email: ActiveValue::Set(email.to_string()),
+        password: ActiveValue::Set(String::new()),
+        first_name: ActiveValue::Set("First".to_string()),
+        last_name: ActiveValue::Set("Last".to_string()),
+        approved: ActiveValue::Set(true),
+        admin: ActiveValue::Set(admin),
+        first_login: ActiveValue::Set(false),
+        id: ActiveValue::NotSet,
+    };
+    data.insert(db).await.unwrap()
+}
+
+pub async fn insert_journal(db: &DatabaseConnection, slug: &str) -> journal::Model {
+    let data = journal::ActiveModel {
+        name: ActiveValue::Set(slug.to_string()),
+        slug: ActiveValue::Set(slug.to_string()),
+        start_date: ActiveValue::Set(chrono::NaiveDate::from_ymd_opt(2024, 5, 1).unwrap()),
+        end_date: ActiveValue::Set(None),
+        cover_id: ActiveValue::Set(None),
+        id: ActiveValue::NotSet,
+    };
+    data.insert(db).await.unwrap()
+}
+
+pub async fn insert_entry(
+    db: &DatabaseConnection,
+    journal_id: i32,
+    author_id: Option<i32>,
+    date: chrono::NaiveDate,
+) -> journal_entry::Model {
+    let data = journal_entry::ActiveModel {
+        journal_id: ActiveValue::Set(journal_id),
+        date: ActiveValue::Set(date),
+        time: ActiveValue::Set(chrono::NaiveTime::from_hms_opt(12, 0, 0).unwrap()),
+        title: ActiveValue::Set("Title".to_string()),
+        text: ActiveValue::Set("Text".to_string()),
+        draft: ActiveValue::Set(false),
+        address: ActiveValue::Set(String::new()),
+        lat: ActiveValue::Set(None),
+        lng: ActiveValue::Set(None),
+        author_id: ActiveValue::Set(author_id),
+        id: ActiveValue::NotSet,
+    };
+    data.insert(db).await.unwrap()
+}
+
+pub async fn insert_file(
+    db: &DatabaseConnection,
+    key: &str,
+    uploaded_by: Option<i32>,
+) -> file::Model {
+    let data = file::ActiveModel {
+        bucket: ActiveValue::Set("media".to_string()),
+        key: ActiveValue::Set(key.to_string()),
+        uploaded_by: ActiveValue::Set(uploaded_by),
+        id: ActiveValue::NotSet,
+    };
+    data.insert(db).await.unwrap()
+}
+
+/// An image, without EXIF.
+pub async fn insert_media(
+    db: &DatabaseConnection,
+    entry_id: i32,
+    file_id: i32,
+    thumbnail_file_id: i32,
+) -> journal_entry_media::Model {
+    let data = journal_entry_media::ActiveModel {
+        journal_entry_id: ActiveValue::Set(entry_id),
+        order: ActiveValue::Set(0),
+        caption: ActiveValue::Set(String::new()),
+        media_type: ActiveValue::Set(journal_entry_media::MediaType::Image),
+        width: ActiveValue::Set(1280),
+        height: ActiveValue::Set(960),
+        file_id: ActiveValue::Set(file_id),
+        thumbnail_width: ActiveValue::Set(640),
+        thumbnail_height: ActiveValue::Set(480),
+        thumbnail_file_id: ActiveValue::Set(thumbnail_file_id),
+        ..Default::default()
+    };
+    data.insert(db).await.unwrap()
+}
+
+pub async fn insert_member(
+    db: &DatabaseConnection,
+    journal_id: i32,
+    user_id: i32,
+    role: JournalMemberRole,
+) {
+    let data = journal_member::ActiveModel {
+        journal_id: ActiveValue::Set(journal_id),
+        user_id: ActiveValue::Set(user_id),
+        role: ActiveValue::Set(role),
+        id: ActiveValue::NotSet,
+    };
+    data.insert(db).await.unwrap();
+}
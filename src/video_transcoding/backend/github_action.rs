@@ -1,14 +1,14 @@
 use std::sync::Arc;
 
 use anyhow::Context;
-use azure_storage::prelude::BlobSasPermissions;
 use sea_orm::EntityTrait;
 use time::OffsetDateTime;
 
 use crate::{
-    storage::FileStore,
+    storage::{FileStore, UrlPermission},
     video_transcoding::{
-        backend::traits::VideoTranscodingBackend, manager::VideoTranscodingManager,
+        backend::traits::{TransientError, VideoTranscodingBackend},
+        manager::VideoTranscodingManager,
         routes::VideoTranscodeCallbackQuery,
     },
     Route,
@@ -52,16 +52,22 @@ impl GithubActionVideoTranscoder {
             }))
             .send()
             .await
-            .context("Failed to send request")?;
+            .context("Failed to send request")
+            .map_err(TransientError)?;
 
         if !response.status().is_success() {
             let url = &self.github_workflow_url;
             let status = response.status();
             let headers = format!("{:?}", response.headers());
             let body = response.text().await.unwrap_or_default();
-            return Err(anyhow::anyhow!(
+            let err = anyhow::anyhow!(
                 "Request '{url}' failed with status {status}\n# headers:\n{headers}# body:\n{body}",
-            ));
+            );
+            // Other client errors, e.g. a bad token, won't fix themselves.
+            if status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+                return Err(TransientError(err).into());
+            }
+            return Err(err);
         }
 
         Ok(())
@@ -78,7 +84,8 @@ impl VideoTranscodingBackend for GithubActionVideoTranscoder {
         let db_file = entities::file::Entity::find_by_id(task.file_id)
             .one(&self.db)
             .await
-            .context("Failed to query file")?
+            .context("Failed to query file")
+            .map_err(TransientError)?
             .with_context(|| format!("File {} not found. FK violation?", task.file_id))?;
 
         let bucket = db_file.bucket;
@@ -91,10 +98,7 @@ impl VideoTranscodingBackend for GithubActionVideoTranscoder {
             .sign_url2(
                 bucket.clone(),
                 input_key.clone(),
-                BlobSasPermissions {
-                    read: true,
-                    ..Default::default()
-                },
+                UrlPermission::Read,
                 expiry,
             )
             .await
@@ -105,11 +109,7 @@ impl VideoTranscodingBackend for GithubActionVideoTranscoder {
             .sign_url2(
                 bucket.clone(),
                 output_key.clone(),
-                BlobSasPermissions {
-                    create: true,
-                    write: true,
-                    ..Default::default()
-                },
+                UrlPermission::Write,
                 expiry,
             )
             .await
@@ -1,13 +1,16 @@
 use std::{path::Path, sync::Arc};
 
 use anyhow::Context;
-use sea_orm::EntityTrait;
+use sea_orm::{ActiveModelTrait, EntityTrait};
+use tracing::{info, warn};
 
 use crate::{
+    journal::thumbnail::{generate_poster, Thumbnail, THUMBNAIL_EXT},
     storage::FileStore,
     video_transcoding::{
-        backend::traits::VideoTranscodingBackend, manager::VideoTranscodingManager,
-        transcode::transcode_video,
+        backend::traits::{TransientError, VideoTranscodingBackend},
+        manager::VideoTranscodingManager,
+        transcode::{probe_media, transcode_video},
     },
 };
 
@@ -28,9 +31,23 @@ impl VideoTranscodingBackend for InProcessVideoTranscoder {
         let db_file = entities::file::Entity::find_by_id(task.file_id)
             .one(&self.db)
             .await
-            .context("Failed to query file")?
+            .context("Failed to query file")
+            .map_err(TransientError)?
             .with_context(|| format!("File {} not found. FK violation?", task.file_id))?;
 
+        // Nothing to show the video, or its poster, anymore.
+        let used = VideoTranscodingManager::media_uses_file(&self.db, db_file.id)
+            .await
+            .context("Failed to query media")
+            .map_err(TransientError)?;
+        if !used {
+            info!(
+                "Skipping task {}, no media uses file {}",
+                task.id, db_file.id
+            );
+            return Ok(());
+        }
+
         let work_dir = Path::new(&self.work_dir);
         let input_path = work_dir.join(db_file.id.to_string());
         let output_path = work_dir.join(format!("{}.transcoded.mp4", db_file.id));
@@ -38,8 +55,11 @@ impl VideoTranscodingBackend for InProcessVideoTranscoder {
         self.storage
             .download_to_file(db_file.bucket.clone(), db_file.key.clone(), &input_path)
             .await
-            .context("Failed to download blob")?;
+            .context("Failed to download blob")
+            .map_err(TransientError)?;
 
+        // Of the original: the transcoded video is always H.264, with the rotation applied.
+        let probe = probe_media(&input_path).await?;
         transcode_video(&input_path, &output_path).await?;
 
         let output_key = VideoTranscodingManager::get_output_storage_key(&db_file.key);
@@ -52,12 +72,56 @@ impl VideoTranscodingBackend for InProcessVideoTranscoder {
                     "Failed to upload transcoded file at {:?} for task {}",
                     output_path, task.id
                 )
-            })?;
+            })
+            .map_err(TransientError)?;
+
+        // Not worth failing the task for, the thumbnail from the upload is still there.
+        let poster = match self.upload_poster(&db_file, &output_path).await {
+            Ok(poster) => Some(poster),
+            Err(err) => {
+                warn!("No poster for task {}: {err:#?}", task.id);
+                None
+            }
+        };
+        let poster = poster.as_ref().map(|(file_id, poster)| (*file_id, poster));
+        VideoTranscodingManager::update_media_video(&self.db, db_file.id, &probe, poster)
+            .await
+            .context("Failed to update media")
+            .map_err(TransientError)?;
 
         VideoTranscodingManager::update_file_key(&self.db, &self.storage, db_file.id, output_key)
             .await
-            .context("Failed to update file key")?;
+            .context("Failed to update file key")
+            .map_err(TransientError)?;
 
         Ok(())
     }
 }
+
+impl InProcessVideoTranscoder {
+    /// Returns the ID of the uploaded poster file, and its dimensions.
+    async fn upload_poster(
+        &self,
+        db_file: &entities::file::Model,
+        video_path: &Path,
+    ) -> anyhow::Result<(i32, Thumbnail)> {
+        let output_probe = probe_media(video_path).await?;
+        let poster_path =
+            Path::new(&self.work_dir).join(format!("{}.poster{THUMBNAIL_EXT}", db_file.id));
+        let poster = generate_poster(video_path, &poster_path, &output_probe).await?;
+
+        let key = format!("{}_thumbnail{THUMBNAIL_EXT}", nanoid::nanoid!());
+        self.storage
+            .upload_file(db_file.bucket.clone(), key.clone(), &poster_path)
+            .await
+            .with_context(|| format!("Failed to upload poster {key}"))?;
+        let data = entities::file::ActiveModel {
+            bucket: sea_orm::ActiveValue::Set(db_file.bucket.clone()),
+            key: sea_orm::ActiveValue::Set(key),
+            uploaded_by: sea_orm::ActiveValue::Set(None),
+            id: sea_orm::ActiveValue::NotSet,
+        };
+        let file_id = data.insert(&self.db).await?.id;
+        Ok((file_id, poster))
+    }
+}
@@ -5,5 +5,38 @@ pub trait VideoTranscodingBackend: Send + Sync + 'static + Debug {
     /// Returns whether [`transcode`] is delayed (e.g., queued in an external system).
     fn is_delayed(&self) -> bool;
 
+    /// Errors wrapped in [`TransientError`] are retried, others fail the task.
     async fn transcode(&self, task: &entities::video_transcode_task::Model) -> anyhow::Result<()>;
 }
+
+/// A failure worth retrying, e.g. a network blip while talking to storage.
+#[derive(Debug, thiserror::Error)]
+#[error(transparent)]
+pub struct TransientError(pub anyhow::Error);
+
+impl TransientError {
+    /// Whether `err` or any of its causes is transient.
+    pub fn is_transient(err: &anyhow::Error) -> bool {
+        err.chain().any(|cause| cause.is::<TransientError>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Context;
+
+    use super::*;
+
+    #[test]
+    fn test_is_transient() {
+        let err: anyhow::Result<()> = Err(anyhow::anyhow!("Connection reset"));
+        let err = err
+            .context("Failed to download blob")
+            .map_err(TransientError)
+            .context("Failed to transcode");
+        assert!(TransientError::is_transient(&err.unwrap_err()));
+
+        let err = anyhow::anyhow!("ffmpeg exited with status: 1");
+        assert!(!TransientError::is_transient(&err));
+    }
+}
@@ -2,12 +2,38 @@ use anyhow::Context;
 use entities::video_transcode_task;
 use std::{sync::Arc, time::Duration};
 use tokio::{select, sync::mpsc, task::JoinHandle, time};
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
 use crate::video_transcoding::{
-    backend::traits::VideoTranscodingBackend, manager::VideoTranscodingManager,
+    backend::traits::{TransientError, VideoTranscodingBackend},
+    manager::VideoTranscodingManager,
 };
 
+/// See [`app_config::VideoTranscodingConfig`].
+pub const DEFAULT_MAX_ATTEMPTS: i32 = 5;
+/// GitHub cancels jobs after 6 hours.
+pub const DEFAULT_DISPATCH_TIMEOUT_MINUTES: i64 = 6 * 60;
+
+/// How hard the daemon tries before failing a task.
+#[derive(Debug, Clone, Copy)]
+pub struct TaskPolicy {
+    pub max_attempts: i32,
+    /// After which a dispatched task whose callback never came is dispatched again.
+    pub dispatch_timeout: chrono::Duration,
+}
+
+impl TaskPolicy {
+    pub fn from_conf(conf: &app_config::VideoTranscodingConfig) -> Self {
+        let timeout = conf
+            .dispatch_timeout_minutes
+            .unwrap_or(DEFAULT_DISPATCH_TIMEOUT_MINUTES);
+        Self {
+            max_attempts: conf.max_attempts.unwrap_or(DEFAULT_MAX_ATTEMPTS),
+            dispatch_timeout: chrono::Duration::minutes(timeout),
+        }
+    }
+}
+
 enum Message {
     Process(Vec<video_transcode_task::Model>),
     Shutdown,
@@ -20,19 +46,26 @@ pub struct VideoTranscoder {
     // NOTE: We only require an Arc here to allow starting the daemon after construction,
     // and we only need *that* because we use init_state in many commands.
     backend: Arc<dyn VideoTranscodingBackend>,
+    policy: TaskPolicy,
 }
 
 impl VideoTranscoder {
-    pub fn new(db: sea_orm::DatabaseConnection, backend: Arc<dyn VideoTranscodingBackend>) -> Self {
+    pub fn new(
+        db: sea_orm::DatabaseConnection,
+        backend: Arc<dyn VideoTranscodingBackend>,
+        policy: TaskPolicy,
+    ) -> Self {
         Self {
             daemon: None,
             db,
             backend,
+            policy,
         }
     }
 
     pub async fn start(&mut self) {
-        let daemon = VideoTranscodeDaemon::start(self.backend.clone(), self.db.clone()).await;
+        let daemon =
+            VideoTranscodeDaemon::start(self.backend.clone(), self.db.clone(), self.policy).await;
         self.daemon = Some(daemon);
     }
 
@@ -63,11 +96,12 @@ impl VideoTranscodeDaemon {
     pub async fn start(
         backend: Arc<dyn VideoTranscodingBackend>,
         db: sea_orm::DatabaseConnection,
+        policy: TaskPolicy,
     ) -> Self {
         let (tx, rx) = mpsc::channel(32);
 
         let handle = tokio::spawn(async move {
-            run(backend, db, rx).await;
+            run(backend, db, policy, rx).await;
         });
 
         Self {
@@ -101,6 +135,7 @@ impl VideoTranscodeDaemon {
 async fn run(
     backend: Arc<dyn VideoTranscodingBackend>,
     db: sea_orm::DatabaseConnection,
+    policy: TaskPolicy,
     mut rx: mpsc::Receiver<Message>,
 ) -> () {
     let poll_interval = Duration::from_secs(30);
@@ -108,24 +143,38 @@ async fn run(
     ticker.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
     let backend = backend.as_ref();
 
-    if let Err(e) = process_pending(backend, &db).await {
+    if let Err(e) = process_pending(backend, &db, policy).await {
         error!("Transcode (startup) error: {e:#?}");
     };
 
     loop {
         select! {
-            _ = ticker.tick(), if !backend.is_delayed() => {
-                // XXX
-                if let Err(e) = process_pending(backend, &db).await {
+            _ = ticker.tick() => {
+                // Dispatched tasks aren't pending, so delayed backends can poll too.
+                if let Err(e) = process_pending(backend, &db, policy).await {
                     error!("Transcode (interval) error: {e:#?}");
                 }
+                if backend.is_delayed() {
+                    if let Err(e) = process_stale(backend, &db, policy).await {
+                        error!("Transcode (watchdog) error: {e:#?}");
+                    }
+                }
             }
             message = rx.recv() => {
                 match message {
                     Some(message) => {
                         match message {
                             Message::Process(tasks) => {
-                                process_tasks(backend, &db, tasks).await;
+                                // The ticker may have processed them in the meantime.
+                                let tasks = VideoTranscodingManager::refresh_pending(&db, tasks).await;
+                                let tasks = match tasks {
+                                    Ok(tasks) => tasks,
+                                    Err(e) => {
+                                        error!("Transcode (message) error: {e:#?}");
+                                        continue;
+                                    }
+                                };
+                                process_tasks(backend, &db, policy, tasks).await;
                             }
                             Message::Shutdown => {
                                 break;
@@ -145,44 +194,113 @@ async fn run(
 async fn process_pending(
     backend: &dyn VideoTranscodingBackend,
     db: &sea_orm::DatabaseConnection,
+    policy: TaskPolicy,
 ) -> anyhow::Result<()> {
     let pending = VideoTranscodingManager::list_pending(db).await?;
     debug!("Found {} pending video transcoding tasks.", pending.len());
 
-    process_tasks(backend, db, pending).await;
+    process_tasks(backend, db, policy, pending).await;
+    Ok(())
+}
+
+/// Dispatches again tasks whose callback never came, e.g. because the server was down.
+async fn process_stale(
+    backend: &dyn VideoTranscodingBackend,
+    db: &sea_orm::DatabaseConnection,
+    policy: TaskPolicy,
+) -> anyhow::Result<()> {
+    let dispatched_before = chrono::Utc::now() - policy.dispatch_timeout;
+    let stale = VideoTranscodingManager::list_stale(db, dispatched_before).await?;
+    let mut retries = Vec::with_capacity(stale.len());
+    for task in stale {
+        let task_id = task.id;
+        if task.attempts < policy.max_attempts {
+            warn!("No callback for task {task_id}, dispatching it again");
+            retries.push(task);
+        } else {
+            error!(
+                "No callback for task {task_id} after {} attempts",
+                task.attempts
+            );
+            let detail = format!("No callback after {} attempts", task.attempts);
+            mark_task_error(db, task_id, task.attempts, detail).await;
+        }
+    }
+
+    process_tasks(backend, db, policy, retries).await;
     Ok(())
 }
 
 async fn process_tasks(
     backend: &dyn VideoTranscodingBackend,
     db: &sea_orm::DatabaseConnection,
+    policy: TaskPolicy,
     tasks: Vec<video_transcode_task::Model>,
 ) {
     for task in tasks {
         let task_id = task.id;
-        info!("Processing task {task_id}");
+        let attempts = task.attempts + 1;
+        info!("Processing task {task_id} (attempt {attempts})");
         match backend.transcode(&task).await {
+            Err(err) if TransientError::is_transient(&err) && attempts < policy.max_attempts => {
+                match VideoTranscodingManager::mark_task_retry(
+                    db,
+                    task.id,
+                    attempts,
+                    err.to_string(),
+                )
+                .await
+                {
+                    Ok(Some(next_attempt_at)) => {
+                        warn!("Failed task {task_id}, retrying at {next_attempt_at}: {err:#?}");
+                    }
+                    Ok(None) => {
+                        warn!("Failed task {task_id}, which was cancelled or completed: {err:#?}");
+                    }
+                    Err(err) => {
+                        error!("Failed to schedule a retry of task {task_id}: {err:#?}");
+                    }
+                }
+            }
             Err(err) => {
                 error!("Failed task {task_id}: {err:#?}");
-                VideoTranscodingManager::mark_task_error(db, task.id, err.to_string())
-                    .await
-                    .unwrap_or_else(|err| {
-                        error!("Failed to mark task {task_id} as error: {err:#?}");
-                    });
+                mark_task_error(db, task.id, attempts, err.to_string()).await;
             }
             Ok(_) => {
                 if backend.is_delayed() {
-                    info!("Delayed task {task_id}");
-                    // Do nothing, leave it as pending.
+                    info!("Dispatched task {task_id}");
+                    match VideoTranscodingManager::mark_task_dispatched(db, task.id, attempts).await
+                    {
+                        Ok(true) => {}
+                        Ok(false) => {
+                            warn!("Task {task_id} was completed or cancelled while dispatching");
+                        }
+                        Err(err) => {
+                            error!("Failed to mark task {task_id} as dispatched: {err:#?}");
+                        }
+                    }
                 } else {
                     info!("Completed task {task_id}");
-                    VideoTranscodingManager::mark_task_completed(db, task.id)
-                        .await
-                        .unwrap_or_else(|err| {
-                            error!("Failed to mark task {task_id} as completed: {err:#?}");
-                        });
+                    if let Err(err) =
+                        VideoTranscodingManager::mark_task_completed(db, task.id).await
+                    {
+                        error!("Failed to mark task {task_id} as completed: {err:#?}");
+                    }
                 }
             }
         }
     }
 }
+
+async fn mark_task_error(
+    db: &sea_orm::DatabaseConnection,
+    task_id: i32,
+    attempts: i32,
+    detail: String,
+) {
+    match VideoTranscodingManager::mark_task_error(db, task_id, attempts, detail).await {
+        Ok(true) => {}
+        Ok(false) => warn!("Task {task_id} was cancelled or completed, leaving it as is"),
+        Err(err) => error!("Failed to mark task {task_id} as error: {err:#?}"),
+    }
+}
@@ -1,8 +1,19 @@
 use anyhow::{anyhow, Context};
-use entities::{prelude::VideoTranscodeTask, video_transcode_task};
-use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter};
+use entities::{
+    prelude::VideoTranscodeTask,
+    video_transcode_task::{self, TaskStatus},
+};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Condition, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder,
+    QuerySelect,
+};
+use tracing::{info, warn};
 
-use crate::storage::{FileKey, FileStore};
+use crate::{
+    journal::thumbnail::Thumbnail,
+    storage::{FileKey, FileStore},
+    video_transcoding::transcode::MediaProbe,
+};
 
 pub struct VideoTranscodingManager {}
 
@@ -17,6 +28,7 @@ impl VideoTranscodingManager {
             status: sea_orm::ActiveValue::Set(entities::video_transcode_task::TaskStatus::Pending),
             detail: sea_orm::ActiveValue::Set("".to_string()),
             created_at: sea_orm::ActiveValue::Set(chrono::Utc::now()),
+            attempts: sea_orm::ActiveValue::Set(0),
             ..Default::default()
         };
 
@@ -28,10 +40,11 @@ impl VideoTranscodingManager {
         Ok(task)
     }
 
+    /// Returns `false` if the task was already completed.
     pub async fn mark_task_completed(
         db: &sea_orm::DatabaseConnection,
         task_id: i32,
-    ) -> anyhow::Result<()> {
+    ) -> anyhow::Result<bool> {
         let data = video_transcode_task::ActiveModel {
             status: sea_orm::ActiveValue::Set(
                 entities::video_transcode_task::TaskStatus::Completed,
@@ -40,31 +53,109 @@ impl VideoTranscodingManager {
             detail: sea_orm::ActiveValue::Set("".to_string()),
             ..Default::default()
         };
-        let _update_result = VideoTranscodeTask::update_many()
+        let update_result = VideoTranscodeTask::update_many()
             .set(data)
             .filter(entities::video_transcode_task::Column::Id.eq(task_id))
+            .filter(entities::video_transcode_task::Column::Status.ne(TaskStatus::Completed))
             .exec(db)
             .await?;
-        Ok(())
+        Ok(update_result.rows_affected > 0)
     }
 
+    /// Returns `false` if the task was cancelled or completed in the meantime.
     pub async fn mark_task_error(
         db: &sea_orm::DatabaseConnection,
         task_id: i32,
+        attempts: i32,
         detail: String,
-    ) -> anyhow::Result<()> {
+    ) -> anyhow::Result<bool> {
         let data = video_transcode_task::ActiveModel {
             status: sea_orm::ActiveValue::Set(entities::video_transcode_task::TaskStatus::Failed),
             updated_at: sea_orm::ActiveValue::Set(Some(chrono::Utc::now())),
             detail: sea_orm::ActiveValue::Set(detail),
+            attempts: sea_orm::ActiveValue::Set(attempts),
+            next_attempt_at: sea_orm::ActiveValue::Set(None),
             ..Default::default()
         };
-        let _update_result = VideoTranscodeTask::update_many()
+        let update_result = VideoTranscodeTask::update_many()
             .set(data)
             .filter(entities::video_transcode_task::Column::Id.eq(task_id))
+            .filter(
+                entities::video_transcode_task::Column::Status
+                    .is_in([TaskStatus::Pending, TaskStatus::Dispatched]),
+            )
             .exec(db)
             .await?;
-        Ok(())
+        Ok(update_result.rows_affected > 0)
+    }
+
+    /// For delayed backends, until their callback calls [`Self::mark_task_completed`].
+    /// Returns `false` if the task was completed or cancelled in the meantime, e.g. by a
+    /// callback faster than us.
+    pub async fn mark_task_dispatched(
+        db: &sea_orm::DatabaseConnection,
+        task_id: i32,
+        attempts: i32,
+    ) -> anyhow::Result<bool> {
+        let now = chrono::Utc::now();
+        let data = video_transcode_task::ActiveModel {
+            status: sea_orm::ActiveValue::Set(
+                entities::video_transcode_task::TaskStatus::Dispatched,
+            ),
+            updated_at: sea_orm::ActiveValue::Set(Some(now)),
+            attempts: sea_orm::ActiveValue::Set(attempts),
+            next_attempt_at: sea_orm::ActiveValue::Set(None),
+            dispatched_at: sea_orm::ActiveValue::Set(Some(now)),
+            ..Default::default()
+        };
+        // Stale tasks are dispatched again while still dispatched.
+        let update_result = VideoTranscodeTask::update_many()
+            .set(data)
+            .filter(entities::video_transcode_task::Column::Id.eq(task_id))
+            .filter(
+                entities::video_transcode_task::Column::Status
+                    .is_in([TaskStatus::Pending, TaskStatus::Dispatched]),
+            )
+            .exec(db)
+            .await?;
+        Ok(update_result.rows_affected > 0)
+    }
+
+    /// Leaves the task pending, until [`Self::retry_delay`] has passed.
+    /// Returns `None` if the task was cancelled or completed in the meantime.
+    pub async fn mark_task_retry(
+        db: &sea_orm::DatabaseConnection,
+        task_id: i32,
+        attempts: i32,
+        detail: String,
+    ) -> anyhow::Result<Option<chrono::DateTime<chrono::Utc>>> {
+        let now = chrono::Utc::now();
+        let next_attempt_at = now + Self::retry_delay(attempts);
+        let data = video_transcode_task::ActiveModel {
+            status: sea_orm::ActiveValue::Set(entities::video_transcode_task::TaskStatus::Pending),
+            updated_at: sea_orm::ActiveValue::Set(Some(now)),
+            detail: sea_orm::ActiveValue::Set(detail),
+            attempts: sea_orm::ActiveValue::Set(attempts),
+            next_attempt_at: sea_orm::ActiveValue::Set(Some(next_attempt_at)),
+            ..Default::default()
+        };
+        let update_result = VideoTranscodeTask::update_many()
+            .set(data)
+            .filter(entities::video_transcode_task::Column::Id.eq(task_id))
+            .filter(
+                entities::video_transcode_task::Column::Status
+                    .is_in([TaskStatus::Pending, TaskStatus::Dispatched]),
+            )
+            .exec(db)
+            .await?;
+        Ok((update_result.rows_affected > 0).then_some(next_attempt_at))
+    }
+
+    /// Exponential backoff: 1 minute after the first attempt, then 2, 4... up to a day.
+    pub fn retry_delay(attempts: i32) -> chrono::Duration {
+        let exponent = (attempts - 1).clamp(0, 20) as u32;
+        let delay = chrono::Duration::minutes(2_i64.pow(exponent));
+        delay.min(chrono::Duration::days(1))
     }
 
     pub async fn get_task_by_id(
@@ -78,6 +169,7 @@ impl VideoTranscodingManager {
         Ok(task)
     }
 
+    /// Pending tasks that are due, i.e. not waiting for a retry.
     pub async fn list_pending(
         db: &sea_orm::DatabaseConnection,
     ) -> anyhow::Result<Vec<video_transcode_task::Model>> {
@@ -85,16 +177,130 @@ impl VideoTranscodingManager {
         // single "process".
         VideoTranscodeTask::find()
             .filter(entities::video_transcode_task::Column::Status.eq("pending"))
+            .filter(
+                Condition::any()
+                    .add(entities::video_transcode_task::Column::NextAttemptAt.is_null())
+                    .add(
+                        entities::video_transcode_task::Column::NextAttemptAt
+                            .lte(chrono::Utc::now()),
+                    ),
+            )
             .all(db)
             .await
             .context("Failed to list pending tasks")
     }
 
+    /// Keeps the tasks that are still pending.
+    pub async fn refresh_pending(
+        db: &sea_orm::DatabaseConnection,
+        tasks: Vec<video_transcode_task::Model>,
+    ) -> anyhow::Result<Vec<video_transcode_task::Model>> {
+        VideoTranscodeTask::find()
+            .filter(entities::video_transcode_task::Column::Id.is_in(tasks.iter().map(|t| t.id)))
+            .filter(
+                entities::video_transcode_task::Column::Status
+                    .eq(entities::video_transcode_task::TaskStatus::Pending),
+            )
+            .all(db)
+            .await
+            .context("Failed to refresh pending tasks")
+    }
+
+    /// Dispatched tasks still waiting for their callback.
+    pub async fn list_stale(
+        db: &sea_orm::DatabaseConnection,
+        dispatched_before: chrono::DateTime<chrono::Utc>,
+    ) -> anyhow::Result<Vec<video_transcode_task::Model>> {
+        VideoTranscodeTask::find()
+            .filter(
+                entities::video_transcode_task::Column::Status
+                    .eq(entities::video_transcode_task::TaskStatus::Dispatched),
+            )
+            .filter(entities::video_transcode_task::Column::DispatchedAt.lte(dispatched_before))
+            .all(db)
+            .await
+            .context("Failed to list stale tasks")
+    }
+
+    /// Most recent first.
+    pub async fn list_tasks(
+        db: &sea_orm::DatabaseConnection,
+        status: Option<TaskStatus>,
+        limit: u64,
+    ) -> anyhow::Result<Vec<video_transcode_task::Model>> {
+        let mut query = VideoTranscodeTask::find();
+        if let Some(status) = status {
+            query = query.filter(entities::video_transcode_task::Column::Status.eq(status));
+        }
+        query
+            .order_by_desc(entities::video_transcode_task::Column::Id)
+            .limit(limit)
+            .all(db)
+            .await
+            .context("Failed to list tasks")
+    }
+
+    /// Starts over, as if the task had just been enqueued.
+    /// Returns `None` if the task hasn't failed.
+    pub async fn reset_task(
+        db: &sea_orm::DatabaseConnection,
+        task_id: i32,
+    ) -> anyhow::Result<Option<video_transcode_task::Model>> {
+        let data = video_transcode_task::ActiveModel {
+            status: sea_orm::ActiveValue::Set(TaskStatus::Pending),
+            updated_at: sea_orm::ActiveValue::Set(Some(chrono::Utc::now())),
+            detail: sea_orm::ActiveValue::Set("".to_string()),
+            attempts: sea_orm::ActiveValue::Set(0),
+            next_attempt_at: sea_orm::ActiveValue::Set(None),
+            dispatched_at: sea_orm::ActiveValue::Set(None),
+            ..Default::default()
+        };
+        let update_result = VideoTranscodeTask::update_many()
+            .set(data)
+            .filter(entities::video_transcode_task::Column::Id.eq(task_id))
+            .filter(entities::video_transcode_task::Column::Status.eq(TaskStatus::Failed))
+            .exec(db)
+            .await
+            .context("Failed to reset task")?;
+        if update_result.rows_affected == 0 {
+            return Ok(None);
+        }
+        Self::get_task_by_id(db, task_id).await
+    }
+
+    /// Fails the task without further attempts. The video stays in its original format.
+    /// Returns `false` if the task wasn't pending or dispatched.
+    pub async fn cancel_task(
+        db: &sea_orm::DatabaseConnection,
+        task_id: i32,
+        detail: String,
+    ) -> anyhow::Result<bool> {
+        let data = video_transcode_task::ActiveModel {
+            status: sea_orm::ActiveValue::Set(TaskStatus::Failed),
+            updated_at: sea_orm::ActiveValue::Set(Some(chrono::Utc::now())),
+            detail: sea_orm::ActiveValue::Set(detail),
+            next_attempt_at: sea_orm::ActiveValue::Set(None),
+            ..Default::default()
+        };
+        let update_result = VideoTranscodeTask::update_many()
+            .set(data)
+            .filter(entities::video_transcode_task::Column::Id.eq(task_id))
+            .filter(
+                entities::video_transcode_task::Column::Status
+                    .is_in([TaskStatus::Pending, TaskStatus::Dispatched]),
+            )
+            .exec(db)
+            .await?;
+        Ok(update_result.rows_affected > 0)
+    }
+
     pub fn get_output_storage_key(input_key: &str) -> FileKey {
         format!("{}.transcoded.mp4", input_key)
     }
 
     /// Updates the `key` on the database `File`, and deletes the old file from storage.
+    /// The delete is best effort: once the key is updated, failing would transcode the
+    /// transcoded file on retry. Leftovers are collected by `cleanup-storage`.
     pub async fn update_file_key(
         db: &sea_orm::DatabaseConnection,
         storage: &FileStore,
@@ -123,11 +329,252 @@ impl VideoTranscodingManager {
             .await
             .context("Failed to update file key")?;
 
-        storage
-            .delete_file(&file.bucket, &file.key)
+        if let Err(err) = storage.delete_file(&file.bucket, &file.key).await {
+            warn!("Failed to delete {} from storage: {err:#?}", file.key);
+        }
+
+        Ok(())
+    }
+
+    /// Whether a media still uses the file, it may have been deleted since the task was queued.
+    pub async fn media_uses_file(
+        db: &sea_orm::DatabaseConnection,
+        file_id: i32,
+    ) -> anyhow::Result<bool> {
+        let count = entities::journal_entry_media::Entity::find()
+            .filter(entities::journal_entry_media::Column::FileId.eq(file_id))
+            .count(db)
+            .await?;
+        Ok(count > 0)
+    }
+
+    /// Saves what ffprobe found on the media using the video, and its poster if any.
+    /// The previous thumbnail is left for `cleanup-storage`, the journal cover may use it.
+    /// Does nothing if the media was deleted meanwhile.
+    pub async fn update_media_video(
+        db: &sea_orm::DatabaseConnection,
+        file_id: i32,
+        probe: &MediaProbe,
+        poster: Option<(i32, &Thumbnail)>,
+    ) -> anyhow::Result<()> {
+        let mut update_data = entities::journal_entry_media::ActiveModel {
+            duration_s: sea_orm::ActiveValue::Set(probe.duration_s),
+            video_codec: sea_orm::ActiveValue::Set(probe.codec.clone()),
+            rotation: sea_orm::ActiveValue::Set(probe.rotation),
+            ..Default::default()
+        };
+        if let Some((poster_file_id, poster)) = poster {
+            update_data.thumbnail_file_id = sea_orm::ActiveValue::Set(poster_file_id);
+            update_data.thumbnail_width = sea_orm::ActiveValue::Set(poster.width);
+            update_data.thumbnail_height = sea_orm::ActiveValue::Set(poster.height);
+        }
+
+        let update_result = entities::journal_entry_media::Entity::update_many()
+            .set(update_data)
+            .filter(entities::journal_entry_media::Column::FileId.eq(file_id))
+            .exec(db)
             .await
-            .context("Failed to delete file from storage")?;
+            .context("Failed to update media")?;
+        if update_result.rows_affected == 0 {
+            info!("No media uses file {file_id} anymore");
+        }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::testing::{insert_entry, insert_file, insert_journal, insert_media, test_db};
+
+    #[tokio::test]
+    async fn test_update_file_key() {
+        let db = test_db().await;
+        let storage = FileStore::temp();
+        // Not in storage, so the delete fails.
+        let file = insert_file(&db, "a.mov", None).await;
+
+        VideoTranscodingManager::update_file_key(&db, &storage, file.id, "a.mov.mp4".into())
+            .await
+            .unwrap();
+        let file = entities::file::Entity::find_by_id(file.id)
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(file.key, "a.mov.mp4");
+    }
+
+    async fn status(db: &sea_orm::DatabaseConnection, task_id: i32) -> (TaskStatus, i32) {
+        let task = VideoTranscodingManager::get_task_by_id(db, task_id)
+            .await
+            .unwrap()
+            .unwrap();
+        (task.status, task.attempts)
+    }
+
+    #[tokio::test]
+    async fn test_mark_task_dispatched() {
+        let db = test_db().await;
+        let file = insert_file(&db, "a.mov", None).await;
+        let task = VideoTranscodingManager::enqueue_task(&db, file.id)
+            .await
+            .unwrap();
+
+        assert!(
+            VideoTranscodingManager::mark_task_dispatched(&db, task.id, 1)
+                .await
+                .unwrap()
+        );
+        assert_eq!(status(&db, task.id).await, (TaskStatus::Dispatched, 1));
+
+        // Stale, dispatched again.
+        assert!(
+            VideoTranscodingManager::mark_task_dispatched(&db, task.id, 2)
+                .await
+                .unwrap()
+        );
+        assert_eq!(status(&db, task.id).await, (TaskStatus::Dispatched, 2));
+
+        // The callback came before the task was marked as dispatched.
+        VideoTranscodingManager::mark_task_completed(&db, task.id)
+            .await
+            .unwrap();
+        assert!(
+            !VideoTranscodingManager::mark_task_dispatched(&db, task.id, 3)
+                .await
+                .unwrap()
+        );
+        assert_eq!(status(&db, task.id).await, (TaskStatus::Completed, 2));
+    }
+
+    #[tokio::test]
+    async fn test_admin_actions() {
+        let db = test_db().await;
+        let file = insert_file(&db, "a.mov", None).await;
+        let task = VideoTranscodingManager::enqueue_task(&db, file.id)
+            .await
+            .unwrap();
+
+        // Pending
+        assert!(VideoTranscodingManager::reset_task(&db, task.id)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(
+            VideoTranscodingManager::cancel_task(&db, task.id, "Cancelled".to_string())
+                .await
+                .unwrap()
+        );
+
+        // Failed
+        assert!(
+            !VideoTranscodingManager::cancel_task(&db, task.id, "Cancelled".to_string())
+                .await
+                .unwrap()
+        );
+        let task = VideoTranscodingManager::reset_task(&db, task.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(task.status, TaskStatus::Pending);
+        assert!(VideoTranscodingManager::mark_task_completed(&db, task.id)
+            .await
+            .unwrap());
+
+        // Completed
+        assert!(!VideoTranscodingManager::mark_task_completed(&db, task.id)
+            .await
+            .unwrap());
+        assert!(
+            !VideoTranscodingManager::cancel_task(&db, task.id, "Cancelled".to_string())
+                .await
+                .unwrap()
+        );
+        assert!(VideoTranscodingManager::reset_task(&db, task.id)
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(status(&db, task.id).await, (TaskStatus::Completed, 0));
+    }
+
+    #[tokio::test]
+    async fn test_failure_after_cancel() {
+        let db = test_db().await;
+        let file = insert_file(&db, "a.mov", None).await;
+        let task = VideoTranscodingManager::enqueue_task(&db, file.id)
+            .await
+            .unwrap();
+        assert!(
+            VideoTranscodingManager::mark_task_retry(&db, task.id, 1, "Failed".to_string())
+                .await
+                .unwrap()
+                .is_some()
+        );
+
+        // Cancelled while the attempt was running.
+        VideoTranscodingManager::cancel_task(&db, task.id, "Cancelled".to_string())
+            .await
+            .unwrap();
+        assert!(
+            VideoTranscodingManager::mark_task_retry(&db, task.id, 2, "Failed".to_string())
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            !VideoTranscodingManager::mark_task_error(&db, task.id, 2, "Failed".to_string())
+                .await
+                .unwrap()
+        );
+        let task = VideoTranscodingManager::get_task_by_id(&db, task.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(task.status, TaskStatus::Failed);
+        assert_eq!(task.attempts, 1);
+        assert_eq!(task.detail, "Cancelled");
+    }
+
+    #[tokio::test]
+    async fn test_update_media_video() {
+        let db = test_db().await;
+        let video = insert_file(&db, "a.mov", None).await;
+        let thumbnail = insert_file(&db, "a_thumbnail.jpeg", None).await;
+        let probe = MediaProbe {
+            width: 1920,
+            height: 1080,
+            rotation: Some(90),
+            codec: Some("hevc".to_string()),
+            duration_s: Some(12.5),
+        };
+
+        // The media was deleted meanwhile.
+        assert!(!VideoTranscodingManager::media_uses_file(&db, video.id)
+            .await
+            .unwrap());
+        VideoTranscodingManager::update_media_video(&db, video.id, &probe, None)
+            .await
+            .unwrap();
+
+        let journal = insert_journal(&db, "lisbon").await;
+        let date = chrono::NaiveDate::from_ymd_opt(2024, 5, 3).unwrap();
+        let entry = insert_entry(&db, journal.id, None, date).await;
+        let media = insert_media(&db, entry.id, video.id, thumbnail.id).await;
+        assert!(VideoTranscodingManager::media_uses_file(&db, video.id)
+            .await
+            .unwrap());
+        VideoTranscodingManager::update_media_video(&db, video.id, &probe, None)
+            .await
+            .unwrap();
+        let media = entities::journal_entry_media::Entity::find_by_id(media.id)
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(media.duration_s, Some(12.5));
+        assert_eq!(media.rotation, Some(90));
+        assert_eq!(media.thumbnail_file_id, thumbnail.id);
+    }
+}
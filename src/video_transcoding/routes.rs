@@ -1,9 +1,18 @@
+use std::collections::HashMap;
+
 use axum::extract::Query;
 use axum::http::StatusCode;
-use axum::response::IntoResponse;
+use axum::response::{Html, IntoResponse, Response};
+use axum::Form;
+use minijinja::{context, Value};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
 use serde::{Deserialize, Serialize};
 
-use crate::{video_transcoding::manager::VideoTranscodingManager, AppState, RouteResult};
+use crate::{
+    video_transcoding::manager::VideoTranscodingManager, AppState, AuthSession, Route, RouteError,
+    RouteResult, Templ, Toast,
+};
+use entities::{prelude::*, video_transcode_task::TaskStatus, *};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct VideoTranscodeCallbackQuery {
@@ -25,9 +34,189 @@ pub async fn video_transcode_callback_post(
         }
     };
 
-    VideoTranscodingManager::update_file_key(&state.db, &state.storage, task.file_id, output_key)
+    // Stale tasks are dispatched again, so several jobs may call back for the same task.
+    if task.status == TaskStatus::Completed {
+        return Ok(StatusCode::OK.into_response());
+    }
+    let file = File::find_by_id(task.file_id).one(&state.db).await?;
+    if file.is_none_or(|file| file.key != output_key) {
+        VideoTranscodingManager::update_file_key(
+            &state.db,
+            &state.storage,
+            task.file_id,
+            output_key,
+        )
         .await?;
+    }
     VideoTranscodingManager::mark_task_completed(&state.db, task_id).await?;
 
     Ok(StatusCode::OK.into_response())
 }
+
+const TASK_LIST_LIMIT: u64 = 200;
+
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct VideoTranscodeTaskListQuery {
+    pub status: Option<TaskStatus>,
+}
+
+async fn query_and_render_task_list(
+    state: &AppState,
+    templ: &Templ,
+    status: Option<TaskStatus>,
+    partial: bool,
+) -> Result<Html<String>, RouteError> {
+    let tasks = VideoTranscodingManager::list_tasks(&state.db, status, TASK_LIST_LIMIT).await?;
+
+    // The entry each video belongs to, if it wasn't deleted.
+    let medias = JournalEntryMedia::find()
+        .filter(journal_entry_media::Column::FileId.is_in(tasks.iter().map(|t| t.file_id)))
+        .find_also_related(JournalEntry)
+        .all(&state.db)
+        .await?;
+    let journals: HashMap<i32, String> = Journal::find()
+        .all(&state.db)
+        .await?
+        .into_iter()
+        .map(|journal| (journal.id, journal.name))
+        .collect();
+    let entries: HashMap<i32, journal_entry::Model> = medias
+        .into_iter()
+        .filter_map(|(media, entry)| Some((media.file_id, entry?)))
+        .collect();
+
+    let tasks = tasks
+        .iter()
+        .map(|task| {
+            let entry = entries.get(&task.file_id).map(|entry| {
+                context! {
+                    title => entry.title,
+                    date => entry.date,
+                    journal_name => journals.get(&entry.journal_id),
+                    href => Route::JournalEntryEditGet { entry_id: Some(entry.id) }.as_path(),
+                }
+            });
+            context! { entry, ..Value::from_serialize(task) }
+        })
+        .collect::<Vec<_>>();
+
+    let filters = [
+        None,
+        Some(TaskStatus::Pending),
+        Some(TaskStatus::Dispatched),
+        Some(TaskStatus::Failed),
+        Some(TaskStatus::Completed),
+    ]
+    .into_iter()
+    .map(|filter| {
+        let query = VideoTranscodeTaskListQuery { status: filter };
+        let href = match filter {
+            Some(_) => Route::VideoTranscodeTaskListGet(Some(&query)).as_path(),
+            None => Route::VideoTranscodeTaskListGet(None).as_path(),
+        };
+        context! {
+            status => filter,
+            active => filter == status,
+            href,
+        }
+    })
+    .collect::<Vec<_>>();
+
+    let ctx = context! {
+        tasks,
+        status,
+        filters,
+        limit => TASK_LIST_LIMIT,
+        href_retry => Route::VideoTranscodeTaskRetryPost.as_path(),
+        href_cancel => Route::VideoTranscodeTaskCancelPost.as_path(),
+        href_complete => Route::VideoTranscodeTaskCompletePost.as_path(),
+        wide_layout => true,
+    };
+    templ.render_ctx_fragment(
+        "video_transcode_task_list.html",
+        ctx,
+        if partial {
+            Some("frag_task_list")
+        } else {
+            None
+        },
+    )
+}
+
+pub async fn video_transcode_task_list_get(
+    state: AppState,
+    templ: Templ,
+    Query(query): Query<VideoTranscodeTaskListQuery>,
+) -> RouteResult {
+    let html = query_and_render_task_list(&state, &templ, query.status, false).await?;
+    Ok(html.into_response())
+}
+
+#[derive(Deserialize, Debug)]
+pub struct VideoTranscodeTaskActionForm {
+    task_id: i32,
+    /// The list filter, to render the list again.
+    #[serde(default)]
+    status: Option<TaskStatus>,
+}
+
+pub async fn video_transcode_task_retry_post(
+    state: AppState,
+    templ: Templ,
+    form: Form<VideoTranscodeTaskActionForm>,
+) -> Result<Response, Toast> {
+    let r: RouteResult = async {
+        let Some(task) = VideoTranscodingManager::reset_task(&state.db, form.task_id).await? else {
+            return Ok(Toast::danger("Only failed tasks can be retried").into_response());
+        };
+        state.video_transcoder.process(vec![task]).await?;
+        let html = query_and_render_task_list(&state, &templ, form.status, true).await?;
+        let toast = Toast::success("Task has been queued");
+        let resp = (toast.into_headers(), html);
+        Ok(resp.into_response())
+    }
+    .await;
+    r.map_err(Toast::error)
+}
+
+pub async fn video_transcode_task_cancel_post(
+    state: AppState,
+    templ: Templ,
+    session: AuthSession,
+    form: Form<VideoTranscodeTaskActionForm>,
+) -> Result<Response, Toast> {
+    let r: RouteResult = async {
+        let user = &session.user.as_ref().expect("Should be authenticated").0;
+        let detail = format!("Cancelled by {}", user.email);
+        if !VideoTranscodingManager::cancel_task(&state.db, form.task_id, detail).await? {
+            return Ok(
+                Toast::danger("Only pending or dispatched tasks can be cancelled").into_response(),
+            );
+        }
+        let html = query_and_render_task_list(&state, &templ, form.status, true).await?;
+        let toast = Toast::success("Task has been cancelled");
+        let resp = (toast.into_headers(), html);
+        Ok(resp.into_response())
+    }
+    .await;
+    r.map_err(Toast::error)
+}
+
+/// For videos that play fine as they are, e.g. transcoded by hand.
+pub async fn video_transcode_task_complete_post(
+    state: AppState,
+    templ: Templ,
+    form: Form<VideoTranscodeTaskActionForm>,
+) -> Result<Response, Toast> {
+    let r: RouteResult = async {
+        if !VideoTranscodingManager::mark_task_completed(&state.db, form.task_id).await? {
+            return Ok(Toast::danger("Task is already completed").into_response());
+        }
+        let html = query_and_render_task_list(&state, &templ, form.status, true).await?;
+        let toast = Toast::success("Task has been marked as done");
+        let resp = (toast.into_headers(), html);
+        Ok(resp.into_response())
+    }
+    .await;
+    r.map_err(Toast::error)
+}
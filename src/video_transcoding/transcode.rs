@@ -1,6 +1,114 @@
 use std::{path::Path, process::Stdio};
 
-use anyhow::Context;
+use anyhow::{bail, Context};
+use serde::Deserialize;
+
+/// What ffprobe tells about the first video (or image) stream of a file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaProbe {
+    /// As stored, i.e. before rotation.
+    pub width: i32,
+    pub height: i32,
+    /// From the display matrix, in degrees, counterclockwise.
+    pub rotation: Option<i32>,
+    pub codec: Option<String>,
+    /// None for images.
+    pub duration_s: Option<f64>,
+}
+
+impl MediaProbe {
+    /// Whether the displayed width and height are swapped.
+    pub fn is_transposed(&self) -> bool {
+        self.rotation
+            .is_some_and(|rotation| rotation.abs() % 180 == 90)
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct ProbeOutput {
+    streams: Vec<ProbeStream>,
+    format: Option<ProbeFormat>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ProbeStream {
+    codec_name: Option<String>,
+    width: i32,
+    height: i32,
+    // ffprobe prints durations as strings.
+    duration: Option<String>,
+    #[serde(default)]
+    side_data_list: Vec<ProbeSideData>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ProbeSideData {
+    rotation: Option<i32>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ProbeFormat {
+    duration: Option<String>,
+}
+
+pub(crate) async fn run(program: &str, args: &[&str]) -> anyhow::Result<Vec<u8>> {
+    let output = tokio::process::Command::new(program)
+        .args(args)
+        .stderr(Stdio::piped())
+        .stdout(Stdio::piped())
+        .output()
+        .await
+        .with_context(|| format!("Failed to run {program}"))?;
+    if !output.status.success() {
+        bail!(
+            "{program} exited with status: {}\n# stderr: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    Ok(output.stdout)
+}
+
+pub async fn probe_media(input_path: &Path) -> anyhow::Result<MediaProbe> {
+    let output = run(
+        "ffprobe",
+        &[
+            "-v",
+            "error",
+            "-select_streams",
+            "v:0",
+            "-show_entries",
+            "stream=codec_name,width,height,duration:stream_side_data=rotation:format=duration",
+            "-of",
+            "json",
+            &input_path.to_string_lossy(),
+        ],
+    )
+    .await?;
+    parse_probe(&output).with_context(|| format!("Invalid ffprobe output for {input_path:?}"))
+}
+
+fn parse_probe(output: &[u8]) -> anyhow::Result<MediaProbe> {
+    let probe: ProbeOutput = serde_json::from_slice(output)?;
+    let stream = probe
+        .streams
+        .into_iter()
+        .next()
+        .context("No image or video stream")?;
+    // Images have no duration, though some formats report one anyway.
+    let duration_s = stream
+        .duration
+        .or(probe.format.and_then(|format| format.duration))
+        .and_then(|duration| duration.parse::<f64>().ok())
+        .filter(|duration| duration.is_finite() && *duration > 0.0);
+    Ok(MediaProbe {
+        width: stream.width,
+        height: stream.height,
+        rotation: stream.side_data_list.iter().find_map(|d| d.rotation),
+        codec: stream.codec_name,
+        duration_s,
+    })
+}
 
 /// Transcodes a video file to a universal format (H.264 video codec, AAC audio codec).
 /// We do this because user-uploaded videos can be in weird formats, e.g. some Android
@@ -55,6 +163,38 @@ pub async fn transcode_video(input_path: &Path, output_path: &Path) -> anyhow::R
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_probe() {
+        let output = br#"{
+            "programs": [],
+            "streams": [{
+                "codec_name": "hevc",
+                "width": 1920,
+                "height": 1080,
+                "duration": "12.345000",
+                "side_data_list": [{"rotation": -90}]
+            }],
+            "format": {"duration": "12.400000"}
+        }"#;
+        let probe = parse_probe(output).unwrap();
+        assert_eq!(
+            probe,
+            MediaProbe {
+                width: 1920,
+                height: 1080,
+                rotation: Some(-90),
+                codec: Some("hevc".to_string()),
+                duration_s: Some(12.345),
+            }
+        );
+        assert!(probe.is_transposed());
+
+        let output = br#"{"streams": [{"width": 4, "height": 3}], "format": {}}"#;
+        let probe = parse_probe(output).unwrap();
+        assert_eq!(probe.duration_s, None);
+        assert!(!probe.is_transposed());
+    }
+
     const ANDROID_VIDEO_PATH: &str = "test_data/video_transcoding/android_video.mp4";
 
     #[tokio::test]